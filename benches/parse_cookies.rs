@@ -0,0 +1,26 @@
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, Criterion};
+use bilibili_live_tool::BilibiliClient;
+
+/// 模拟浏览器导出的完整cookie，约数KB、几十个键
+fn browser_cookie_string() -> String {
+    let mut cookie_str = String::from(
+        "buvid3=ABCD-1234-5678infoc; b_nut=1700000000; _uuid=F1A2-B3C4-D5E6; \
+         SESSDATA=a1b2c3d4%2C1700000000%2Cabcd%2A42; bili_jct=0123456789abcdef0123456789abcdef; \
+         DedeUserID=42; DedeUserID__ckMd5=ffeeddccbbaa9988; sid=abc123",
+    );
+    for i in 0..60 {
+        cookie_str.push_str(&format!("; bp_video_offset_{}=9{:018}%2C{}", i, i, "x".repeat(32)));
+    }
+    cookie_str
+}
+
+fn bench_parse_cookies(c: &mut Criterion) {
+    let cookie_str = browser_cookie_string();
+    c.bench_function("parse_cookies", |b| {
+        b.iter(|| BilibiliClient::parse_cookies(black_box(&cookie_str)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_parse_cookies);
+criterion_main!(benches);
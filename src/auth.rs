@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::time::Duration;
-use tokio::time::sleep;
-use crate::client::{BilibiliClient, ApiResponse};
+use std::future::Future;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::time::{sleep, timeout};
+use tokio_util::sync::CancellationToken;
+use crate::client::{BilibiliClient, ApiResponse, DEFAULT_TIMEOUT_MS};
+use crate::config::Endpoints;
 use crate::qr::{QRCode, QRCodeData};
 use crate::error::{Result, BiliError};
 
@@ -23,25 +27,171 @@ pub struct LoginStatusData {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserInfo {
+    #[serde(alias = "mid")]
     pub uid: u64,
+    #[serde(alias = "roomid")]
     pub room_id: u64,
     pub csrf: String,
     pub cookies: HashMap<String, String>,
+    /// 用于刷新cookie的令牌，只有扫码登录时才会下发
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+/// 检查cookie是否需要刷新的接口返回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CookieInfoData {
+    pub refresh: bool,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CookieRefreshData {
+    refresh_token: String,
+}
+
+/// 刷新后的登录凭证
+#[derive(Debug, Clone)]
+pub struct RefreshedCookies {
+    pub cookies: HashMap<String, String>,
+    pub refresh_token: String,
+    /// 确认刷新失败的原因；新cookie仍然可用，只是旧的refresh_token可能没有失效
+    pub confirm_error: Option<String>,
+}
+
+const COOKIE_INFO_URL: &str = "https://passport.bilibili.com/x/passport-login/web/cookie/info";
+const COOKIE_REFRESH_URL: &str = "https://passport.bilibili.com/x/passport-login/web/cookie/refresh";
+const CONFIRM_REFRESH_URL: &str = "https://passport.bilibili.com/x/passport-login/web/confirm/refresh";
+const CORRESPOND_URL: &str = "https://www.bilibili.com/correspond/1";
+const CAPTCHA_PATH: &str = "/x/passport-login/captcha?source=main_web";
+const SMS_SEND_PATH: &str = "/x/passport-login/web/sms/send";
+const SMS_LOGIN_PATH: &str = "/x/passport-login/web/login/sms";
+const ROOM_ID_BY_UID_PATH: &str = "/room/v2/Room/room_id_by_uid";
+const NAV_PATH: &str = "/x/web-interface/nav";
+
+/// 中国大陆的国际冠字码
+pub const DEFAULT_COUNTRY_ID: u32 = 86;
+
+/// 计算CorrespondPath使用的B站公钥
+const CORRESPOND_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDLgd2OAkcGVtoE3ThUREbio0Eg
+Uc/prcajMKXvkCKFCWhJYJcLkcM2DKKcSeFpD/j6Boy538YXnR6VhcuUJOhH2x71
+nzPjfdTcqMz7djHum0qSZA0AyCBDABUqCrfNgCiJ00Ra7GmRj+YCK1NJEuewlb40
+JNrRuoEUXpabUzGB8QIDAQAB
+-----END PUBLIC KEY-----";
+
+/// 二维码失效后默认自动重新生成的次数
+pub const DEFAULT_QR_REFRESH_LIMIT: u32 = 3;
+
+/// 二维码失效时 `wait_for_login` 返回的错误信息
+const QR_EXPIRED_MESSAGE: &str = "二维码已失效，请重新生成";
+
+/// cookie文件中构造UserInfo必须的字段
+const COOKIE_FILE_REQUIRED: [&str; 2] = ["bili_jct", "DedeUserID"];
+
+/// 登录成功后必须拿到的cookie
+const REQUIRED_LOGIN_COOKIES: [&str; 3] = ["SESSDATA", "bili_jct", "DedeUserID"];
+
+/// 发送短信前需要完成的极验人机验证参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captcha {
+    pub token: String,
+    pub gt: String,
+    pub challenge: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CaptchaData {
+    token: String,
+    geetest: GeetestData,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeetestData {
+    gt: String,
+    challenge: String,
+}
+
+/// 用户完成极验后得到的结果
+#[derive(Debug, Clone)]
+pub struct CaptchaSolution {
+    pub token: String,
+    pub challenge: String,
+    pub validate: String,
+    pub seccode: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SmsSendData {
+    captcha_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SmsLoginData {
+    #[serde(default)]
+    status: i32,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+/// 二维码轮询的时间参数
+#[derive(Debug, Clone)]
+pub struct QrPollTiming {
+    /// 两次轮询之间的间隔
+    pub interval: Duration,
+    /// 单次轮询的超时时间，超时后跳过本次继续下一轮
+    pub poll_timeout: Duration,
+    /// 整个登录流程的最长等待时间
+    pub deadline: Duration,
+}
+
+impl Default for QrPollTiming {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(2),
+            poll_timeout: Duration::from_secs(5),
+            deadline: Duration::from_secs(180),
+        }
+    }
 }
 
 pub struct Auth {
     client: BilibiliClient,
+    /// 二维码失效后自动重新生成的最大次数
+    qr_refresh_limit: u32,
+    /// 扫码登录时同时用系统默认图片查看器打开二维码
+    open_qr_viewer: bool,
+    /// 短信登录和查询直播间号使用的服务地址
+    endpoints: Endpoints,
 }
 
 impl Auth {
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            client: BilibiliClient::new()?,
-        })
+        Ok(Self::with_client(BilibiliClient::new(DEFAULT_TIMEOUT_MS)?))
     }
     
     pub fn with_client(client: BilibiliClient) -> Self {
-        Self { client }
+        Self { client, qr_refresh_limit: DEFAULT_QR_REFRESH_LIMIT, open_qr_viewer: false, endpoints: Endpoints::default() }
+    }
+    
+    /// 使用配置中的服务地址
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+    
+    /// 设置二维码失效后自动重新生成的最大次数，0表示失效即失败
+    pub fn with_qr_refresh_limit(mut self, limit: u32) -> Self {
+        self.qr_refresh_limit = limit;
+        self
+    }
+    
+    /// 扫码登录时是否额外用系统默认图片查看器打开二维码，适合终端字符二维码无法识别的情况
+    pub fn with_qr_viewer(mut self, enabled: bool) -> Self {
+        self.open_qr_viewer = enabled;
+        self
     }
     
     /// 生成登录二维码
@@ -82,7 +232,7 @@ impl Auth {
         
         let json: ApiResponse<LoginStatusData> = response.json().await?;
         
-        let status_data = json.data.unwrap_or_else(|| LoginStatusData {
+        let status_data = json.data.unwrap_or(LoginStatusData {
             code: json.code,
             message: json.message,
             url: None,
@@ -95,66 +245,121 @@ impl Auth {
     
     /// 登录
     pub async fn qr_login(&self) -> Result<UserInfo> {
-        // 生成二维码
-        let qr_data = self.generate_qrcode().await?;
-        
-        println!("请扫描以下二维码登录:");
-        QRCode::print_unicode_to_terminal(&qr_data.url)?;
-        // println!("二维码链接: {}", qr_data.url);
-        println!("等待扫描二维码...");
-        
-        let mut login_cookies: Option<HashMap<String, String>> = None;
-        let mut last_status_code = -1; // 记录上次状态码，避免重复打印
-        
-        // 轮询登录状态
-        loop {
-            let (status, cookies) = self.check_login_status(&qr_data.qrcode_key).await?;
+        self.qr_login_with_cancel(&CancellationToken::new(), || {}).await
+    }
+    
+    /// 二维码登录，cancel被触发时尽快返回 `BiliError::General("已取消")`
+    ///
+    /// 第一张二维码打印完成、开始等待扫码时调用 on_waiting，可在此时开始监听取消按键。
+    /// 二维码失效时清掉旧的二维码并重新生成，最多 `qr_refresh_limit` 次。
+    pub async fn qr_login_with_cancel(&self, cancel: &CancellationToken, on_waiting: impl FnOnce()) -> Result<UserInfo> {
+        let mut on_waiting = Some(on_waiting);
+        let mut refreshes = 0;
+        // 查看器打开的临时图片，扫码结束或二维码刷新时删除
+        let mut _viewer_image = None;
+        let (cookies, refresh_token) = loop {
+            // 生成二维码
+            let qr_data = self.generate_qrcode().await?;
             
-            // 保存cookies
-            if let Some(cookies_dict) = cookies {
-                login_cookies = Some(cookies_dict);
+            if refreshes > 0 {
+                clear_terminal()?;
+                print_status(&format!("二维码已失效，已重新生成（{}/{}）", refreshes, self.qr_refresh_limit));
             }
-            
-            // 只有状态变化时才打印消息
-            if status.code != last_status_code {
-                match status.code {
-                    0 => {
-                        println!("登录成功!");
-                        break;
-                    }
-                    86038 => {
-                        return Err(BiliError::Login("二维码已失效，请重新生成".to_string()));
-                    }
-                    86090 => {
-                        println!("二维码已扫描，等待确认...");
-                    }
-                    86101 => {
-
+            print_status("请扫描以下二维码登录:");
+            QRCode::print_unicode_to_terminal(&qr_data.url)?;
+            if self.open_qr_viewer {
+                _viewer_image = match QRCode::open_in_viewer(&qr_data.url) {
+                    Ok(image) => Some(image),
+                    Err(e) => {
+                        print_status(&format!("{}，请扫描终端中的二维码", e));
+                        None
                     }
-                    _ => {
-                        return Err(BiliError::Login(format!("登录失败: {}", status.message)));
-                    }
-                }
-                last_status_code = status.code;
+                };
             }
-            
-            if status.code == 0 {
-                break;
+            print_status("等待扫描二维码...");
+            if let Some(on_waiting) = on_waiting.take() {
+                on_waiting();
             }
             
-            sleep(Duration::from_secs(2)).await;
-        }
-        
-        // 使用获取到的cookies
-        let cookies = login_cookies.ok_or_else(|| BiliError::Login("未获取到登录cookies".to_string()))?;
+            // 轮询登录状态
+            let result = wait_for_login(
+                || self.check_login_status(&qr_data.qrcode_key),
+                &QrPollTiming::default(),
+                cancel,
+            ).await;
+            match result {
+                Err(e) if is_qr_expired(&e) && refreshes < self.qr_refresh_limit => refreshes += 1,
+                result => break result?,
+            }
+        };
         
         // 获取用户信息
-        println!("正在获取用户信息...");
-        let user_info = self.get_user_info(&cookies).await?;
+        print_status("正在获取用户信息...");
+        let mut user_info = self.get_user_info(&cookies).await?;
+        user_info.refresh_token = refresh_token;
         
         Ok(user_info)
     }
     
+    /// 获取发送短信前需要完成的极验参数
+    pub async fn request_captcha(&self) -> Result<Captcha> {
+        let response: ApiResponse<CaptchaData> = self.client.get(&format!("{}{}", self.endpoints.passport, CAPTCHA_PATH)).await?;
+        let data = response.data.ok_or_else(|| BiliError::Login("获取人机验证参数失败".to_string()))?;
+        Ok(Captcha {
+            token: data.token,
+            gt: data.geetest.gt,
+            challenge: data.geetest.challenge,
+        })
+    }
+    
+    /// 发送短信验证码，返回登录时需要的captcha_key
+    ///
+    /// 接口要求先通过 [`Auth::request_captcha`] 获取并完成极验验证。
+    pub async fn send_sms_code(&self, tel: &str, cid: u32, captcha: &CaptchaSolution) -> Result<String> {
+        check_tel(tel)?;
+        let cid = cid.to_string();
+        let url = format!("{}{}", self.endpoints.passport, SMS_SEND_PATH);
+        let response: ApiResponse<SmsSendData> = self.client.post(&url, &[
+            ("cid", cid.as_str()),
+            ("tel", tel),
+            ("source", "main_web"),
+            ("token", captcha.token.as_str()),
+            ("challenge", captcha.challenge.as_str()),
+            ("validate", captcha.validate.as_str()),
+            ("seccode", captcha.seccode.as_str()),
+        ]).await?;
+        let data = response.data.ok_or_else(|| BiliError::Login("发送短信验证码失败".to_string()))?;
+        Ok(data.captcha_key)
+    }
+    
+    /// 短信验证码登录，成功后和扫码登录一样组装出UserInfo
+    pub async fn sms_login(&self, tel: &str, cid: u32, code: &str, captcha_key: &str) -> Result<UserInfo> {
+        check_tel(tel)?;
+        if code.trim().is_empty() {
+            return Err(BiliError::Validation("验证码不能为空".to_string()));
+        }
+        let cid = cid.to_string();
+        let url = format!("{}{}", self.endpoints.passport, SMS_LOGIN_PATH);
+        let response: ApiResponse<SmsLoginData> = self.client.post(&url, &[
+            ("cid", cid.as_str()),
+            ("tel", tel),
+            ("code", code.trim()),
+            ("source", "main_web"),
+            ("captcha_key", captcha_key),
+            ("keep", "true"),
+        ]).await?;
+        let data = response.data.ok_or_else(|| BiliError::Login("短信登录失败".to_string()))?;
+        if data.status != 0 {
+            return Err(BiliError::Login(format!("短信登录需要额外验证: {}", data.message)));
+        }
+        
+        // 登录cookie由响应的Set-Cookie写入客户端的jar
+        let cookies = collect_login_cookies(|name| self.client.get_cookie(name))?;
+        let mut user_info = self.get_user_info(&cookies).await?;
+        user_info.refresh_token = data.refresh_token;
+        Ok(user_info)
+    }
+    
     /// 获取用户信息
     pub async fn get_user_info(&self, cookies: &HashMap<String, String>) -> Result<UserInfo> {
         let dede_user_id = cookies.get("DedeUserID")
@@ -174,12 +379,13 @@ impl Auth {
             room_id,
             csrf: csrf.clone(),
             cookies: cookies.clone(),
+            refresh_token: None,
         })
     }
     
     /// 根据用户ID获取直播间ID
     pub async fn get_room_id(&self, uid: u64) -> Result<u64> {
-        let url = format!("https://api.live.bilibili.com/room/v2/Room/room_id_by_uid?uid={}", uid);
+        let url = format!("{}{}?uid={}", self.endpoints.live_api, ROOM_ID_BY_UID_PATH, uid);
         
         #[derive(Deserialize)]
         struct RoomIdData {
@@ -192,6 +398,22 @@ impl Auth {
         Ok(data.room_id)
     }
     
+    /// 从浏览器导出的cookie文件登录，支持Netscape cookies.txt和 `k=v; k=v` 字符串
+    pub async fn from_cookie_file(&self, path: impl AsRef<Path>) -> Result<UserInfo> {
+        let content = tokio::fs::read_to_string(path.as_ref()).await?;
+        let cookies = parse_cookie_file(&content)?;
+        
+        let missing: Vec<_> = COOKIE_FILE_REQUIRED.iter()
+            .copied()
+            .filter(|name| !cookies.contains_key(*name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(BiliError::Auth(format!("cookie文件缺少必要字段: {}", missing.join(", "))));
+        }
+        
+        self.get_user_info(&cookies).await
+    }
+    
     /// 从cookie字符串解析cookies
     pub fn parse_cookie_string(cookie_str: &str) -> Result<HashMap<String, String>> {
         BilibiliClient::parse_cookies(cookie_str)
@@ -206,28 +428,505 @@ impl Auth {
     }
     
     /// 验证cookies是否有效
+    ///
+    /// 确认未登录时返回`Ok(false)`，网络异常等无法判断的情况返回错误。
     pub async fn validate_cookies(&self, cookies: &HashMap<String, String>) -> Result<bool> {
-        let client = BilibiliClient::with_cookies(&Self::cookies_to_string(cookies))?;
+        let client = self.client.with_session_cookies(cookies)?;
         
         // 尝试获取用户信息来验证cookies
-        let result = client.get::<serde_json::Value>("https://api.bilibili.com/x/web-interface/nav").await;
+        let result = client.get::<serde_json::Value>(&format!("{}{}", self.endpoints.main_api, NAV_PATH)).await;
         
         match result {
             Ok(response) => {
-                if let Some(data) = response.data {
-                    if let Some(is_login) = data.get("isLogin") {
-                        return Ok(is_login.as_bool().unwrap_or(false));
-                    }
+                if let Some(data) = response.data
+                    && let Some(is_login) = data.get("isLogin")
+                {
+                    return Ok(is_login.as_bool().unwrap_or(false));
                 }
                 Ok(false)
             }
-            Err(_) => Ok(false),
+            Err(e) if e.is_auth_error() => Ok(false),
+            Err(e) => Err(e),
         }
     }
+    
+    /// 检查cookie是否需要刷新
+    pub async fn check_cookie_refresh(&self, cookies: &HashMap<String, String>) -> Result<CookieInfoData> {
+        let csrf = cookies.get("bili_jct").map(String::as_str).unwrap_or_default();
+        let client = self.client.with_session_cookies(cookies)?;
+        
+        let url = format!("{}?csrf={}", COOKIE_INFO_URL, csrf);
+        let response: ApiResponse<CookieInfoData> = client.get(&url).await?;
+        if !response.is_success() {
+            return Err(BiliError::api_error(response.code, response.get_message()));
+        }
+        response.data.ok_or_else(|| BiliError::auth("获取cookie刷新状态失败"))
+    }
+    
+    /// 用refresh_token换取新的cookie，返回合并后的cookies和新的refresh_token
+    ///
+    /// 刷新成功后会确认更新，使旧的refresh_token失效；确认失败只记录日志。
+    pub async fn refresh_cookie(&self, cookies: &HashMap<String, String>, refresh_token: &str) -> Result<RefreshedCookies> {
+        let csrf = cookies.get("bili_jct")
+            .ok_or_else(|| BiliError::auth("未找到CSRF token"))?;
+        let client = self.client.with_session_cookies(cookies)?;
+        
+        // 通过CorrespondPath获取refresh_csrf
+        let path = correspond_path(chrono::Utc::now().timestamp_millis())?;
+        let html = client.get_client()
+            .get(format!("{}/{}", CORRESPOND_URL, path))
+            .send()
+            .await?
+            .text()
+            .await?;
+        let refresh_csrf = parse_refresh_csrf(&html)
+            .ok_or_else(|| BiliError::auth("获取refresh_csrf失败"))?;
+        
+        let response = client.get_client()
+            .post(COOKIE_REFRESH_URL)
+            .headers(BilibiliClient::get_default_headers())
+            .form(&[
+                ("csrf", csrf.as_str()),
+                ("refresh_csrf", refresh_csrf.as_str()),
+                ("source", "main_web"),
+                ("refresh_token", refresh_token),
+            ])
+            .send()
+            .await?;
+        
+        // 新cookie通过Set-Cookie下发，覆盖旧值
+        let mut new_cookies = cookies.clone();
+        for cookie in response.cookies() {
+            new_cookies.insert(cookie.name().to_string(), cookie.value().to_string());
+        }
+        
+        let json: ApiResponse<CookieRefreshData> = response.json().await?;
+        if !json.is_success() {
+            return Err(BiliError::api_error(json.code, json.get_message()));
+        }
+        let data = json.data.ok_or_else(|| BiliError::auth("刷新cookie失败"))?;
+        
+        // 确认刷新，使旧的refresh_token失效
+        let new_csrf = new_cookies.get("bili_jct").cloned().unwrap_or_default();
+        let confirm_client = self.client.with_session_cookies(&new_cookies)?;
+        let confirm: Result<ApiResponse<serde_json::Value>> = confirm_client
+            .post(CONFIRM_REFRESH_URL, &[("csrf", new_csrf.as_str()), ("refresh_token", refresh_token)])
+            .await;
+        
+        Ok(RefreshedCookies {
+            cookies: new_cookies,
+            refresh_token: data.refresh_token,
+            confirm_error: confirm.err().map(|e| e.context("确认刷新cookie").to_string()),
+        })
+    }
+}
+
+/// 用B站公钥对 `refresh_{timestamp}` 做RSA-OAEP(SHA-256)加密，得到CorrespondPath
+pub fn correspond_path(timestamp: i64) -> Result<String> {
+    use rsa::{Oaep, RsaPublicKey, pkcs8::DecodePublicKey};
+    
+    let key = RsaPublicKey::from_public_key_pem(CORRESPOND_PUBLIC_KEY)
+        .map_err(|e| BiliError::internal(format!("解析公钥失败: {}", e)))?;
+    let message = format!("refresh_{}", timestamp);
+    let encrypted = key.encrypt(&mut rand::thread_rng(), Oaep::new::<sha2::Sha256>(), message.as_bytes())
+        .map_err(|e| BiliError::internal(format!("计算CorrespondPath失败: {}", e)))?;
+    Ok(hex::encode(encrypted))
+}
+
+/// 从correspond页面中提取refresh_csrf
+fn parse_refresh_csrf(html: &str) -> Option<String> {
+    static REFRESH_CSRF_RE: std::sync::LazyLock<regex::Regex> =
+        std::sync::LazyLock::new(|| regex::Regex::new(r#"<div id="1-name">([^<]+)</div>"#).unwrap());
+    REFRESH_CSRF_RE.captures(html)
+        .map(|cap| cap[1].trim().to_string())
+        .filter(|csrf| !csrf.is_empty())
 }
 
 impl Default for Auth {
     fn default() -> Self {
         Self::new().unwrap()
     }
-} 
\ No newline at end of file
+}
+
+/// 轮询二维码登录状态直到登录成功，返回登录cookies和refresh_token
+///
+/// 单次轮询卡住时只记录并进入下一轮，直到超过整体截止时间才报错。
+pub async fn wait_for_login<F, Fut>(mut poll: F, timing: &QrPollTiming, cancel: &CancellationToken) -> Result<(HashMap<String, String>, Option<String>)>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(LoginStatusData, Option<HashMap<String, String>>)>>,
+{
+    let started = Instant::now();
+    let mut login_cookies: HashMap<String, String> = HashMap::new();
+    let mut refresh_token = None;
+    let mut last_status_code = -1; // 记录上次状态码，避免重复打印
+    
+    loop {
+        if cancel.is_cancelled() {
+            return Err(BiliError::general("已取消"));
+        }
+        if started.elapsed() >= timing.deadline {
+            return Err(BiliError::timeout("等待扫码登录超时"));
+        }
+        
+        let polled = tokio::select! {
+            _ = cancel.cancelled() => return Err(BiliError::general("已取消")),
+            polled = timeout(timing.poll_timeout, poll()) => polled,
+        };
+        let (status, cookies) = match polled {
+            Ok(result) => result?,
+            Err(_) => {
+                print_status("查询登录状态超时，继续等待...");
+                continue;
+            }
+        };
+        
+        // 累积cookies，扫码确认前的轮询也可能已经下发部分cookie
+        if let Some(cookies_dict) = cookies {
+            login_cookies.extend(cookies_dict);
+        }
+        
+        if status.code == 0 {
+            refresh_token = status.refresh_token.clone().filter(|token| !token.is_empty());
+        }
+        
+        // 只有状态变化时才打印消息
+        if status.code != last_status_code {
+            match status.code {
+                0 => {
+                    print_status("登录成功!");
+                    break;
+                }
+                86038 => {
+                    return Err(BiliError::Login(QR_EXPIRED_MESSAGE.to_string()));
+                }
+                86090 => {
+                    print_status("二维码已扫描，等待确认...");
+                }
+                86101 => {
+
+                }
+                _ => {
+                    return Err(BiliError::Login(format!("登录失败: {}", status.message)));
+                }
+            }
+            last_status_code = status.code;
+        }
+        
+        if status.code == 0 {
+            break;
+        }
+        
+        tokio::select! {
+            _ = cancel.cancelled() => return Err(BiliError::general("已取消")),
+            _ = sleep(timing.interval) => {}
+        }
+    }
+    
+    // 登录成功的响应可能没有带上Set-Cookie，缺少必要cookie时再轮询一次补齐
+    if !missing_login_cookies(&login_cookies).is_empty() {
+        sleep(timing.interval).await;
+        if let Ok(Ok((_, Some(cookies)))) = timeout(timing.poll_timeout, poll()).await {
+            login_cookies.extend(cookies);
+        }
+    }
+    
+    let missing = missing_login_cookies(&login_cookies);
+    if !missing.is_empty() {
+        return Err(BiliError::Login(format!("未获取到登录cookies: {}", missing.join(", "))));
+    }
+    Ok((login_cookies, refresh_token))
+}
+
+/// 解析cookie文件内容
+///
+/// 含有制表符分隔的7列时按Netscape cookies.txt解析，只保留bilibili.com的cookie；
+/// 否则把每行当作 `k=v; k=v` 字符串。
+fn parse_cookie_file(content: &str) -> Result<HashMap<String, String>> {
+    let netscape: Vec<Vec<&str>> = content.lines()
+        .map(|line| line.strip_prefix("#HttpOnly_").unwrap_or(line))
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.trim_end_matches('\r').split('\t').collect::<Vec<_>>())
+        .filter(|fields| fields.len() == 7)
+        .collect();
+    
+    if netscape.is_empty() {
+        return BilibiliClient::parse_cookies(&content.lines().collect::<Vec<_>>().join(";"));
+    }
+    // 和cookie字符串一样把值做URL解码
+    netscape.into_iter()
+        .filter(|fields| fields[0].trim_start_matches('.').ends_with("bilibili.com"))
+        .map(|fields| {
+            let value = urlencoding::decode(fields[6])
+                .map_err(|e| BiliError::General(format!("解析cookie失败: {}", e)))?;
+            Ok((fields[5].to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// 手机号只能是数字
+fn check_tel(tel: &str) -> Result<()> {
+    if tel.is_empty() || !tel.chars().all(|c| c.is_ascii_digit()) {
+        return Err(BiliError::Validation(format!("手机号格式错误: {}", tel)));
+    }
+    Ok(())
+}
+
+/// 收集登录必须的cookie，缺少任意一个时返回错误
+fn collect_login_cookies(lookup: impl Fn(&str) -> Option<String>) -> Result<HashMap<String, String>> {
+    let mut cookies = HashMap::new();
+    for name in REQUIRED_LOGIN_COOKIES {
+        let value = lookup(name)
+            .ok_or_else(|| BiliError::Login(format!("登录响应中缺少cookie: {}", name)))?;
+        cookies.insert(name.to_string(), value);
+    }
+    Ok(cookies)
+}
+
+/// 是否为二维码失效导致的登录失败
+fn is_qr_expired(error: &BiliError) -> bool {
+    matches!(error, BiliError::Login(message) if message == QR_EXPIRED_MESSAGE)
+}
+
+/// 清屏并把光标移到左上角，避免重新生成的二维码和旧的堆叠在一起
+fn clear_terminal() -> Result<()> {
+    use crossterm::{cursor::MoveTo, execute, terminal::{Clear, ClearType}};
+    execute!(std::io::stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
+    Ok(())
+}
+
+/// 返回缺少的必要登录cookie
+fn missing_login_cookies(cookies: &HashMap<String, String>) -> Vec<&'static str> {
+    REQUIRED_LOGIN_COOKIES.iter()
+        .copied()
+        .filter(|name| !cookies.contains_key(*name))
+        .collect()
+}
+
+/// 等待扫码期间终端可能处于raw模式，显式输出\r保证换行正常
+fn print_status(message: &str) {
+    print!("{}\r\n", message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve_once;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_user_info_accepts_both_field_spellings() {
+        for json in [
+            r#"{"uid": 42, "room_id": 123456, "csrf": "c", "cookies": {}}"#,
+            r#"{"mid": 42, "roomid": 123456, "csrf": "c", "cookies": {}}"#,
+        ] {
+            let info: UserInfo = serde_json::from_str(json).unwrap();
+            assert_eq!(info.uid, 42);
+            assert_eq!(info.room_id, 123456);
+        }
+    }
+
+    fn login_cookies() -> HashMap<String, String> {
+        HashMap::from([
+            ("SESSDATA".to_string(), "abc".to_string()),
+            ("bili_jct".to_string(), "csrf".to_string()),
+            ("DedeUserID".to_string(), "42".to_string()),
+        ])
+    }
+
+    fn status(code: i32) -> LoginStatusData {
+        LoginStatusData {
+            code,
+            message: String::new(),
+            url: None,
+            refresh_token: None,
+            timestamp: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stalled_poll_is_skipped() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let timing = QrPollTiming {
+            interval: Duration::from_millis(10),
+            poll_timeout: Duration::from_millis(50),
+            deadline: Duration::from_secs(5),
+        };
+        
+        let counter = calls.clone();
+        let (cookies, _) = wait_for_login(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if n == 0 {
+                    // 第一次轮询永远不返回
+                    std::future::pending::<()>().await;
+                }
+                Ok((status(0), Some(login_cookies())))
+            }
+        }, &timing, &CancellationToken::new()).await.unwrap();
+        
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cookies.get("SESSDATA").map(String::as_str), Some("abc"));
+    }
+
+    #[tokio::test]
+    async fn test_extra_poll_when_success_lacks_cookies() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let timing = QrPollTiming {
+            interval: Duration::from_millis(10),
+            poll_timeout: Duration::from_secs(1),
+            deadline: Duration::from_secs(5),
+        };
+        
+        let counter = calls.clone();
+        let (cookies, _) = wait_for_login(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match n {
+                    // 扫码后先下发了SESSDATA，确认登录的响应没有带cookie
+                    0 => Ok((status(86090), Some(HashMap::from([("SESSDATA".to_string(), "abc".to_string())])))),
+                    1 => Ok((status(0), None)),
+                    _ => Ok((status(0), Some(login_cookies()))),
+                }
+            }
+        }, &timing, &CancellationToken::new()).await.unwrap();
+        
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(cookies, login_cookies());
+    }
+
+    #[test]
+    fn test_correspond_path_and_refresh_csrf() {
+        // 1024位公钥加密结果为128字节
+        let path = correspond_path(1684466082130).unwrap();
+        assert_eq!(path.len(), 256);
+        assert!(path.chars().all(|c| c.is_ascii_hexdigit()));
+        
+        let html = r#"<div id="1-name">b0cc8411ded2f9db2cff2edb3123acac</div><div id="2-name"></div>"#;
+        assert_eq!(parse_refresh_csrf(html).as_deref(), Some("b0cc8411ded2f9db2cff2edb3123acac"));
+        assert_eq!(parse_refresh_csrf("<html></html>"), None);
+    }
+
+    #[tokio::test]
+    async fn test_expired_qrcode_is_recognized() {
+        let timing = QrPollTiming {
+            interval: Duration::from_millis(10),
+            poll_timeout: Duration::from_secs(1),
+            deadline: Duration::from_secs(5),
+        };
+        
+        let result = wait_for_login(|| async { Ok((status(86038), None)) }, &timing, &CancellationToken::new()).await;
+        assert!(is_qr_expired(&result.unwrap_err()));
+        
+        let result = wait_for_login(|| async { Ok((status(-1), None)) }, &timing, &CancellationToken::new()).await;
+        assert!(!is_qr_expired(&result.unwrap_err()));
+    }
+
+    #[tokio::test]
+    async fn test_sms_login_inputs_and_cookies() {
+        let data: CaptchaData = serde_json::from_str(
+            r#"{"type":"geetest","token":"tok","geetest":{"challenge":"chal","gt":"gt"}}"#,
+        ).unwrap();
+        assert_eq!((data.token.as_str(), data.geetest.gt.as_str(), data.geetest.challenge.as_str()), ("tok", "gt", "chal"));
+
+        let auth = Auth::new().unwrap();
+        let err = auth.sms_login("138-0000", DEFAULT_COUNTRY_ID, "123456", "key").await.unwrap_err();
+        assert!(matches!(err, BiliError::Validation(_)));
+        assert!(auth.sms_login("13800000000", DEFAULT_COUNTRY_ID, " ", "key").await.is_err());
+
+        let jar = HashMap::from([("SESSDATA", "s"), ("bili_jct", "j"), ("DedeUserID", "42")]);
+        let cookies = collect_login_cookies(|name| jar.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(cookies["DedeUserID"], "42");
+        let err = collect_login_cookies(|name| (name != "bili_jct").then(|| "v".to_string())).unwrap_err();
+        assert!(err.to_string().contains("bili_jct"));
+    }
+
+    #[tokio::test]
+    async fn test_sms_send_and_login_requests() {
+        let (passport, sent) = serve_once(r#"{"code":0,"message":"0","data":{"captcha_key":"ckey"}}"#).await;
+        let auth = Auth::new().unwrap().with_endpoints(Endpoints { passport, ..Endpoints::default() });
+        let solution = CaptchaSolution {
+            token: "tok".to_string(),
+            challenge: "chal".to_string(),
+            validate: "val".to_string(),
+            seccode: "sec".to_string(),
+        };
+        assert_eq!(auth.send_sms_code("13800000000", DEFAULT_COUNTRY_ID, &solution).await.unwrap(), "ckey");
+        let sent = sent.await.unwrap();
+        assert!(sent.head.starts_with(&format!("POST {}", SMS_SEND_PATH)));
+        assert!(sent.body.contains("tel=13800000000") && sent.body.contains("token=tok"));
+        assert!(!sent.body.contains("csrf"));
+
+        // 登录cookie本应由响应的Set-Cookie写入，这里预先放进jar
+        let (passport, login) = serve_once(r#"{"code":0,"message":"0","data":{"status":0,"message":"","refresh_token":"rt"}}"#).await;
+        let (live_api, room) = serve_once(r#"{"code":0,"message":"0","data":{"room_id":654321}}"#).await;
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+        client.add_cookies(&HashMap::from([
+            ("SESSDATA".to_string(), "s".to_string()),
+            ("bili_jct".to_string(), "j".to_string()),
+            ("DedeUserID".to_string(), "42".to_string()),
+        ]));
+        let auth = Auth::with_client(client).with_endpoints(Endpoints { passport, live_api, ..Endpoints::default() });
+        let user_info = auth.sms_login("13800000000", DEFAULT_COUNTRY_ID, " 123456 ", "ckey").await.unwrap();
+        assert_eq!((user_info.uid, user_info.room_id, user_info.csrf.as_str()), (42, 654321, "j"));
+        assert_eq!(user_info.refresh_token.as_deref(), Some("rt"));
+        let login = login.await.unwrap();
+        assert!(login.head.starts_with(&format!("POST {}", SMS_LOGIN_PATH)));
+        assert!(login.body.contains("code=123456") && login.body.contains("captcha_key=ckey"));
+        assert!(room.await.unwrap().head.starts_with(&format!("GET {}?uid=42", ROOM_ID_BY_UID_PATH)));
+    }
+
+    #[test]
+    fn test_parse_cookie_file_formats() {
+        let netscape = "# Netscape HTTP Cookie File\n\
+            .bilibili.com\tTRUE\t/\tFALSE\t1767225600\tDedeUserID\t42\n\
+            #HttpOnly_.bilibili.com\tTRUE\t/\tTRUE\t1767225600\tSESSDATA\tabc%2C123\n\
+            .bilibili.com\tTRUE\t/\tFALSE\t1767225600\tbili_jct\tcsrf\n\
+            .example.com\tTRUE\t/\tFALSE\t1767225600\tother\tx\n";
+        let cookies = parse_cookie_file(netscape).unwrap();
+        assert_eq!(cookies.get("DedeUserID").map(String::as_str), Some("42"));
+        assert_eq!(cookies.get("SESSDATA").map(String::as_str), Some("abc,123"));
+        assert!(!cookies.contains_key("other"));
+        
+        let cookies = parse_cookie_file("SESSDATA=abc; bili_jct=csrf\nDedeUserID=42\n").unwrap();
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies.get("bili_jct").map(String::as_str), Some("csrf"));
+    }
+
+    #[tokio::test]
+    async fn test_cookie_file_missing_fields() {
+        let path = std::env::temp_dir().join(format!("bilibili_live_tool_cookies_{}.txt", std::process::id()));
+        std::fs::write(&path, "SESSDATA=abc; DedeUserID=42").unwrap();
+        
+        let err = Auth::new().unwrap().from_cookie_file(&path).await.unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(err, BiliError::Auth(ref msg) if msg.contains("bili_jct") && !msg.contains("DedeUserID")));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_between_polls() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cancel = CancellationToken::new();
+        let timing = QrPollTiming {
+            interval: Duration::from_secs(60),
+            poll_timeout: Duration::from_secs(5),
+            deadline: Duration::from_secs(180),
+        };
+        
+        let counter = calls.clone();
+        let trigger = cancel.clone();
+        let result = wait_for_login(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let trigger = trigger.clone();
+            async move {
+                // 第一次轮询返回"未扫码"后用户按下Esc
+                trigger.cancel();
+                Ok((status(86101), None))
+            }
+        }, &timing, &cancel).await;
+        
+        assert!(matches!(result, Err(BiliError::General(ref msg)) if msg == "已取消"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, OnceCell};
 use crate::client::{BilibiliClient, ApiResponse};
+use crate::config::Config;
+use crate::danmaku::{self, DanmakuInfo, DanmakuMessage};
 use crate::error::{Result, BiliError};
+use crate::util::{json_path, json_u64};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BulletData {
@@ -22,20 +27,333 @@ pub struct BulletResponse {
     pub data: Option<serde_json::Value>,
 }
 
+impl From<ApiResponse<serde_json::Value>> for BulletResponse {
+    fn from(response: ApiResponse<serde_json::Value>) -> Self {
+        Self {
+            code: response.code,
+            msg: response.msg.unwrap_or_default(),
+            message: response.message,
+            data: response.data,
+        }
+    }
+}
+
+/// 发送成功但内容被屏蔽时返回的msg，弹幕只有自己可见
+const SHIELDED_MESSAGES: [&str; 2] = ["f", "k"];
+
+/// 弹幕发送结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentBullet {
+    /// 实际显示的弹幕内容，被屏蔽替换时与发送的内容不同
+    pub text: String,
+    /// 是否有部分内容被屏蔽或替换
+    pub modified: bool,
+}
+
+impl SentBullet {
+    /// 服务端原样接受的弹幕
+    pub fn unchanged(text: impl Into<String>) -> Self {
+        Self { text: text.into(), modified: false }
+    }
+
+    /// 展示给用户的提示
+    pub fn summary(&self) -> String {
+        if self.modified {
+            format!("发送成功，但部分内容被屏蔽: {}", self.text)
+        } else {
+            "发送成功".to_string()
+        }
+    }
+}
+
+/// 礼物记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GiftRecord {
+    #[serde(rename = "uname", alias = "name")]
+    pub sender: String,
+    #[serde(default)]
+    pub uid: u64,
+    pub gift_name: String,
+    #[serde(rename = "gift_num")]
+    pub count: u32,
+    /// 礼物价值（金瓜子）
+    #[serde(rename = "gold", default)]
+    pub value: u64,
+    #[serde(default)]
+    pub time: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GiftListData {
+    #[serde(default)]
+    pub list: Option<Vec<GiftRecord>>,
+    #[serde(default)]
+    pub has_more: i32,
+}
+
+/// 在线榜记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankRecord {
+    #[serde(rename = "userRank")]
+    pub rank: u32,
+    pub uid: u64,
+    #[serde(alias = "uname")]
+    pub name: String,
+    #[serde(default)]
+    pub score: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OnlineRankData {
+    #[serde(rename = "onlineNum", default)]
+    pub online_num: u64,
+    #[serde(rename = "OnlineRankItem", default)]
+    pub items: Option<Vec<RankRecord>>,
+}
+
+/// 直播间可用的单个表情
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Emoticon {
+    /// 显示名，如 `[dog]`
+    pub emoji: String,
+    pub descript: String,
+    /// 发送表情弹幕时作为msg的key，如 `official_147`
+    pub emoticon_unique: String,
+    /// 1 表示当前用户可用
+    pub perm: i32,
+}
+
+impl Emoticon {
+    /// 按显示名（可省略方括号）、描述或key匹配
+    pub fn matches(&self, name: &str) -> bool {
+        let bare = |s: &str| s.trim_start_matches('[').trim_end_matches(']').to_string();
+        !name.is_empty()
+            && (self.emoticon_unique == name || self.descript == name || bare(&self.emoji) == bare(name))
+    }
+}
+
+/// 表情包
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmoticonPackage {
+    pub pkg_name: String,
+    /// 1 表示当前用户可用
+    pub pkg_perm: i32,
+    pub emoticons: Vec<Emoticon>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmoticonListData {
+    pub data: Vec<EmoticonPackage>,
+}
+
+/// 在直播间的表情包中按名称查找可用的表情
+///
+/// 直播间未开通该表情、或当前用户未解锁所在表情包时返回错误。
+pub fn find_emoticon<'a>(packages: &'a [EmoticonPackage], name: &str) -> Result<&'a Emoticon> {
+    let (package, emoticon) = packages.iter()
+        .flat_map(|package| package.emoticons.iter().map(move |emoticon| (package, emoticon)))
+        .find(|(_, emoticon)| emoticon.matches(name))
+        .ok_or_else(|| BiliError::Bullet(format!("直播间未开通表情: {}", name)))?;
+    if package.pkg_perm != 1 || emoticon.perm != 1 {
+        return Err(BiliError::Bullet(format!("表情未解锁: {}（{}）", name, package.pkg_name)));
+    }
+    Ok(emoticon)
+}
+
+/// 弹幕配置中的单个颜色
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanmakuColor {
+    #[serde(default)]
+    pub name: String,
+    pub color: u32,
+    /// 1 表示当前用户可用
+    #[serde(default)]
+    pub status: i32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DanmakuColorGroup {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub color: Vec<DanmakuColor>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DanmakuConfigData {
+    #[serde(default)]
+    pub group: Vec<DanmakuColorGroup>,
+    #[serde(default)]
+    pub fontsize: Vec<u32>,
+}
+
+/// 当前用户在该直播间可用的弹幕颜色和字号
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DanmakuAllowance {
+    pub colors: Vec<u32>,
+    /// 为空表示接口未限制字号
+    pub fontsizes: Vec<u32>,
+}
+
+impl DanmakuAllowance {
+    pub fn from_config(data: &DanmakuConfigData) -> Self {
+        let mut colors: Vec<u32> = data.group.iter()
+            .flat_map(|group| &group.color)
+            .filter(|color| color.status == 1)
+            .map(|color| color.color)
+            .collect();
+        colors.dedup();
+
+        Self {
+            colors,
+            fontsizes: data.fontsize.clone(),
+        }
+    }
+
+    /// 检查颜色和字号是否可用，未指定的选项使用默认值不做检查
+    pub fn check(&self, color: Option<u32>, fontsize: Option<u32>) -> Result<()> {
+        if let Some(color) = color
+            && !self.colors.contains(&color)
+        {
+            let allowed: Vec<String> = self.colors.iter().map(|c| format!("#{:06X}", c)).collect();
+            return Err(BiliError::validation(format!("当前等级不支持该颜色，可用颜色: {}", allowed.join(", "))));
+        }
+
+        if let Some(fontsize) = fontsize
+            && !self.fontsizes.is_empty()
+            && !self.fontsizes.contains(&fontsize)
+        {
+            let allowed: Vec<String> = self.fontsizes.iter().map(u32::to_string).collect();
+            return Err(BiliError::validation(format!("当前等级不支持该字号，可用字号: {}", allowed.join(", "))));
+        }
+
+        Ok(())
+    }
+}
+
+/// 遇到致命错误即停止的批量发送结果
+#[derive(Debug)]
+pub struct FailFastResults {
+    /// 致命错误之前已发送的弹幕及结果
+    pub results: Vec<(String, Result<SentBullet>)>,
+    /// 导致中止的弹幕及错误
+    pub fatal: Option<(String, BiliError)>,
+    /// 因中止而未发送的弹幕
+    pub skipped: Vec<String>,
+}
+
+impl FailFastResults {
+    /// 是否全部发送成功
+    pub fn all_sent(&self) -> bool {
+        self.fatal.is_none() && self.skipped.is_empty() && self.results.iter().all(|(_, result)| result.is_ok())
+    }
+    
+    /// 汇总为一条提示，如 `成功2条，失败1条，未发送1条`
+    pub fn summary(&self) -> String {
+        let sent: Vec<&SentBullet> = self.results.iter().filter_map(|(_, result)| result.as_ref().ok()).collect();
+        let shielded = sent.iter().filter(|bullet| bullet.modified).count();
+        let failed = self.results.len() - sent.len() + usize::from(self.fatal.is_some());
+        
+        let mut parts = vec![format!("成功{}条", sent.len())];
+        if shielded > 0 {
+            parts.push(format!("其中{}条部分内容被屏蔽", shielded));
+        }
+        if failed > 0 {
+            parts.push(format!("失败{}条", failed));
+        }
+        if !self.skipped.is_empty() {
+            parts.push(format!("未发送{}条", self.skipped.len()));
+        }
+        if let Some((_, e)) = &self.fatal {
+            parts.push(format!("已停止: {}", e));
+        }
+        parts.join("，")
+    }
+}
+
+/// 弹幕校验规则，返回错误即拒绝发送
+pub type Validator = Box<dyn Fn(&str) -> Result<()> + Send + Sync>;
+
+/// 频率过高时首次重试前的等待时间，之后逐次加倍
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// 频率过高时逐次加倍的等待时间上限
+const MAX_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(10);
+
+/// 单条弹幕因频率过高重试的最大次数
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// 发送频率过高，包括10030、10031和1003205
+const RATE_LIMITED_MESSAGE: &str = "发送频率过高";
+
+/// 表示登录态失效的错误码，映射为 [`BiliError::Auth`] 以便上层重新登录
+const BULLET_AUTH_ERROR_CODES: [(i32, &str); 2] = [
+    (-101, "未登录"),
+    (-111, "csrf校验失败，请重新登录"),
+];
+
+/// 发送弹幕的错误码及含义，新增错误码只需追加到这里
+const BULLET_ERROR_CODES: [(i32, &str); 7] = [
+    (-400, "参数错误"),
+    (1003, "你已被禁言"),
+    (10030, RATE_LIMITED_MESSAGE),
+    (10031, RATE_LIMITED_MESSAGE),
+    (1003205, RATE_LIMITED_MESSAGE),
+    (1003212, "超出限制长度"),
+    (11000, "弹幕被屏蔽"),
+];
+
+/// 查询弹幕错误码的含义
+pub fn bullet_error_meaning(code: i32) -> Option<&'static str> {
+    BULLET_AUTH_ERROR_CODES.iter()
+        .chain(BULLET_ERROR_CODES.iter())
+        .find(|(known, _)| *known == code)
+        .map(|(_, meaning)| *meaning)
+}
+
+/// 把发送弹幕返回的非0错误码转换为错误，message为服务端提示
+fn bullet_error(code: i32, message: &str) -> BiliError {
+    if let Some((_, meaning)) = BULLET_AUTH_ERROR_CODES.iter().find(|(known, _)| *known == code) {
+        return BiliError::Auth(meaning.to_string());
+    }
+    match bullet_error_meaning(code) {
+        Some(meaning) => BiliError::Bullet(meaning.to_string()),
+        // 未开通或未解锁表情包时服务端只在提示中说明
+        None if message.contains("表情") => BiliError::Bullet(format!("表情在当前直播间不可用: {}", message)),
+        None => BiliError::Bullet(format!("未知错误({}): {}", code, message)),
+    }
+}
+
+/// 普通用户单条弹幕的最大字符数，高等级用户/房间可通过 `with_max_len` 放宽
+pub const MAX_MESSAGE_CHARS: usize = 20;
+
+#[derive(Clone)]
 pub struct Bullet {
     client: BilibiliClient,
     room_id: u64,
     csrf: String,
+    /// 本次会话内缓存的弹幕颜色/字号权限
+    allowance: OnceCell<DanmakuAllowance>,
+    /// 发送前过滤的敏感词，为空时不过滤
+    sensitive_words: Vec<String>,
+    /// 单条弹幕的最大字符数
+    max_len: usize,
 }
 
 impl Bullet {
+    /// 使用默认配置（代理取自HTTP_PROXY环境变量）创建
     pub fn new(room_id: u64, csrf: String, cookie_str: &str) -> Result<Self> {
-        let client = BilibiliClient::with_cookies(cookie_str)?;
-        Ok(Self {
-            client,
-            room_id,
-            csrf,
-        })
+        Self::from_config(&Config::default(), room_id, csrf, cookie_str)
+    }
+    
+    /// 按配置文件的超时、代理和限速创建
+    pub fn from_config(config: &Config, room_id: u64, csrf: String, cookie_str: &str) -> Result<Self> {
+        let client = BilibiliClient::from_config(config)?;
+        client.add_cookies(&BilibiliClient::parse_cookies(cookie_str)?);
+        Ok(Self::with_client(client, room_id, csrf))
     }
     
     pub fn with_client(client: BilibiliClient, room_id: u64, csrf: String) -> Self {
@@ -43,82 +361,139 @@ impl Bullet {
             client,
             room_id,
             csrf,
+            allowance: OnceCell::new(),
+            sensitive_words: Vec::new(),
+            max_len: MAX_MESSAGE_CHARS,
         }
     }
     
+    /// 获取底层客户端
+    pub fn get_client(&self) -> &BilibiliClient {
+        &self.client
+    }
+    
+    /// 设置单条弹幕的最大字符数，等级较高的用户或房间上限更高
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+    
+    /// 设置敏感词列表，传入空列表即关闭敏感词过滤
+    pub fn with_sensitive_words(mut self, words: Vec<String>) -> Self {
+        self.sensitive_words = words;
+        self
+    }
+    
     /// 发送弹幕
-    pub async fn send_bullet(&self, msg: &str) -> Result<String> {
-        self.send_bullet_with_options(msg, None, None).await
+    pub async fn send_bullet(&self, msg: &str) -> Result<SentBullet> {
+        self.send_bullet_with_options(msg, None, None, None).await
     }
     
     /// 发送带选项的弹幕
-    pub async fn send_bullet_with_options(&self, msg: &str, color: Option<u32>, fontsize: Option<u32>) -> Result<String> {
+    ///
+    /// emoticon为表情的key（`emoticon_unique`）时发送表情弹幕：msg字段填该key且dm_type=1，传入的msg只用于展示。
+    pub async fn send_bullet_with_options(&self, msg: &str, color: Option<u32>, fontsize: Option<u32>, emoticon: Option<&str>) -> Result<SentBullet> {
         let url = "https://api.live.bilibili.com/msg/send";
         
+        // 指定了颜色或字号时先按直播间配置校验，获取配置失败则交给服务端判断
+        if (color.is_some() || fontsize.is_some())
+            && let Ok(allowance) = self.get_danmaku_allowance().await
+        {
+            allowance.check(color, fontsize)?;
+        }
+        
+        let form = self.send_form(msg, color, fontsize, emoticon);
+        let data: Vec<_> = form.iter().map(|(key, value)| (*key, value.as_str())).collect();
+        let response: ApiResponse<serde_json::Value> = self.client.post_form(url, &data).await?;
+        let bullet_response = BulletResponse::from(response);
+        match emoticon {
+            // 表情弹幕回显的是表情key，展示时仍使用表情名
+            Some(key) => parse_send_response(key, bullet_response).map(|_| SentBullet::unchanged(msg)),
+            None => parse_send_response(msg, bullet_response),
+        }
+    }
+    
+    /// 发送弹幕的表单，emoticon为表情key时按表情弹幕发送
+    fn send_form(&self, msg: &str, color: Option<u32>, fontsize: Option<u32>, emoticon: Option<&str>) -> Vec<(&'static str, String)> {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
         
-        let color_str = color.unwrap_or(16777215).to_string();
-        let fontsize_str = fontsize.unwrap_or(25).to_string();
-        let timestamp_str = timestamp.to_string();
-        let room_id_str = self.room_id.to_string();
-        
-        let data = vec![
-            ("msg", msg),
-            ("color", color_str.as_str()),
-            ("fontsize", fontsize_str.as_str()),
-            ("rnd", timestamp_str.as_str()),
-            ("roomid", room_id_str.as_str()),
-            ("csrf_token", self.csrf.as_str()),
-            ("csrf", self.csrf.as_str()),
+        let mut data = vec![
+            ("msg", emoticon.unwrap_or(msg).to_string()),
+            ("color", color.unwrap_or(16777215).to_string()),
+            ("fontsize", fontsize.unwrap_or(25).to_string()),
+            ("rnd", timestamp.to_string()),
+            ("roomid", self.room_id.to_string()),
+            ("csrf_token", self.csrf.clone()),
+            ("csrf", self.csrf.clone()),
         ];
-        
-        let response = self.client.get_client()
-            .post(url)
-            .headers(BilibiliClient::get_default_headers())
-            .form(&data)
-            .send()
-            .await?;
-        
-        let bullet_response: BulletResponse = response.json().await?;
-        
-        match bullet_response.code {
-            0 => Ok("发送成功".to_string()),
-            1003212 => Err(BiliError::Bullet("超出限制长度".to_string())),
-            -101 => Err(BiliError::Bullet("未登录".to_string())),
-            -400 => Err(BiliError::Bullet("参数错误".to_string())),
-            10031 => Err(BiliError::Bullet("发送频率过高".to_string())),
-            _ => Err(BiliError::Bullet(format!("未知错误: {}", bullet_response.msg))),
+        if emoticon.is_some() {
+            data.push(("dm_type", "1".to_string()));
+            data.push(("emoticonOptions", "[object Object]".to_string()));
         }
+        data
+    }
+    
+    /// 获取当前用户在该直播间可用的表情包
+    pub async fn get_emoticons(&self) -> Result<Vec<EmoticonPackage>> {
+        let url = format!("https://api.live.bilibili.com/xlive/web-ucenter/v2/emoticon/GetEmoticons?platform=pc&room_id={}", self.room_id);
+        
+        let response: ApiResponse<EmoticonListData> = self.client.get(&url).await?;
+        Ok(response.data.unwrap_or_default().data)
+    }
+    
+    /// 按名称（如 `[dog]`、`dog` 或表情key）发送官方表情
+    pub async fn send_emoticon(&self, name: &str) -> Result<SentBullet> {
+        let packages = self.get_emoticons().await?;
+        let emoticon = find_emoticon(&packages, name)?;
+        let display = if emoticon.emoji.is_empty() { name } else { emoticon.emoji.as_str() };
+        self.send_bullet_with_options(display, None, None, Some(&emoticon.emoticon_unique)).await
     }
     
     /// 发送带颜色的弹幕
-    pub async fn send_colored_bullet(&self, msg: &str, color: u32) -> Result<String> {
-        self.send_bullet_with_options(msg, Some(color), None).await
+    pub async fn send_colored_bullet(&self, msg: &str, color: u32) -> Result<SentBullet> {
+        self.send_bullet_with_options(msg, Some(color), None, None).await
     }
     
     /// 发送带字体大小的弹幕
-    pub async fn send_sized_bullet(&self, msg: &str, fontsize: u32) -> Result<String> {
-        self.send_bullet_with_options(msg, None, Some(fontsize)).await
+    pub async fn send_sized_bullet(&self, msg: &str, fontsize: u32) -> Result<SentBullet> {
+        self.send_bullet_with_options(msg, None, Some(fontsize), None).await
     }
     
-    /// 批量发送弹幕
-    pub async fn send_bullets(&self, messages: Vec<&str>) -> Result<Vec<(String, Result<String>)>> {
+    /// 批量发送弹幕，发送间隔由客户端统一的限速控制
+    pub async fn send_bullets(&self, messages: Vec<&str>) -> Result<Vec<(String, Result<SentBullet>)>> {
         let mut results = Vec::new();
         
         for msg in messages {
             let result = self.send_bullet(msg).await;
             results.push((msg.to_string(), result));
-            
-            // 防止发送过快，等待1秒
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         }
         
         Ok(results)
     }
     
+    /// 批量发送弹幕，遇到鉴权/权限类错误时立即停止
+    ///
+    /// 发送间隔由客户端统一的限速控制；频率限制等可重试的错误不会中止后续发送。
+    pub async fn send_bullets_fail_fast(&self, messages: Vec<&str>) -> FailFastResults {
+        send_until_fatal(messages, |msg| self.send_bullet(msg)).await
+    }
+    
+    /// 校验后批量发送弹幕，遇到频率过高时加倍等待后重试，鉴权类错误时停止
+    ///
+    /// 用于开播后自动发送配置的弹幕序列，平时的发送间隔由客户端统一的限速控制。
+    pub async fn send_bullets_adaptive(&self, messages: Vec<&str>) -> FailFastResults {
+        send_with_backoff(
+            messages,
+            |msg| self.send_validated_bullet(msg),
+            RATE_LIMIT_BACKOFF,
+            MAX_RATE_LIMIT_BACKOFF,
+            RATE_LIMIT_RETRIES,
+        ).await
+    }
+    
     /// 获取弹幕颜色常量
     pub fn get_color_white() -> u32 { 16777215 }
     pub fn get_color_red() -> u32 { 16711680 }
@@ -133,30 +508,78 @@ impl Bullet {
     pub fn get_fontsize_normal() -> u32 { 25 }
     pub fn get_fontsize_large() -> u32 { 36 }
     
-    /// 验证弹幕内容
-    pub fn validate_message(msg: &str) -> Result<()> {
-        if msg.is_empty() {
+    /// 使用默认规则和配置的敏感词验证弹幕内容
+    pub fn validate_message(&self, msg: &str) -> Result<()> {
+        Self::validate_with(msg, &self.validators())
+    }
+    
+    /// 默认规则加上配置的敏感词过滤
+    pub fn validators(&self) -> Vec<Validator> {
+        let mut validators = Self::validators_with_max_len(self.max_len);
+        if !self.sensitive_words.is_empty() {
+            let words = self.sensitive_words.clone();
+            validators.push(Box::new(move |msg: &str| Self::check_sensitive_words(msg, &words)));
+        }
+        validators
+    }
+    
+    /// 依次执行各校验规则，遇到第一个错误即返回
+    pub fn validate_with(msg: &str, validators: &[Validator]) -> Result<()> {
+        validators.iter().try_for_each(|validate| validate(msg))
+    }
+    
+    /// 默认校验规则：非空和长度限制，可在此基础上追加自定义规则
+    pub fn default_validators() -> Vec<Validator> {
+        Self::validators_with_max_len(MAX_MESSAGE_CHARS)
+    }
+    
+    /// 非空和指定长度上限的校验规则
+    pub fn validators_with_max_len(max_len: usize) -> Vec<Validator> {
+        vec![
+            Box::new(Self::check_not_empty),
+            Box::new(move |msg: &str| Self::check_length_with(msg, max_len)),
+        ]
+    }
+    
+    /// 只有空白字符的弹幕也视为空
+    pub fn check_not_empty(msg: &str) -> Result<()> {
+        if msg.trim().is_empty() {
             return Err(BiliError::Bullet("弹幕内容不能为空".to_string()));
         }
-        
-        if msg.len() > 20 {
-            return Err(BiliError::Bullet("弹幕内容过长，最多20个字符".to_string()));
+        Ok(())
+    }
+    
+    /// 按默认上限检查长度
+    pub fn check_length(msg: &str) -> Result<()> {
+        Self::check_length_with(msg, MAX_MESSAGE_CHARS)
+    }
+    
+    /// 按字符数检查长度，一个中文或emoji算一个字符
+    pub fn check_length_with(msg: &str, max_len: usize) -> Result<()> {
+        if msg.chars().count() > max_len {
+            return Err(BiliError::Bullet(format!("弹幕内容过长，最多{}个字符", max_len)));
         }
-        
-        // 检查是否包含敏感词汇
-        let sensitive_words = vec!["fuck", "shit", "damn"];
-        for word in sensitive_words {
-            if msg.to_lowercase().contains(word) {
-                return Err(BiliError::Bullet("弹幕包含敏感词汇".to_string()));
-            }
+        Ok(())
+    }
+    
+    /// 检查是否包含敏感词汇，忽略大小写
+    pub fn check_sensitive_words(msg: &str, words: &[String]) -> Result<()> {
+        let msg = msg.to_lowercase();
+        if words.iter().any(|word| !word.is_empty() && msg.contains(&word.to_lowercase())) {
+            return Err(BiliError::Bullet("弹幕包含敏感词汇".to_string()));
         }
-        
         Ok(())
     }
     
     /// 发送验证过的弹幕
-    pub async fn send_validated_bullet(&self, msg: &str) -> Result<String> {
-        Self::validate_message(msg)?;
+    pub async fn send_validated_bullet(&self, msg: &str) -> Result<SentBullet> {
+        self.validate_message(msg)?;
+        self.send_bullet(msg).await
+    }
+    
+    /// 使用自定义规则验证后发送弹幕
+    pub async fn send_bullet_validated_with(&self, msg: &str, validators: &[Validator]) -> Result<SentBullet> {
+        Self::validate_with(msg, validators)?;
         self.send_bullet(msg).await
     }
     
@@ -167,15 +590,36 @@ impl Bullet {
         let response: ApiResponse<serde_json::Value> = self.client.get(&url).await?;
         let data = response.data.ok_or_else(|| BiliError::Bullet("获取弹幕历史失败".to_string()))?;
         
-        if let Some(room) = data.get("room") {
-            if let Some(history) = room.get("history") {
-                if let Some(history_array) = history.as_array() {
-                    return Ok(history_array.clone());
-                }
-            }
-        }
+        Ok(json_path(&data, "room.history")
+            .and_then(|history| history.as_array())
+            .cloned()
+            .unwrap_or_default())
+    }
+    
+    /// 获取最近收到的礼物
+    pub async fn get_recent_gifts(&self) -> Result<Vec<GiftRecord>> {
+        let url = "https://api.live.bilibili.com/xlive/revenue/v1/giftStream/getReceivedGiftStreamNextList?limit=20&coin_type=0";
+        
+        let response: ApiResponse<GiftListData> = self.client.get(url).await?;
         
-        Ok(vec![])
+        Ok(response.data.and_then(|data| data.list).unwrap_or_default())
+    }
+    
+    /// 获取直播间在线榜
+    pub async fn get_online_rank(&self) -> Result<Vec<RankRecord>> {
+        let url = format!("https://api.live.bilibili.com/room/v1/Room/get_info?room_id={}", self.room_id);
+        let response: ApiResponse<serde_json::Value> = self.client.get(&url).await?;
+        let ruid = response.data
+            .and_then(|data| json_u64(&data, "uid"))
+            .ok_or_else(|| BiliError::Bullet("获取主播ID失败".to_string()))?;
+        
+        let url = format!(
+            "https://api.live.bilibili.com/xlive/general-interface/v1/rank/getOnlineGoldRank?ruid={}&roomId={}&page=1&pageSize=50",
+            ruid, self.room_id
+        );
+        let response: ApiResponse<OnlineRankData> = self.client.get(&url).await?;
+        
+        Ok(response.data.and_then(|data| data.items).unwrap_or_default())
     }
     
     /// 获取直播间弹幕配置
@@ -188,6 +632,40 @@ impl Bullet {
         Ok(config)
     }
     
+    /// 连接直播间弹幕服务器，返回实时消息的接收端
+    ///
+    /// 依次尝试getDanmuInfo返回的host_list，全部失败时返回最后一个错误。
+    pub async fn connect_danmaku(&self) -> Result<mpsc::Receiver<DanmakuMessage>> {
+        let info: DanmakuInfo = serde_json::from_value(self.get_bullet_config().await?)
+            .map_err(|e| BiliError::Bullet(format!("解析弹幕配置失败: {}", e)))?;
+        
+        // 未登录时uid为0，服务端会隐藏部分用户名
+        let uid = self.client.get_cookie("DedeUserID").and_then(|v| v.parse().ok()).unwrap_or(0);
+        let buvid = self.client.get_cookie("buvid3").unwrap_or_default();
+        let auth = danmaku::auth_packet(uid, self.room_id, &info.token, &buvid);
+        
+        let mut last_error = BiliError::Bullet("没有可用的弹幕服务器".to_string());
+        for url in info.urls() {
+            match danmaku::connect(&url, auth.clone()).await {
+                Ok(rx) => return Ok(rx),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+    
+    /// 获取当前用户可用的弹幕颜色和字号，成功后在本次会话内缓存
+    pub async fn get_danmaku_allowance(&self) -> Result<&DanmakuAllowance> {
+        self.allowance.get_or_try_init(|| async {
+            let url = format!("https://api.live.bilibili.com/xlive/web-room/v1/dM/GetDMConfigByGroup?room_id={}", self.room_id);
+            
+            let response: ApiResponse<DanmakuConfigData> = self.client.get(&url).await?;
+            let data = response.data.ok_or_else(|| BiliError::Bullet("获取弹幕颜色配置失败".to_string()))?;
+            
+            Ok(DanmakuAllowance::from_config(&data))
+        }).await
+    }
+    
     /// 获取房间号
     pub fn get_room_id(&self) -> u64 {
         self.room_id
@@ -215,4 +693,377 @@ impl Bullet {
             csrf: self.csrf.clone(),
         }
     }
-} 
\ No newline at end of file
+}
+
+/// 解析发送弹幕的响应
+///
+/// 服务端屏蔽部分内容时仍返回code 0，替换后的内容在`data.mode_info.extra`的JSON字符串中。
+fn parse_send_response(sent: &str, response: BulletResponse) -> Result<SentBullet> {
+    match response.code {
+        0 => {
+            let text = response.data.as_ref()
+                .and_then(|data| json_path(data, "mode_info.extra"))
+                .and_then(|extra| extra.as_str())
+                .and_then(|extra| serde_json::from_str::<serde_json::Value>(extra).ok())
+                .and_then(|extra| extra.get("content").and_then(|c| c.as_str()).map(str::to_string))
+                .unwrap_or_else(|| sent.to_string());
+            let shielded = SHIELDED_MESSAGES.contains(&response.msg.as_str())
+                || SHIELDED_MESSAGES.contains(&response.message.as_str());
+            let modified = shielded || text != sent;
+            Ok(SentBullet { text, modified })
+        }
+        code => {
+            let message = if response.msg.is_empty() { &response.message } else { &response.msg };
+            Err(bullet_error(code, message))
+        }
+    }
+}
+
+/// 判断是否为发送频率过高
+pub fn is_rate_limited(error: &BiliError) -> bool {
+    matches!(error.root(), BiliError::Bullet(message) if message == RATE_LIMITED_MESSAGE)
+}
+
+/// 逐条发送，遇到致命错误后跳过剩余消息
+async fn send_until_fatal<'a, F, Fut>(messages: Vec<&'a str>, send: F) -> FailFastResults
+where
+    F: FnMut(&'a str) -> Fut,
+    Fut: Future<Output = Result<SentBullet>>,
+{
+    send_with_backoff(messages, send, Duration::ZERO, Duration::ZERO, 0).await
+}
+
+/// 逐条发送，频率过高时等待backoff后重试同一条，之后每次加倍（不超过max_backoff），最多重试retries次
+///
+/// 未触发频率限制时不额外等待；触发后之后的每条消息都按当前的等待时间放慢发送。遇到致命错误后跳过剩余消息。
+async fn send_with_backoff<'a, F, Fut>(
+    messages: Vec<&'a str>,
+    mut send: F,
+    backoff: Duration,
+    max_backoff: Duration,
+    retries: u32,
+) -> FailFastResults
+where
+    F: FnMut(&'a str) -> Fut,
+    Fut: Future<Output = Result<SentBullet>>,
+{
+    let mut results = Vec::new();
+    let mut remaining = messages.into_iter();
+    let mut interval = Duration::ZERO;
+    
+    while let Some(msg) = remaining.next() {
+        let mut attempt = 0;
+        let result = loop {
+            match send(msg).await {
+                Err(e) if is_rate_limited(&e) && attempt < retries => {
+                    attempt += 1;
+                    interval = if interval.is_zero() { backoff } else { (interval * 2).min(max_backoff) };
+                    tokio::time::sleep(interval).await;
+                }
+                result => break result,
+            }
+        };
+        
+        match result {
+            Err(e) if e.is_auth_error() => {
+                return FailFastResults {
+                    results,
+                    fatal: Some((msg.to_string(), e)),
+                    skipped: remaining.map(str::to_string).collect(),
+                };
+            }
+            result => results.push((msg.to_string(), result)),
+        }
+        
+        if !interval.is_zero() {
+            tokio::time::sleep(interval).await;
+        }
+    }
+    
+    FailFastResults {
+        results,
+        fatal: None,
+        skipped: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::DEFAULT_TIMEOUT_MS;
+
+    #[test]
+    fn test_records_accept_both_name_spellings() {
+        for json in [
+            r#"{"uname": "观众", "gift_name": "辣条", "gift_num": 3}"#,
+            r#"{"name": "观众", "gift_name": "辣条", "gift_num": 3}"#,
+        ] {
+            let gift: GiftRecord = serde_json::from_str(json).unwrap();
+            assert_eq!(gift.sender, "观众");
+        }
+
+        for json in [
+            r#"{"userRank": 1, "uid": 42, "name": "观众"}"#,
+            r#"{"userRank": 1, "uid": 42, "uname": "观众"}"#,
+        ] {
+            let rank: RankRecord = serde_json::from_str(json).unwrap();
+            assert_eq!(rank.name, "观众");
+        }
+    }
+
+    #[test]
+    fn test_custom_validator_rejects_urls() {
+        let mut validators = Bullet::default_validators();
+        validators.push(Box::new(|msg: &str| {
+            if msg.contains("http://") || msg.contains("https://") {
+                return Err(BiliError::validation("弹幕不能包含链接"));
+            }
+            Ok(())
+        }));
+
+        assert!(Bullet::validate_with("主播好", &validators).is_ok());
+        assert!(matches!(
+            Bullet::validate_with("http://a.cn", &validators),
+            Err(BiliError::Validation(_))
+        ));
+        // 默认规则仍然生效
+        assert!(matches!(Bullet::validate_with("", &validators), Err(BiliError::Bullet(_))));
+    }
+
+    #[test]
+    fn test_length_counts_chars_and_words_are_configurable() {
+        assert!(Bullet::check_length(&"主".repeat(MAX_MESSAGE_CHARS)).is_ok());
+        assert!(Bullet::check_length(&"主".repeat(MAX_MESSAGE_CHARS + 1)).is_err());
+
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+        let bullet = Bullet::with_client(client, 1, "csrf".to_string());
+        // 未配置敏感词时不过滤
+        assert!(bullet.validate_message("damn").is_ok());
+
+        let bullet = bullet.with_sensitive_words(vec!["广告".to_string(), "Spam".to_string()]);
+        assert!(bullet.validate_message("加群看广告").is_err());
+        assert!(bullet.validate_message("SPAM").is_err());
+        assert!(bullet.validate_message("主播好").is_ok());
+    }
+
+    #[test]
+    fn test_length_with_mixed_text_and_emoji() {
+        // 中英文混合：5个英文 + 15个中文 = 20个字符，按字节算有50字节
+        let mixed = format!("Hello{}", "主".repeat(15));
+        assert!(Bullet::check_length(&mixed).is_ok());
+        assert!(Bullet::check_length(&format!("{}!", mixed)).is_err());
+
+        let emoji = "😀".repeat(MAX_MESSAGE_CHARS);
+        assert!(Bullet::check_length(&emoji).is_ok());
+        assert!(Bullet::check_length(&format!("{}😀", emoji)).is_err());
+
+        // 放宽上限后可以发送更长的弹幕
+        let long = "主".repeat(30);
+        assert!(Bullet::check_length_with(&long, 30).is_ok());
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+        let bullet = Bullet::with_client(client, 1, "csrf".to_string());
+        assert!(bullet.validate_message(&long).is_err());
+        assert!(bullet.with_max_len(30).validate_message(&long).is_ok());
+    }
+
+    #[test]
+    fn test_blank_message_is_empty() {
+        for msg in ["", "   ", "\t\n", "\u{3000}"] {
+            assert!(Bullet::check_not_empty(msg).is_err(), "{:?}", msg);
+        }
+        assert!(Bullet::check_not_empty(" 主播好 ").is_ok());
+    }
+
+    #[test]
+    fn test_disallowed_color_is_rejected() {
+        let data: DanmakuConfigData = serde_json::from_str(r#"{
+            "group": [
+                {"name": "默认", "color": [
+                    {"name": "白色", "color": 16777215, "status": 1},
+                    {"name": "红色", "color": 16711680, "status": 1}
+                ]},
+                {"name": "舰长", "color": [
+                    {"name": "紫色", "color": 16711935, "status": 0}
+                ]}
+            ]
+        }"#).unwrap();
+        let allowance = DanmakuAllowance::from_config(&data);
+
+        assert!(allowance.check(Some(Bullet::get_color_red()), None).is_ok());
+        assert!(allowance.check(None, Some(Bullet::get_fontsize_large())).is_ok());
+
+        match allowance.check(Some(Bullet::get_color_purple()), None) {
+            Err(BiliError::Validation(msg)) => {
+                assert!(msg.starts_with("当前等级不支持该颜色"));
+                assert!(msg.contains("#FFFFFF"));
+                assert!(msg.contains("#FF0000"));
+                assert!(!msg.contains("#FF00FF"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_stops_on_auth_error() {
+        let mut sent = Vec::new();
+        let outcome = send_until_fatal(
+            vec!["第一条", "太快了", "掉登录", "第四条", "第五条"],
+            |msg| {
+                sent.push(msg);
+                async move {
+                    match msg {
+                        "太快了" => Err(BiliError::Bullet("发送频率过高".to_string())),
+                        "掉登录" => Err(BiliError::Auth("未登录".to_string())),
+                        _ => Ok(SentBullet::unchanged(msg)),
+                    }
+                }
+            },
+        ).await;
+        
+        assert_eq!(sent, vec!["第一条", "太快了", "掉登录"]);
+        assert_eq!(outcome.results.len(), 2);
+        assert!(outcome.results[1].1.is_err());
+        assert_eq!(outcome.fatal.as_ref().map(|(msg, _)| msg.as_str()), Some("掉登录"));
+        assert_eq!(outcome.skipped, vec!["第四条", "第五条"]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_message_is_retried_with_backoff() {
+        let mut sent = Vec::new();
+        let mut limited = 2;
+        let outcome = send_with_backoff(
+            vec!["欢迎", "今天玩主机游戏", "掉登录", "不会发送"],
+            |msg| {
+                sent.push(msg);
+                let rate_limited = msg == "今天玩主机游戏" && limited > 0;
+                if rate_limited {
+                    limited -= 1;
+                }
+                async move {
+                    match msg {
+                        _ if rate_limited => Err(BiliError::Bullet(RATE_LIMITED_MESSAGE.to_string())),
+                        "掉登录" => Err(BiliError::Auth("未登录".to_string())),
+                        _ => Ok(SentBullet::unchanged(msg)),
+                    }
+                }
+            },
+            Duration::from_millis(1),
+            Duration::from_millis(4),
+            RATE_LIMIT_RETRIES,
+        ).await;
+        
+        assert_eq!(sent, vec!["欢迎", "今天玩主机游戏", "今天玩主机游戏", "今天玩主机游戏", "掉登录"]);
+        assert!(outcome.results.iter().all(|(_, result)| result.is_ok()));
+        assert!(!outcome.all_sent());
+        assert_eq!(outcome.summary(), "成功2条，失败1条，未发送1条，已停止: 认证失败: 未登录");
+    }
+
+    #[test]
+    fn test_shielded_response_reports_replacement() {
+        let response: BulletResponse = serde_json::from_str(r#"{
+            "code": 0, "msg": "f", "message": "f",
+            "data": {"mode_info": {"mode": 0, "extra": "{\"content\":\"你好***\",\"color\":16777215}"}}
+        }"#).unwrap();
+        let sent = parse_send_response("你好世界", response).unwrap();
+        assert!(sent.modified);
+        assert_eq!(sent.text, "你好***");
+        assert!(sent.summary().contains("部分内容被屏蔽"));
+
+        let response: BulletResponse = serde_json::from_str(
+            r#"{"code": 0, "msg": "", "message": "", "data": {"mode_info": {"extra": "{\"content\":\"你好\"}"}}}"#,
+        ).unwrap();
+        assert_eq!(parse_send_response("你好", response).unwrap(), SentBullet::unchanged("你好"));
+    }
+
+    #[test]
+    fn test_find_emoticon_by_name_and_permission() {
+        let data: EmoticonListData = serde_json::from_str(r#"{"data": [
+            {"pkg_name": "通用表情", "pkg_perm": 1, "emoticons": [
+                {"emoji": "[dog]", "descript": "dog", "emoticon_unique": "official_147", "perm": 1}
+            ]},
+            {"pkg_name": "房间专属表情", "pkg_perm": 0, "emoticons": [
+                {"emoji": "[主播]", "descript": "主播", "emoticon_unique": "room_1_1", "perm": 0}
+            ]}
+        ]}"#).unwrap();
+        for name in ["[dog]", "dog", "official_147"] {
+            assert_eq!(find_emoticon(&data.data, name).unwrap().emoticon_unique, "official_147");
+        }
+        assert!(find_emoticon(&data.data, "[主播]").unwrap_err().to_string().contains("未解锁"));
+        assert!(find_emoticon(&data.data, "[猫]").unwrap_err().to_string().contains("未开通"));
+    }
+
+    #[test]
+    fn test_emoticon_form_and_error() {
+        let bullet = Bullet::new(1, "csrf".to_string(), "").unwrap();
+        let form = bullet.send_form("[dog]", None, None, Some("official_147"));
+        assert!(form.contains(&("msg", "official_147".to_string())));
+        assert!(form.contains(&("dm_type", "1".to_string())));
+        assert!(!bullet.send_form("你好", None, None, None).iter().any(|(key, _)| *key == "dm_type"));
+
+        let response: BulletResponse = serde_json::from_str(
+            r#"{"code": 10024, "msg": "表情包未解锁", "message": "表情包未解锁", "data": null}"#,
+        ).unwrap();
+        let error = parse_send_response("official_147", response).unwrap_err();
+        assert_eq!(error.to_string(), BiliError::Bullet("表情在当前直播间不可用: 表情包未解锁".to_string()).to_string());
+    }
+
+    #[test]
+    fn test_bullet_error_codes() {
+        for code in [-101, -111] {
+            assert!(bullet_error(code, "").is_auth_error());
+        }
+        for code in [10030, 10031, 1003205] {
+            assert!(is_rate_limited(&bullet_error(code, "")));
+        }
+        assert_eq!(bullet_error_meaning(1003212), Some("超出限制长度"));
+        assert_eq!(bullet_error(12345, "奇怪").to_string(), "弹幕发送失败: 未知错误(12345): 奇怪");
+    }
+
+    #[test]
+    fn test_deserialize_gift_list() {
+        let json = r#"{
+            "code": 0,
+            "message": "0",
+            "data": {
+                "list": [
+                    {"uid": 10086, "uname": "观众A", "time": "2024-05-01 20:00:00", "gift_id": 31036, "gift_name": "小花花", "gift_num": 5, "gold": 500, "silver": 0},
+                    {"uid": 10010, "uname": "观众B", "time": "2024-05-01 20:01:00", "gift_id": 1, "gift_name": "辣条", "gift_num": 1, "gold": 0, "silver": 100}
+                ],
+                "has_more": 0
+            }
+        }"#;
+        let response: ApiResponse<GiftListData> = serde_json::from_str(json).unwrap();
+        let gifts = response.data.and_then(|d| d.list).unwrap_or_default();
+        
+        assert_eq!(gifts.len(), 2);
+        assert_eq!(gifts[0].sender, "观众A");
+        assert_eq!(gifts[0].gift_name, "小花花");
+        assert_eq!(gifts[0].count, 5);
+        assert_eq!(gifts[0].value, 500);
+        
+        let empty: GiftListData = serde_json::from_str(r#"{"list": null, "has_more": 0}"#).unwrap();
+        assert!(empty.list.unwrap_or_default().is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_online_rank() {
+        let json = r#"{
+            "onlineNum": 2,
+            "OnlineRankItem": [
+                {"userRank": 1, "uid": 10086, "name": "观众A", "face": "", "score": 1200, "medalInfo": null, "guard_level": 0},
+                {"userRank": 2, "uid": 10010, "name": "观众B", "face": "", "score": 300, "medalInfo": null, "guard_level": 3}
+            ],
+            "ownInfo": {}
+        }"#;
+        let rank: OnlineRankData = serde_json::from_str(json).unwrap();
+        let items = rank.items.unwrap_or_default();
+        
+        assert_eq!(rank.online_num, 2);
+        assert_eq!(items[0].rank, 1);
+        assert_eq!(items[1].name, "观众B");
+        assert_eq!(items[1].score, 300);
+        
+        let empty: OnlineRankData = serde_json::from_str(r#"{"onlineNum": 0}"#).unwrap();
+        assert!(empty.items.unwrap_or_default().is_empty());
+    }
+}
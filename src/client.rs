@@ -1,11 +1,20 @@
-use reqwest::{Client, header::HeaderMap, cookie::Jar};
+use reqwest::{Client, ClientBuilder, Proxy, header::HeaderMap, cookie::Jar, multipart::Form};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use crate::config::Config;
 use crate::error::{Result, BiliError};
+use crate::trace;
 
 const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/96.0.4664.110 Safari/537.36";
 
+/// 默认请求超时（毫秒），与配置文件的默认值一致
+pub const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     pub code: i32,
@@ -24,59 +33,246 @@ impl<T> ApiResponse<T> {
     }
 }
 
+/// 请求重试配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// 最多重试次数，0表示不重试
+    pub retry_count: u32,
+    /// 首次重试前的等待时间，之后每次翻倍
+    pub retry_delay: Duration,
+}
+
+impl RetryConfig {
+    pub fn none() -> Self {
+        Self {
+            retry_count: 0,
+            retry_delay: Duration::ZERO,
+        }
+    }
+
+    /// 第attempt次重试前的等待时间（从1开始，指数退避）
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        self.retry_delay.saturating_mul(1u32 << attempt.saturating_sub(1).min(16))
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// 按最小间隔限速，克隆出的限速器共享同一个时间表
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    interval: Duration,
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RateLimiter {
+    /// 相邻两次许可之间至少间隔interval
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Arc::new(Mutex::new(None)),
+        }
+    }
+    
+    /// 每秒最多max_per_second次，为0时不限速
+    pub fn per_second(max_per_second: u32) -> Option<Self> {
+        (max_per_second > 0).then(|| Self::new(Duration::from_secs(1) / max_per_second))
+    }
+    
+    /// 等待到可以发出下一次请求，同时预约之后的时间段
+    pub async fn acquire(&self) {
+        let wait_until = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let at = next.map_or(now, |next| next.max(now));
+            *next = Some(at + self.interval);
+            at
+        };
+        tokio::time::sleep_until(wait_until).await;
+    }
+}
+
+/// 一次即将进行的重试，传给 [`BilibiliClient::set_retry_hook`] 设置的回调
+#[derive(Debug)]
+pub struct RetryAttempt<'a> {
+    pub error: &'a BiliError,
+    /// 第几次重试，从1开始
+    pub attempt: u32,
+    pub delay: Duration,
+}
+
+impl fmt::Display for RetryAttempt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "请求失败: {}，{}ms后进行第{}次重试", self.error, self.delay.as_millis(), self.attempt)
+    }
+}
+
+type RetryCallback = Arc<dyn Fn(&RetryAttempt) + Send + Sync>;
+
+/// 重试回调，克隆出的客户端共用同一个回调
+#[derive(Clone, Default)]
+struct RetryHook(Arc<Mutex<Option<RetryCallback>>>);
+
+impl RetryHook {
+    fn notify(&self, attempt: &RetryAttempt) {
+        // 先取出回调再调用，避免回调中再次设置时死锁
+        let callback = self.0.lock().unwrap().clone();
+        if let Some(callback) = callback {
+            callback(attempt);
+        }
+    }
+}
+
+impl fmt::Debug for RetryHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RetryHook")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BilibiliClient {
     client: Client,
     jar: Arc<Jar>,
+    retry: RetryConfig,
+    /// 所有请求发出前共用的限速，None表示不限速
+    limiter: Option<RateLimiter>,
+    /// 创建时的请求超时（毫秒）和代理，供 [`BilibiliClient::with_session_cookies`] 复用
+    timeout: u64,
+    proxy: Option<String>,
+    on_retry: RetryHook,
 }
 
 impl BilibiliClient {
-    pub fn new() -> Result<Self> {
-        let jar = Arc::new(Jar::default());
-        let client = Client::builder()
-            .cookie_provider(jar.clone())
-            .user_agent(USER_AGENT)
-            .build()?;
-        
-        Ok(Self {
-            client,
-            jar,
-        })
+    /// 创建客户端，timeout为单个请求的超时时间（毫秒）
+    pub fn new(timeout: u64) -> Result<Self> {
+        let client = Self::with_builder(|builder| builder.timeout(Duration::from_millis(timeout)))?;
+        Ok(Self { timeout, ..client })
     }
     
-    pub fn with_cookies(cookie_str: &str) -> Result<Self> {
-        let jar = Arc::new(Jar::default());
-        let client = Client::builder()
-            .cookie_provider(jar.clone())
-            .user_agent(USER_AGENT)
-            .build()?;
+    pub fn with_cookies(cookie_str: &str, timeout: u64) -> Result<Self> {
+        let client = Self::new(timeout)?;
         
         // 解析并添加cookies
         let cookies = Self::parse_cookies(cookie_str)?;
-        Self::add_cookies_to_jar(&jar, &cookies);
+        client.add_cookies(&cookies);
         
-        Ok(Self {
-            client,
-            jar,
-        })
+        Ok(client)
     }
     
-    pub fn with_cookies_map(cookies: &HashMap<String, String>) -> Result<Self> {
-        let jar = Arc::new(Jar::default());
-        let client = Client::builder()
-            .cookie_provider(jar.clone())
-            .user_agent(USER_AGENT)
-            .build()?;
+    /// 通过代理访问，支持 `http://`、`https://` 和 `socks5://` 地址
+    pub fn with_proxy(proxy_url: &str, timeout: u64) -> Result<Self> {
+        let proxy = Self::parse_proxy(proxy_url)?;
+        let client = Self::with_builder(|builder| builder.proxy(proxy).timeout(Duration::from_millis(timeout)))?;
+        Ok(Self { timeout, proxy: Some(proxy_url.to_string()), ..client })
+    }
+    
+    /// 创建持有另一组cookie的客户端，沿用当前的超时、代理、重试和限速（限速与当前客户端共用）
+    ///
+    /// 通过 [`BilibiliClient::with_builder`] 做的其他定制不会保留。
+    pub fn with_session_cookies(&self, cookies: &HashMap<String, String>) -> Result<Self> {
+        let client = match &self.proxy {
+            Some(proxy_url) => Self::with_proxy(proxy_url, self.timeout)?,
+            None => Self::new(self.timeout)?,
+        };
+        client.add_cookies(cookies);
+        Ok(Self { retry: self.retry, limiter: self.limiter.clone(), on_retry: self.on_retry.clone(), ..client })
+    }
+    
+    pub fn with_cookies_and_proxy(cookie_str: &str, proxy_url: &str, timeout: u64) -> Result<Self> {
+        let client = Self::with_proxy(proxy_url, timeout)?;
+        
+        let cookies = Self::parse_cookies(cookie_str)?;
+        client.add_cookies(&cookies);
+        
+        Ok(client)
+    }
+    
+    fn parse_proxy(proxy_url: &str) -> Result<Proxy> {
+        Proxy::all(proxy_url.trim())
+            .map_err(|e| BiliError::General(format!("代理地址无效 {}: {}", proxy_url, e)))
+    }
+    
+    /// 按配置文件的超时和代理创建客户端
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let client = match config.proxy_url() {
+            Some(url) => Self::with_proxy(&url, config.timeout)?,
+            None => Self::new(config.timeout)?,
+        };
+        Ok(client.with_rate_limit(config.max_requests_per_second))
+    }
+    
+    pub fn with_cookies_map(cookies: &HashMap<String, String>, timeout: u64) -> Result<Self> {
+        let client = Self::new(timeout)?;
         
         // 直接添加cookies
-        Self::add_cookies_to_jar(&jar, cookies);
+        client.add_cookies(cookies);
+        
+        Ok(client)
+    }
+    
+    /// 在默认配置（UA + cookie jar）基础上自定义 `reqwest::ClientBuilder`
+    ///
+    /// 用于设置代理、证书校验、自定义DNS解析等固定构造函数未暴露的选项。
+    pub fn with_builder(build: impl FnOnce(ClientBuilder) -> ClientBuilder) -> Result<Self> {
+        let jar = Arc::new(Jar::default());
+        let builder = Client::builder()
+            .cookie_provider(jar.clone())
+            .user_agent(USER_AGENT);
+        let client = build(builder).build()?;
         
         Ok(Self {
             client,
             jar,
+            retry: RetryConfig::none(),
+            limiter: None,
+            timeout: DEFAULT_TIMEOUT_MS,
+            proxy: None,
+            on_retry: RetryHook::default(),
         })
     }
     
+    /// 设置get/post对网络错误和503/429的重试次数和初始间隔（毫秒）
+    pub fn with_retry_config(mut self, retry_count: u32, retry_delay: u64) -> Self {
+        self.retry = RetryConfig {
+            retry_count,
+            retry_delay: Duration::from_millis(retry_delay),
+        };
+        self
+    }
+    
+    pub fn retry_config(&self) -> RetryConfig {
+        self.retry
+    }
+    
+    /// 设置每次重试前调用的回调，替换之前的回调，所有克隆出的客户端都可见
+    ///
+    /// 客户端本身不输出重试信息，由CLI打印或由TUI写入日志面板。
+    pub fn set_retry_hook(&self, hook: impl Fn(&RetryAttempt) + Send + Sync + 'static) {
+        *self.on_retry.0.lock().unwrap() = Some(Arc::new(hook));
+    }
+    
+    /// 限制每秒最多发出的请求数（包括重试），为0时不限速
+    pub fn with_rate_limit(mut self, max_per_second: u32) -> Self {
+        self.limiter = RateLimiter::per_second(max_per_second);
+        self
+    }
+    
+    /// 等待限速许可
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire().await;
+        }
+    }
+    
+    /// 向共享的cookie jar中添加cookies，所有克隆出的客户端都可见
+    pub fn add_cookies(&self, cookies: &HashMap<String, String>) {
+        Self::add_cookies_to_jar(&self.jar, cookies);
+    }
+    
     fn add_cookies_to_jar(jar: &Arc<Jar>, cookies: &HashMap<String, String>) {
         // 为B站的主要域名添加cookies
         let domains = [
@@ -98,16 +294,21 @@ impl BilibiliClient {
         }
     }
     
+    /// 解析 `k1=v1; k2=v2` 形式的cookie字符串
+    ///
+    /// 按 `;` 切分后逐段取键值，结果与正则 `(\w+)=([^;]+)` 一致：键取第一个 `=` 前紧邻的单词字符，
+    /// 值为空的段被忽略。浏览器导出的完整cookie可能有几KB，在验证时会被反复解析，因此不使用正则。
     pub fn parse_cookies(cookie_str: &str) -> Result<HashMap<String, String>> {
         let mut cookies = HashMap::new();
-        let regex = regex::Regex::new(r"(\w+)=([^;]+)(?:;|$)").unwrap();
         
-        for cap in regex.captures_iter(cookie_str) {
-            let key = cap.get(1).unwrap().as_str().to_string();
-            let value = urlencoding::decode(cap.get(2).unwrap().as_str())
+        for segment in cookie_str.split(';') {
+            let Some((key, raw_value)) = split_cookie(segment) else {
+                continue;
+            };
+            let value = urlencoding::decode(raw_value)
                 .map_err(|e| BiliError::General(format!("解析cookie失败: {}", e)))?
                 .to_string();
-            cookies.insert(key, value);
+            cookies.insert(key.to_string(), value);
         }
         
         Ok(cookies)
@@ -131,55 +332,171 @@ impl BilibiliClient {
     }
     
     pub async fn get<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<ApiResponse<T>> {
-        let response = self.client
-            .get(url)
-            .headers(Self::get_default_headers())
-            .send()
-            .await?;
-        
-        let json: ApiResponse<T> = response.json().await?;
-        
-        if !json.is_success() {
-            return Err(BiliError::api_error(json.code, json.get_message().to_string()));
-        }
-        
-        Ok(json)
+        let result = retry_with(self.retry, &self.on_retry, || async {
+            self.throttle().await;
+            let response = self.client
+                .get(url)
+                .headers(Self::get_default_headers())
+                .send()
+                .await?;
+            
+            Self::parse_response(response).await
+        }).await;
+        trace::record("GET", url, String::new, &result);
+        result
+    }
+    
+    /// GET请求，返回未按code转换的原始响应，供错误码中也带有数据的接口（如未登录时的nav）使用
+    ///
+    /// 和get一样经过限速和重试。
+    pub async fn get_raw<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<ApiResponse<T>> {
+        let result = retry_with(self.retry, &self.on_retry, || async {
+            self.throttle().await;
+            let response = self.client
+                .get(url)
+                .headers(Self::get_default_headers())
+                .send()
+                .await?;
+            
+            Self::parse_raw_response(response).await
+        }).await;
+        trace::record("GET", url, String::new, &result);
+        result
     }
     
     pub async fn post<T: for<'de> Deserialize<'de>>(&self, url: &str, data: &[(&str, &str)]) -> Result<ApiResponse<T>> {
-        let response = self.client
-            .post(url)
-            .headers(Self::get_default_headers())
-            .form(data)
-            .send()
-            .await?;
+        check_form(data)?;
         
-        let json: ApiResponse<T> = response.json().await?;
-        
-        if !json.is_success() {
-            return Err(BiliError::api_error(json.code, json.get_message().to_string()));
-        }
-        
-        Ok(json)
+        let result = retry_with(self.retry, &self.on_retry, || async {
+            self.throttle().await;
+            let response = self.client
+                .post(url)
+                .headers(Self::get_default_headers())
+                .form(data)
+                .send()
+                .await?;
+            
+            Self::parse_response(response).await
+        }).await;
+        trace::record("POST", url, || trace::redact_form(data.iter().copied()), &result);
+        result
     }
     
     pub async fn post_json<T: for<'de> Deserialize<'de>, D: Serialize>(&self, url: &str, data: &D) -> Result<ApiResponse<T>> {
-        let response = self.client
-            .post(url)
-            .headers(Self::get_default_headers())
-            .json(data)
-            .send()
-            .await?;
+        let result = retry_with(self.retry, &self.on_retry, || async {
+            self.throttle().await;
+            let response = self.client
+                .post(url)
+                .headers(Self::get_default_headers())
+                .json(data)
+                .send()
+                .await?;
+            
+            Self::parse_response(response).await
+        }).await;
+        trace::record("POST", url, || serde_json::to_value(data).map(|value| trace::redact_json(&value)).unwrap_or_default(), &result);
+        result
+    }
+    
+    /// 发送表单并返回未按code转换的原始响应，供自行解析错误码的接口（如发送弹幕）使用
+    ///
+    /// 和post一样经过限速，网络错误和503/429等可重试的错误会重试。
+    pub async fn post_form<T: for<'de> Deserialize<'de>>(&self, url: &str, data: &[(&str, &str)]) -> Result<ApiResponse<T>> {
+        check_form(data)?;
         
-        let json: ApiResponse<T> = response.json().await?;
+        let result = retry_with(self.retry, &self.on_retry, || async {
+            self.throttle().await;
+            let response = self.client
+                .post(url)
+                .headers(Self::get_default_headers())
+                .form(data)
+                .send()
+                .await?;
+            
+            Self::parse_raw_response(response).await
+        }).await;
+        trace::record("POST", url, || trace::redact_form(data.iter().copied()), &result);
+        result
+    }
+    
+    /// 上传文件用的multipart请求，表单无法复用，每次重试时重新构建
+    pub async fn post_multipart<T, F>(&self, url: &str, build_form: F) -> Result<ApiResponse<T>>
+    where
+        T: for<'de> Deserialize<'de>,
+        F: Fn() -> reqwest::Result<Form>,
+    {
+        // 默认的表单content-type会覆盖multipart的boundary
+        let mut headers = Self::get_default_headers();
+        headers.remove("content-type");
+        
+        let result = retry_with(self.retry, &self.on_retry, || async {
+            self.throttle().await;
+            let response = self.client
+                .post(url)
+                .headers(headers.clone())
+                .multipart(build_form()?)
+                .send()
+                .await?;
+            
+            Self::parse_response(response).await
+        }).await;
+        trace::record("POST", url, || "<multipart>".to_string(), &result);
+        result
+    }
+    
+    /// 解析接口响应，统一处理错误码和维护页面，错误附带接口路径
+    async fn parse_response<T: for<'de> Deserialize<'de>>(response: reqwest::Response) -> Result<ApiResponse<T>> {
+        let status = response.status().as_u16();
+        let path = response.url().path().to_string();
+        let body = response.text().await?;
+        Self::parse_body(status, &body).map_err(|e| e.with_endpoint(path))
+    }
+    
+    /// 解析接口响应但不按code转换为错误，维护页面等非JSON响应仍转换为对应的错误
+    async fn parse_raw_response<T: for<'de> Deserialize<'de>>(response: reqwest::Response) -> Result<ApiResponse<T>> {
+        let status = response.status().as_u16();
+        let path = response.url().path().to_string();
+        let body = response.text().await?;
+        Self::parse_value(status, &body)
+            .and_then(|value| Ok(serde_json::from_value(value)?))
+            .map_err(|e| e.with_endpoint(path))
+    }
+    
+    pub(crate) fn parse_body<T: for<'de> Deserialize<'de>>(status: u16, body: &str) -> Result<ApiResponse<T>> {
+        let json: ApiResponse<T> = serde_json::from_value(Self::parse_value(status, body)?)?;
         
         if !json.is_success() {
-            return Err(BiliError::api_error(json.code, json.get_message().to_string()));
+            if BiliError::has_maintenance_marker(json.get_message()) {
+                return Err(BiliError::maintenance(json.code));
+            }
+            return Err(BiliError::from_bili_code(json.code, json.get_message()));
         }
         
         Ok(json)
     }
     
+    /// 把响应体解析为JSON，维护页面、限流和风控拦截返回的非JSON页面转换为对应的错误
+    fn parse_value(status: u16, body: &str) -> Result<serde_json::Value> {
+        match serde_json::from_str(body) {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                // 维护期间接口可能直接返回HTML维护页
+                if BiliError::has_maintenance_marker(body) {
+                    return Err(BiliError::maintenance(status as i32));
+                }
+                // 限流或服务不可用时返回的非JSON页面，交给重试处理
+                if status == 503 || status == 429 {
+                    return Err(BiliError::api_error(status as i32, format!("HTTP {}", status)));
+                }
+                // 风控拦截时返回HTTP 412页面
+                if status == 412 {
+                    return Err(BiliError::from_bili_code(412, format!("HTTP {}", status)));
+                }
+                Err(e.into())
+            }
+        }
+    }
+    
     pub fn get_client(&self) -> &Client {
         &self.client
     }
@@ -187,10 +504,324 @@ impl BilibiliClient {
     pub fn get_jar(&self) -> &Arc<Jar> {
         &self.jar
     }
+    
+    /// 读取jar中B站域名下的cookie值
+    pub fn get_cookie(&self, name: &str) -> Option<String> {
+        use reqwest::cookie::CookieStore;
+        
+        let url = "https://api.live.bilibili.com".parse().ok()?;
+        let header = self.jar.cookies(&url)?;
+        header.to_str().ok()?
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+    }
+}
+
+/// 在一段cookie中找到第一个前面紧邻单词字符且后面有值的 `=`，返回键和值
+fn split_cookie(segment: &str) -> Option<(&str, &str)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    
+    for (pos, _) in segment.match_indices('=') {
+        let before = &segment[..pos];
+        let key_start = before.char_indices()
+            .rev()
+            .take_while(|(_, c)| is_word(*c))
+            .last()
+            .map(|(i, _)| i);
+        let value = &segment[pos + 1..];
+        if let Some(start) = key_start
+            && !value.is_empty()
+        {
+            return Some((&before[start..], value));
+        }
+    }
+    None
+}
+
+/// 对可重试的错误（网络、503/429、维护、请求被拦截）按指数退避重试，业务错误直接返回
+async fn retry_with<T, F, Fut>(config: RetryConfig, hook: &RetryHook, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(e) if e.is_retryable() && attempt < config.retry_count => {
+                attempt += 1;
+                let delay = e.retry_backoff(config.delay_for(attempt));
+                hook.notify(&RetryAttempt { error: &e, attempt, delay });
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
 }
 
 impl Default for BilibiliClient {
     fn default() -> Self {
-        Self::new().unwrap()
+        Self::new(DEFAULT_TIMEOUT_MS).unwrap()
+    }
+}
+
+/// 发送前检查表单，空表单的POST会被B站以-400拒绝，提前报错便于定位参数构造问题
+fn check_form(data: &[(&str, &str)]) -> Result<()> {
+    if data.is_empty() {
+        return Err(BiliError::validation("POST表单为空，请检查请求参数的构造"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve_once, serve_responses};
+
+    #[tokio::test]
+    async fn test_rate_limiter_shared_between_clones() {
+        assert!(RateLimiter::per_second(0).is_none());
+        assert_eq!(RateLimiter::per_second(4).unwrap().interval, Duration::from_millis(250));
+
+        let limiter = RateLimiter::new(Duration::from_millis(40));
+        let cloned = limiter.clone();
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(40));
+        tokio::join!(limiter.acquire(), cloned.acquire());
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        let (proxy, request) = serve_once(r#"{"code":0,"message":"0","data":null}"#).await;
+        let client = BilibiliClient::with_cookies_and_proxy("SESSDATA=abc", &proxy, DEFAULT_TIMEOUT_MS).unwrap();
+
+        let response: ApiResponse<serde_json::Value> = client.get("http://api.live.bilibili.com/test").await.unwrap();
+        assert!(response.is_success());
+
+        // 经过HTTP代理时请求行是完整的目标地址
+        let request = request.await.unwrap();
+        assert!(request.head.starts_with("GET http://api.live.bilibili.com/test"));
+    }
+
+    #[tokio::test]
+    async fn test_session_cookies_keep_proxy() {
+        let (proxy, request) = serve_once(r#"{"code":0,"message":"0","data":null}"#).await;
+        let client = BilibiliClient::with_proxy(&proxy, DEFAULT_TIMEOUT_MS).unwrap().with_retry_config(2, 1);
+        let session = client.with_session_cookies(&HashMap::from([("SESSDATA".to_string(), "abc".to_string())])).unwrap();
+        assert_eq!(session.retry_config(), client.retry_config());
+        assert_eq!(client.get_cookie("SESSDATA"), None);
+
+        let response: ApiResponse<serde_json::Value> = session.get("http://api.live.bilibili.com/test").await.unwrap();
+        assert!(response.is_success());
+        let request = request.await.unwrap();
+        assert!(request.head.starts_with("GET http://api.live.bilibili.com/test"));
+        assert!(request.header("cookie").is_some_and(|cookie| cookie.contains("SESSDATA=abc")));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_maps_to_timeout_error() {
+        // 只接受连接不返回响应的服务
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _socket = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let client = BilibiliClient::new(50).unwrap();
+        let result: Result<ApiResponse<serde_json::Value>> = client.get(&addr).await;
+
+        let err = result.unwrap_err();
+        assert!(matches!(err, BiliError::Timeout(_)), "{:?}", err);
+        assert!(err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_empty_form_fails_before_sending() {
+        // 端口不可达，若真的发出请求会得到网络错误而不是校验错误
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+        
+        let result: Result<ApiResponse<serde_json::Value>> = client.post("http://127.0.0.1:1/update", &[]).await;
+        assert!(matches!(result, Err(BiliError::Validation(ref msg)) if msg.contains("表单为空")));
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_post_form_keeps_business_code() {
+        let (addr, request) = serve_once(r#"{"code":10031,"message":"","msg":"频率过快","data":[]}"#).await;
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap().with_rate_limit(5);
+        
+        let response: ApiResponse<serde_json::Value> = client.post_form(&format!("{}/msg/send", addr), &[("msg", "你好")]).await.unwrap();
+        assert_eq!((response.code, response.get_message()), (10031, "频率过快"));
+        assert!(request.await.unwrap().body.contains("msg="));
+        
+        let result: Result<ApiResponse<serde_json::Value>> = client.post_form("http://127.0.0.1:1/msg/send", &[]).await;
+        assert!(matches!(result, Err(BiliError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_post_multipart_sends_file_part() {
+        let (addr, request) = serve_once(r#"{"code":0,"message":"0","data":null}"#).await;
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+
+        let response: ApiResponse<serde_json::Value> = client.post_multipart(&addr, || {
+            let part = reqwest::multipart::Part::bytes(b"PNGDATA".to_vec()).file_name("cover.png");
+            Ok(Form::new().text("bucket", "live").part("file", part))
+        }).await.unwrap();
+        assert!(response.is_success());
+
+        let request = request.await.unwrap();
+        assert!(request.header("content-type").unwrap().starts_with("multipart/form-data"));
+        assert!(request.body.contains("filename=\"cover.png\""));
+        assert!(request.body.contains("PNGDATA"));
+    }
+
+    /// 原先的正则解析实现，用来核对新解析器的结果
+    fn parse_cookies_regex(cookie_str: &str) -> HashMap<String, String> {
+        static COOKIE_RE: std::sync::LazyLock<regex::Regex> =
+            std::sync::LazyLock::new(|| regex::Regex::new(r"(\w+)=([^;]+)(?:;|$)").unwrap());
+        
+        COOKIE_RE.captures_iter(cookie_str)
+            .map(|cap| (cap[1].to_string(), urlencoding::decode(&cap[2]).unwrap().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_cookies_matches_regex_parser() {
+        let mut cookie_str = String::from(
+            "buvid3=ABCD-1234infoc; b_nut=1700000000; _uuid=F1-2E-3D; SESSDATA=a1b2%2Cc3d4%2A; \
+             bili_jct=0123456789abcdef; DedeUserID=42; DedeUserID__ckMd5=ffee; sid=x y z;",
+        );
+        // 边界情况：空值、缺少=、值中带=、键中带非单词字符、多余空格
+        cookie_str.push_str(" empty=; noequals; a=b=c; x-y=1; -=2=3; =lonely; trailing = spaced ;");
+        for i in 0..200 {
+            cookie_str.push_str(&format!(" bp_video_offset_{}=%E4%B8%AD{};", i, i));
+        }
+
+        let parsed = BilibiliClient::parse_cookies(&cookie_str).unwrap();
+        assert_eq!(parsed, parse_cookies_regex(&cookie_str));
+        assert_eq!(parsed["SESSDATA"], "a1b2,c3d4*");
+        assert_eq!(parsed["bp_video_offset_7"], "中7");
+        assert!(!parsed.contains_key("empty"));
+    }
+
+    #[test]
+    fn test_invalid_proxy_is_general_error() {
+        let result = BilibiliClient::with_proxy("::不是地址::", DEFAULT_TIMEOUT_MS);
+        assert!(matches!(result, Err(BiliError::General(_))));
+    }
+
+    #[tokio::test]
+    async fn test_with_builder_customization() {
+        let (addr, request) = crate::test_support::serve_once(r#"{"code":0,"message":"0","data":null}"#).await;
+        
+        let client = BilibiliClient::with_builder(|builder| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-custom-header", "hello".parse().unwrap());
+            builder.default_headers(headers)
+        }).unwrap();
+        
+        client.get::<serde_json::Value>(&format!("{}/x/test", addr)).await.unwrap();
+        
+        let request = request.await.unwrap();
+        assert_eq!(request.header("x-custom-header"), Some("hello"));
+        assert_eq!(request.header("user-agent"), Some(USER_AGENT));
+        assert!(request.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retries_service_unavailable() {
+        let (addr, served) = serve_responses(vec![
+            (503, "<html>Service Unavailable</html>"),
+            (200, r#"{"code":0,"message":"0","data":1}"#),
+        ]).await;
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap().with_retry_config(2, 1);
+        // 回调设置在克隆上，原客户端同样可见
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let recorded = attempts.clone();
+        client.clone().set_retry_hook(move |attempt| recorded.lock().unwrap().push((attempt.attempt, attempt.error.error_code())));
+
+        let response: ApiResponse<i32> = client.get(&addr).await.unwrap();
+        assert_eq!(response.data, Some(1));
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(*attempts.lock().unwrap(), vec![(1, Some(503))]);
+    }
+
+    #[tokio::test]
+    async fn test_business_error_not_retried() {
+        let (addr, served) = serve_responses(vec![
+            (200, r#"{"code":86038,"message":"二维码已失效","data":null}"#),
+        ]).await;
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap().with_retry_config(3, 1);
+
+        let result: Result<ApiResponse<i32>> = client.get(&format!("{}/x/passport/qrcode?key=abc", addr)).await;
+        let err = result.unwrap_err();
+        assert_eq!(err.error_code(), Some(86038));
+        // 错误信息附带接口路径，不含查询参数
+        assert_eq!(err.endpoint(), Some("/x/passport/qrcode"));
+        assert_eq!(err.to_string(), "API响应错误: code=86038, message=二维码已失效（接口: /x/passport/qrcode）");
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_maintenance_html_page() {
+        let body = "<html><head><title>系统维护中</title></head><body>哔哩哔哩正在维护，请稍后再试</body></html>";
+        let err = BilibiliClient::parse_body::<serde_json::Value>(503, body).unwrap_err();
+        
+        assert_eq!(err.to_string(), "API响应错误: code=503, message=B站正在维护，请稍后重试");
+        assert!(err.is_maintenance());
+        assert!(err.is_retryable());
+        assert_eq!(err.retry_backoff(Duration::from_secs(1)), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_maintenance_json_message() {
+        let body = r#"{"code":-500,"message":"系统维护中，请稍后重试","data":null}"#;
+        let err = BilibiliClient::parse_body::<serde_json::Value>(200, body).unwrap_err();
+        
+        assert!(err.is_maintenance());
+        assert!(err.is_retryable());
+        assert_eq!(err.error_code(), Some(-500));
+    }
+
+    #[test]
+    fn test_business_error_not_maintenance() {
+        let body = r#"{"code":-101,"message":"账号未登录","data":null}"#;
+        let err = BilibiliClient::parse_body::<serde_json::Value>(200, body).unwrap_err();
+        
+        assert!(!err.is_maintenance());
+        assert!(!err.is_retryable());
+        assert!(err.is_auth_error());
+    }
+
+    #[tokio::test]
+    async fn test_request_blocked_is_retried() {
+        let (addr, served) = serve_responses(vec![
+            (200, r#"{"code":-412,"message":"请求被拦截","data":null}"#),
+            (200, r#"{"code":0,"message":"0","data":1}"#),
+        ]).await;
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap().with_retry_config(3, 1);
+
+        let result: Result<ApiResponse<i32>> = client.get(&addr).await;
+        assert_eq!(result.unwrap().data, Some(1));
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+    }
+
+    #[tokio::test]
+    async fn test_risk_check_not_retried() {
+        let (addr, served) = serve_responses(vec![
+            (200, r#"{"code":-352,"message":"-352","data":null}"#),
+            (200, r#"{"code":0,"message":"0","data":1}"#),
+        ]).await;
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap().with_retry_config(3, 1);
+
+        let err = client.get::<i32>(&addr).await.unwrap_err();
+        assert!(err.is_risk_check());
+        assert!(!err.is_retryable());
+        assert!(!err.is_auth_error());
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+}
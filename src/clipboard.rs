@@ -0,0 +1,106 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::error::{Result, BiliError};
+
+/// 剪贴板不可用时写入的文件名
+const FALLBACK_FILE: &str = "bilibili_live_stream.txt";
+
+/// 文本实际写入的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CopyTarget {
+    /// 已写入系统剪贴板
+    Clipboard,
+    /// 剪贴板不可用（如纯SSH终端），已写入该文件
+    File(PathBuf),
+}
+
+/// 系统剪贴板
+///
+/// X11下剪贴板内容由写入的进程提供，需要在程序运行期间保留该实例。
+#[derive(Default)]
+pub struct Clipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 写入剪贴板，剪贴板不可用时写入fallback_dir下的文件
+    ///
+    /// fallback_dir应为当前用户私有的目录（如配置目录），不要使用共享的临时目录。
+    pub fn copy(&mut self, text: &str, fallback_dir: &Path) -> Result<CopyTarget> {
+        let inner = &mut self.inner;
+        copy_or_write(text, |text| {
+            let clipboard = match inner {
+                Some(clipboard) => clipboard,
+                None => inner.insert(arboard::Clipboard::new().map_err(|e| e.to_string())?),
+            };
+            clipboard.set_text(text).map_err(|e| e.to_string())
+        }, fallback_dir)
+    }
+}
+
+/// 先尝试set，失败时把文本写入dir下的文件
+fn copy_or_write<F>(text: &str, set: F, dir: &Path) -> Result<CopyTarget>
+where
+    F: FnOnce(&str) -> std::result::Result<(), String>,
+{
+    match set(text) {
+        Ok(()) => Ok(CopyTarget::Clipboard),
+        Err(reason) => {
+            let path = dir.join(FALLBACK_FILE);
+            write_private(&path, text)
+                .map_err(|e| BiliError::general(format!("剪贴板不可用（{}），写入文件也失败: {}", reason, e)))?;
+            Ok(CopyTarget::File(path))
+        }
+    }
+}
+
+/// 推流码不应被其他用户读取，unix下以0600权限写入
+///
+/// 先删除旧文件再以create_new新建，不会跟随预先放置的符号链接，也不会沿用旧文件的权限。
+fn write_private(path: &Path, text: &str) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_falls_back_to_file_without_clipboard() {
+        let dir = std::env::temp_dir().join(format!("bilibili_live_tool_clipboard_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let target = copy_or_write("rtmp://server/?streamname=live_1", |_| Err("no display".to_string()), &dir).unwrap();
+        let CopyTarget::File(path) = target else {
+            panic!("应写入文件");
+        };
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "rtmp://server/?streamname=live_1");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        // 再次写入时替换旧文件
+        copy_or_write("rtmp://server/?streamname=live_2", |_| Err("no display".to_string()), &dir).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "rtmp://server/?streamname=live_2");
+
+        assert_eq!(copy_or_write("text", |_| Ok(()), &dir).unwrap(), CopyTarget::Clipboard);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
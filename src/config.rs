@@ -1,21 +1,212 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::client::BilibiliClient;
 use crate::error::{Result, BiliError};
+use crate::credential::Credentials;
+use crate::live::{RoomInfo, StreamKey};
+use crate::secret::{self, SecretBox, PASSPHRASE_ENV};
+
+/// 默认账号档位名，旧版单账号配置中的认证信息即属于该档位
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// 覆盖配置文件认证信息的环境变量
+pub const COOKIE_ENV: &str = "BILI_COOKIE";
+pub const ROOM_ID_ENV: &str = "BILI_ROOM_ID";
+pub const CSRF_ENV: &str = "BILI_CSRF";
+
+/// 从环境变量读取的认证信息，优先级高于配置文件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvOverrides {
+    pub cookie_str: Option<String>,
+    pub room_id: Option<String>,
+    pub csrf: Option<String>,
+}
+
+impl EnvOverrides {
+    /// 读取当前进程的环境变量
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+    
+    /// 用给定的查找函数读取，未设置或为空的变量视为未提供
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let read = |name| lookup(name).map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+        Self {
+            cookie_str: read(COOKIE_ENV),
+            room_id: read(ROOM_ID_ENV),
+            csrf: read(CSRF_ENV),
+        }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.cookie_str.is_none() && self.room_id.is_none() && self.csrf.is_none()
+    }
+}
+
+/// 被环境变量覆盖前配置文件中的认证信息，保存时原样写回
+#[derive(Debug, Clone, Default)]
+pub struct FileCredentials {
+    pub room_id: Option<String>,
+    pub cookie_str: Option<String>,
+    pub csrf: Option<String>,
+    pub refresh_token: Option<String>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    /// 当前使用的账号档位，其认证信息保存在下面的顶层字段中
+    pub profile: String,
     pub room_id: Option<String>,
+    /// 手动指定要操作的直播间（可为短号），用于管理非登录账号的直播间；为None时使用登录账号的直播间
+    pub managed_room_id: Option<u64>,
     pub cookie_str: Option<String>,
     pub csrf: Option<String>,
+    /// 开启后cookie_str/csrf/refresh_token加密写入配置文件，口令读取环境变量 BILI_CONFIG_PASSPHRASE
+    pub encrypt: bool,
+    /// 扫码登录下发的refresh_token，用于cookie过期后免扫码刷新
+    pub refresh_token: Option<String>,
     pub last_settings: Option<LastSettings>,
     pub retry_count: u32,
     pub retry_delay: u64,
     pub timeout: u64,
+    /// 每秒最多发出的请求数，避免批量操作触发风控，为0时不限速
+    pub max_requests_per_second: u32,
+    /// 访问B站接口使用的代理（http/https/socks5），未设置时读取环境变量 HTTP_PROXY
+    pub proxy: Option<String>,
+    /// 工具访问的各服务地址，doctor按此检测连通性
+    pub endpoints: Endpoints,
+    /// 登录方式，默认扫码
+    pub login_method: LoginMethod,
+    /// 扫码登录时二维码失效后自动重新生成的最大次数
+    pub qr_refresh_limit: u32,
+    /// 扫码登录时同时用系统默认图片查看器打开二维码图片
+    pub qr_open_viewer: bool,
+    /// 启动时因网络异常无法验证cookie时，继续使用已保存的cookie而不是重新扫码
+    pub offline_grace: bool,
     // 推流信息
     pub stream_server: Option<String>,
-    pub stream_key: Option<String>,
+    pub stream_key: Option<StreamKey>,
+    pub stream_saved_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 开播请求已发出但推流码尚未保存，进程中断后据此找回推流码
+    pub pending_start_at: Option<chrono::DateTime<chrono::Utc>>,
+    // 界面设置
+    /// TUI是否使用备用屏幕，关闭后保留终端滚动记录
+    pub tui_alternate_screen: bool,
+    /// 开播/下播操作之间的最小间隔（秒）
+    pub toggle_cooldown_secs: u64,
+    /// 推流码在界面上的显示方式
+    pub stream_key_visibility: StreamKeyVisibility,
+    /// 标题过长时自动截断，关闭时返回错误
+    pub auto_truncate_title: bool,
+    /// 启动时自动把直播间分区恢复为上次使用的分区
+    pub auto_apply_last_area: bool,
+    /// 偏好的推流协议（rtmp/srt），直播间未提供时回落到rtmp
+    pub stream_protocol: String,
+    /// 弹幕面板在内存中保留的最大消息条数
+    pub danmaku_buffer: usize,
+    /// 发送弹幕前过滤的敏感词，为空时关闭过滤
+    pub sensitive_words: Vec<String>,
+    /// 开播成功后自动逐条发送的弹幕
+    pub auto_danmaku: Vec<String>,
+    /// 调试用：关闭App签名和WBI签名
+    pub disable_signing: bool,
+    // 通知设置
+    /// 开播/下播时POST事件JSON的地址，未设置时只记录日志
+    pub webhook_url: Option<String>,
+    /// 未在使用的其他账号档位
+    pub profiles: HashMap<String, Credentials>,
+    /// 加载时使用的配置文件路径，之后的保存都写回该文件；为None时使用默认路径
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    /// 加载时合并的环境变量
+    #[serde(skip)]
+    pub env: EnvOverrides,
+    /// 合并环境变量前配置文件中的认证信息，为None时没有被覆盖
+    #[serde(skip)]
+    pub file_credentials: Option<FileCredentials>,
+}
+
+/// 登录方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginMethod {
+    /// 手机B站扫码
+    #[default]
+    Qr,
+    /// 短信验证码，手机无法扫码时使用
+    Sms,
+}
+
+/// 推流码显示方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKeyVisibility {
+    /// 显示完整推流码
+    #[default]
+    Always,
+    /// 显示为 `live_***`
+    Masked,
+    /// 界面上不显示，只在导出文件时写出
+    Hidden,
 }
 
+impl StreamKeyVisibility {
+    /// 按显示方式格式化推流码，Hidden时返回None
+    pub fn display(&self, key: &StreamKey) -> Option<String> {
+        match self {
+            StreamKeyVisibility::Always => Some(key.reveal().to_string()),
+            StreamKeyVisibility::Masked => Some(format!("{:?}", key)),
+            StreamKeyVisibility::Hidden => None,
+        }
+    }
+}
+
+/// 工具访问的各服务地址
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Endpoints {
+    /// 登录相关接口
+    pub passport: String,
+    /// 直播接口
+    pub live_api: String,
+    /// 主站接口，如nav和WBI
+    pub main_api: String,
+    /// 弹幕WebSocket服务器
+    pub danmaku_ws: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            passport: "https://passport.bilibili.com".to_string(),
+            live_api: "https://api.live.bilibili.com".to_string(),
+            main_api: "https://api.bilibili.com".to_string(),
+            danmaku_ws: "wss://broadcastlv.chat.bilibili.com".to_string(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// 按名称列出全部地址
+    pub fn entries(&self) -> [(&'static str, &str); 4] {
+        [
+            ("passport", &self.passport),
+            ("live-api", &self.live_api),
+            ("main-api", &self.main_api),
+            ("danmaku-ws", &self.danmaku_ws),
+        ]
+    }
+}
+
+/// 默认每秒最多发出的请求数
+pub const DEFAULT_MAX_REQUESTS_PER_SECOND: u32 = 5;
+
+/// 推流信息超过该时长视为可能失效
+pub const STREAM_INFO_MAX_AGE_HOURS: i64 = 6;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LastSettings {
     pub live_title: String,
@@ -24,52 +215,106 @@ pub struct LastSettings {
     pub last_used: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+impl LastSettings {
+    /// 保存的分区ID，优先使用子分区
+    pub fn area(&self) -> Option<u32> {
+        self.sub_area_id.or(self.area_id)
+    }
+    
+    /// 既没有标题也没有分区时视为空
+    pub fn is_empty(&self) -> bool {
+        self.live_title.is_empty() && self.area().is_none()
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
+            profile: DEFAULT_PROFILE.to_string(),
             room_id: None,
+            managed_room_id: None,
             cookie_str: None,
             csrf: None,
+            encrypt: false,
+            refresh_token: None,
             last_settings: None,
             retry_count: 3,
             retry_delay: 1000,
-            timeout: 30000,
+            timeout: crate::client::DEFAULT_TIMEOUT_MS,
+            max_requests_per_second: DEFAULT_MAX_REQUESTS_PER_SECOND,
+            proxy: None,
+            endpoints: Endpoints::default(),
+            login_method: LoginMethod::Qr,
+            qr_refresh_limit: crate::auth::DEFAULT_QR_REFRESH_LIMIT,
+            qr_open_viewer: false,
+            offline_grace: false,
             stream_server: None,
             stream_key: None,
+            stream_saved_at: None,
+            pending_start_at: None,
+            tui_alternate_screen: true,
+            toggle_cooldown_secs: 3,
+            stream_key_visibility: StreamKeyVisibility::Always,
+            auto_truncate_title: false,
+            auto_apply_last_area: false,
+            stream_protocol: crate::live::DEFAULT_STREAM_PROTOCOL.to_string(),
+            danmaku_buffer: 200,
+            sensitive_words: Vec::new(),
+            auto_danmaku: Vec::new(),
+            disable_signing: false,
+            webhook_url: None,
+            profiles: HashMap::new(),
+            path: None,
+            env: EnvOverrides::default(),
+            file_credentials: None,
         }
     }
 }
 
 impl Config {
-    /// 加载配置文件
+    /// 加载默认路径（程序所在目录）下的配置文件
     pub fn load() -> Result<Self> {
-        let config_path = Self::get_config_path();
+        Self::load_from(&Self::get_config_path())
+    }
+    
+    /// 加载指定路径的配置文件，不存在时创建默认配置；之后的保存都写回该路径
+    ///
+    /// 加载后合并 `BILI_COOKIE`、`BILI_ROOM_ID`、`BILI_CSRF` 环境变量，见 [`Config::merge_env`]。
+    pub fn load_from(config_path: &Path) -> Result<Self> {
         if !config_path.exists() {
-            let config = Self::default();
+            let mut config = Self { path: Some(config_path.to_path_buf()), ..Self::default() };
             config.save()?;
+            config.merge_env(EnvOverrides::from_env());
             return Ok(config);
         }
         
-        let config_str = std::fs::read_to_string(&config_path)
+        let config_str = std::fs::read_to_string(config_path)
             .map_err(|e| BiliError::general(format!("读取配置文件失败: {}", e)))?;
         
-        let config: Config = toml::from_str(&config_str)
+        let mut config: Config = toml::from_str(&config_str)
             .map_err(|e| BiliError::general(format!("解析配置文件失败: {}", e)))?;
+        config.open_secrets(secret::passphrase_from_env().as_deref())?;
+        config.path = Some(config_path.to_path_buf());
+        config.merge_env(EnvOverrides::from_env());
         
         Ok(config)
     }
     
-    /// 保存配置
+    /// 保存配置到加载时的文件
     pub fn save(&self) -> Result<()> {
-        let config_path = Self::get_config_path();
-        
+        self.save_to(&self.config_path())
+    }
+    
+    /// 保存配置到指定路径
+    pub fn save_to(&self, config_path: &Path) -> Result<()> {
         // 确保目录存在
         if let Some(parent) = config_path.parent() {
             std::fs::create_dir_all(parent)
                 .map_err(|e| BiliError::general(format!("创建配置目录失败: {}", e)))?;
         }
         
-        let config_str = toml::to_string_pretty(self)
+        let stored = self.to_stored(secret::passphrase_from_env().as_deref())?;
+        let config_str = toml::to_string_pretty(&stored)
             .map_err(|e| BiliError::general(format!("序列化配置失败: {}", e)))?;
         
         std::fs::write(config_path, config_str)
@@ -78,20 +323,153 @@ impl Config {
         Ok(())
     }
     
+    /// 所有账号档位的cookie_str、csrf和refresh_token
+    fn secret_fields(&mut self) -> Vec<&mut String> {
+        let mut fields: Vec<&mut String> = self.cookie_str.iter_mut()
+            .chain(self.csrf.iter_mut())
+            .chain(self.refresh_token.iter_mut())
+            .collect();
+        for creds in self.profiles.values_mut() {
+            fields.push(&mut creds.cookie_str);
+            fields.push(&mut creds.csrf);
+            fields.extend(creds.refresh_token.iter_mut());
+        }
+        fields
+    }
+    
+    /// 解密配置中的加密字段，旧版明文字段原样保留
+    fn open_secrets(&mut self, passphrase: Option<&str>) -> Result<()> {
+        let fields: Vec<&mut String> = self.secret_fields().into_iter()
+            .filter(|field| secret::is_encrypted(field))
+            .collect();
+        if fields.is_empty() {
+            return Ok(());
+        }
+        
+        let passphrase = passphrase.ok_or_else(|| BiliError::Auth(
+            format!("配置中的cookie已加密，请设置环境变量 {}", PASSPHRASE_ENV)
+        ))?;
+        let mut secrets = SecretBox::new(passphrase);
+        for field in fields {
+            *field = secrets.decrypt(field)?;
+        }
+        Ok(())
+    }
+    
+    /// 生成写入文件的配置，开启encrypt时加密认证字段，明文配置在此时升级为加密格式
+    fn to_stored(&self, passphrase: Option<&str>) -> Result<Config> {
+        static WARNED: AtomicBool = AtomicBool::new(false);
+        
+        let mut stored = self.clone();
+        if let Some(file) = &self.file_credentials {
+            stored.room_id = file.room_id.clone();
+            stored.cookie_str = file.cookie_str.clone();
+            stored.csrf = file.csrf.clone();
+            stored.refresh_token = file.refresh_token.clone();
+        }
+        if !self.encrypt {
+            return Ok(stored);
+        }
+        let Some(passphrase) = passphrase else {
+            if !WARNED.swap(true, Ordering::Relaxed) {
+                eprintln!("警告: 已开启encrypt但未设置环境变量 {}，cookie仍以明文保存", PASSPHRASE_ENV);
+            }
+            return Ok(stored);
+        };
+        
+        let mut secrets = SecretBox::new(passphrase);
+        for field in stored.secret_fields() {
+            if !secret::is_encrypted(field) {
+                *field = secrets.encrypt(field)?;
+            }
+        }
+        Ok(stored)
+    }
+    
+    /// 用环境变量覆盖认证信息
+    ///
+    /// 只提供cookie时从其中的bili_jct取csrf；未同时提供房间号时清空配置文件中的房间号，
+    /// 需由调用方按 [`Config::env_room_uid`] 查询。覆盖的值只在内存中生效，保存时写回文件原有的认证信息。
+    pub fn merge_env(&mut self, env: EnvOverrides) {
+        if env.is_empty() {
+            return;
+        }
+        self.file_credentials.get_or_insert_with(|| FileCredentials {
+            room_id: self.room_id.clone(),
+            cookie_str: self.cookie_str.clone(),
+            csrf: self.csrf.clone(),
+            refresh_token: self.refresh_token.clone(),
+        });
+        
+        if let Some(cookie_str) = &env.cookie_str {
+            // 配置文件中的csrf、房间号和refresh_token可能属于另一个账号
+            self.csrf = BilibiliClient::parse_cookies(cookie_str).ok()
+                .and_then(|cookies| cookies.get("bili_jct").cloned());
+            self.room_id = None;
+            self.refresh_token = None;
+            self.cookie_str = Some(cookie_str.clone());
+        }
+        if let Some(csrf) = &env.csrf {
+            self.csrf = Some(csrf.clone());
+        }
+        if let Some(room_id) = &env.room_id {
+            self.room_id = Some(room_id.clone());
+        }
+        self.env = env;
+    }
+    
+    /// 认证信息是否来自环境变量
+    pub fn has_env_credentials(&self) -> bool {
+        !self.env.is_empty()
+    }
+    
+    /// 环境变量只提供了cookie时，需要据此查询房间号的用户ID（DedeUserID）
+    pub fn env_room_uid(&self) -> Option<u64> {
+        if self.env.cookie_str.is_none() || self.room_id.is_some() {
+            return None;
+        }
+        let cookies = BilibiliClient::parse_cookies(self.cookie_str.as_deref()?).ok()?;
+        cookies.get("DedeUserID")?.parse().ok()
+    }
+    
+    /// 实际使用的代理地址，配置文件优先于环境变量
+    pub fn proxy_url(&self) -> Option<String> {
+        Self::resolve_proxy(self.proxy.as_deref(), std::env::var("HTTP_PROXY").ok().as_deref())
+    }
+    
+    fn resolve_proxy(configured: Option<&str>, env: Option<&str>) -> Option<String> {
+        configured
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .or_else(|| env.map(str::trim).filter(|url| !url.is_empty()))
+            .map(str::to_string)
+    }
+    
     /// 获取程序根目录
     fn get_app_dir() -> PathBuf {
         // 优先尝试获取可执行文件所在目录
-        if let Ok(exe_path) = std::env::current_exe() {
-            if let Some(parent) = exe_path.parent() {
-                return parent.to_path_buf();
-            }
+        if let Ok(exe_path) = std::env::current_exe()
+            && let Some(parent) = exe_path.parent()
+        {
+            return parent.to_path_buf();
         }
         
         // 如果获取不到，使用当前工作目录
         std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
     }
     
-    /// 获取配置文件路径
+    /// 指定之后保存时写入的配置文件
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+    
+    /// 当前配置对应的文件路径，未从文件加载时为默认路径
+    pub fn config_path(&self) -> PathBuf {
+        self.path.clone().unwrap_or_else(Self::get_config_path)
+    }
+    
+    /// 获取默认配置文件路径
     pub fn get_config_path() -> PathBuf {
         let mut path = Self::get_app_dir();
         path.push("config.toml");
@@ -126,11 +504,76 @@ impl Config {
         self.csrf = Some(csrf);
     }
     
+    /// 获取认证信息
+    pub fn get_credentials(&self) -> Option<Credentials> {
+        match (&self.room_id, &self.cookie_str, &self.csrf) {
+            (Some(room_id), Some(cookie_str), Some(csrf)) => Some(Credentials {
+                room_id: room_id.clone(),
+                cookie_str: cookie_str.clone(),
+                csrf: csrf.clone(),
+                refresh_token: self.refresh_token.clone(),
+            }),
+            _ => None,
+        }
+    }
+    
     /// 清除认证信息
     pub fn clear_credentials(&mut self) {
         self.room_id = None;
         self.cookie_str = None;
         self.csrf = None;
+        self.refresh_token = None;
+    }
+    
+    /// 切换到指定账号档位，当前档位的认证信息会被保留，目标档位不存在时需要重新登录
+    pub fn use_profile(&mut self, name: &str) {
+        if name == self.profile {
+            return;
+        }
+        
+        // 环境变量提供的认证信息不属于任何档位，归档前换回配置文件中的值
+        if let Some(file) = self.file_credentials.take() {
+            self.room_id = file.room_id;
+            self.cookie_str = file.cookie_str;
+            self.csrf = file.csrf;
+            self.refresh_token = file.refresh_token;
+            self.env = EnvOverrides::default();
+        }
+        if let Some(creds) = self.get_credentials() {
+            self.profiles.insert(self.profile.clone(), creds);
+        }
+        self.clear_credentials();
+        // 推流信息属于原账号的直播间，切换后不再有效
+        self.stream_server = None;
+        self.stream_key = None;
+        self.stream_saved_at = None;
+        self.pending_start_at = None;
+        if let Some(creds) = self.profiles.remove(name) {
+            self.set_credentials(creds.room_id, creds.cookie_str, creds.csrf);
+            self.refresh_token = creds.refresh_token;
+        }
+        self.profile = name.to_string();
+    }
+    
+    /// 所有账号档位名（包含当前档位），按名称排序
+    pub fn list_profiles(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.profiles.keys()
+            .map(String::as_str)
+            .chain(std::iter::once(self.profile.as_str()))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+    
+    /// 删除账号档位，删除当前档位时只清除其认证信息；返回档位是否存在
+    pub fn remove_profile(&mut self, name: &str) -> bool {
+        if name == self.profile {
+            let existed = self.has_credentials();
+            self.clear_credentials();
+            return existed;
+        }
+        self.profiles.remove(name).is_some()
     }
     
     /// 保存最近的设置
@@ -144,39 +587,96 @@ impl Config {
         self.save()
     }
     
+    /// 记录最近使用的分区，保留上次的标题
+    pub fn remember_area(&mut self, area_id: Option<u32>, sub_area_id: u32) -> Result<()> {
+        let title = self.last_settings.as_ref()
+            .map(|settings| settings.live_title.clone())
+            .unwrap_or_default();
+        self.save_last_settings(title, area_id, Some(sub_area_id))
+    }
+    
+    /// 开播成功后记录直播间当前的标题和分区，供"使用上次设置开播"恢复
+    pub fn remember_live_settings(&mut self, room_info: &RoomInfo) -> Result<()> {
+        let nonzero = |id: u32| (id != 0).then_some(id);
+        self.save_last_settings(room_info.title.clone(), nonzero(room_info.parent_area_id), nonzero(room_info.area_id))
+    }
+    
+    /// 可以用于"使用上次设置开播"的设置，没有历史记录时返回None
+    pub fn last_live_settings(&self) -> Option<&LastSettings> {
+        self.last_settings.as_ref().filter(|settings| !settings.is_empty())
+    }
+    
+    /// 需要自动恢复的分区ID
+    ///
+    /// 未开启自动恢复、没有保存分区、与当前分区相同或保存的分区已不存在时返回None。
+    pub fn last_area_to_apply(&self, current_area: u32, exists: impl Fn(u32) -> bool) -> Option<u32> {
+        if !self.auto_apply_last_area {
+            return None;
+        }
+        
+        let settings = self.last_settings.as_ref()?;
+        let stored = settings.area()?;
+        (stored != current_area && exists(stored)).then_some(stored)
+    }
+    
     /// 获取房间ID
     pub fn get_room_id(&self) -> Option<u64> {
         self.room_id.as_ref().and_then(|id| id.parse().ok())
     }
     
-    /// 保存推流信息（安全保存，不会覆盖其他配置）
-    pub fn save_stream_info(&mut self, server: String, key: String) -> Result<()> {
-        // 重新加载最新的配置文件，确保不丢失其他设置
-        let mut latest_config = Self::load()?;
-        latest_config.stream_server = Some(server);
-        latest_config.stream_key = Some(key);
-        latest_config.save()?;
-        
-        // 更新当前实例的推流信息
-        self.stream_server = latest_config.stream_server.clone();
-        self.stream_key = latest_config.stream_key.clone();
-        
-        Ok(())
+    /// 用B站返回的房间号校正已保存的房间号，返回是否发生了变化
+    pub fn reconcile_room_id(&mut self, fetched: u64) -> bool {
+        if self.get_room_id() == Some(fetched) {
+            return false;
+        }
+        self.room_id = Some(fetched.to_string());
+        true
     }
     
-    /// 清除推流信息（安全清除，不会覆盖其他配置）
+    /// 保存推流信息
+    ///
+    /// 直接修改当前实例并写回其配置文件，内存中尚未保存的凭证和设置一并写入，不会被文件中的旧值覆盖。
+    pub fn save_stream_info(&mut self, server: String, key: StreamKey) -> Result<()> {
+        self.apply_stream_info(server, key);
+        self.save()
+    }
+    
+    /// 写入推流信息并记录保存时间
+    fn apply_stream_info(&mut self, server: String, key: StreamKey) {
+        self.stream_server = Some(server);
+        self.stream_key = Some(key);
+        self.stream_saved_at = Some(chrono::Utc::now());
+        self.pending_start_at = None;
+    }
+    
+    /// 开播前写入待确认标记，推流码保存后自动清除
+    pub fn mark_pending_start(&mut self) -> Result<()> {
+        self.pending_start_at = Some(chrono::Utc::now());
+        self.save()
+    }
+    
+    /// 清除待确认的开播标记
+    pub fn clear_pending_start(&mut self) -> Result<()> {
+        self.pending_start_at = None;
+        self.save()
+    }
+    
+    /// 是否存在未确认的开播请求
+    pub fn has_pending_start(&self) -> bool {
+        self.pending_start_at.is_some()
+    }
+    
+    /// 清除推流信息并写回当前实例的配置文件
     pub fn clear_stream_info(&mut self) -> Result<()> {
-        // 重新加载最新的配置文件，确保不丢失其他设置
-        let mut latest_config = Self::load()?;
-        latest_config.stream_server = None;
-        latest_config.stream_key = None;
-        latest_config.save()?;
-        
-        // 更新当前实例的推流信息
         self.stream_server = None;
         self.stream_key = None;
-        
-        Ok(())
+        self.stream_saved_at = None;
+        self.save()
+    }
+    
+    /// 获取开播/下播冷却时间
+    pub fn toggle_cooldown(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.toggle_cooldown_secs)
     }
     
     /// 检查是否有推流信息
@@ -184,12 +684,271 @@ impl Config {
         self.stream_server.is_some() && self.stream_key.is_some()
     }
     
+    /// 保存的推流信息是否已过期，没有保存时间的旧记录视为过期
+    pub fn is_stream_info_stale(&self, max_age: chrono::Duration) -> bool {
+        if !self.has_stream_info() {
+            return false;
+        }
+        match self.stream_saved_at {
+            Some(saved_at) => chrono::Utc::now() - saved_at > max_age,
+            None => true,
+        }
+    }
+    
     /// 获取推流信息
-    pub fn get_stream_info(&self) -> Option<(String, String)> {
+    pub fn get_stream_info(&self) -> Option<(String, StreamKey)> {
         if let (Some(server), Some(key)) = (&self.stream_server, &self.stream_key) {
             Some((server.clone(), key.clone()))
         } else {
             None
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_overrides_take_priority_and_are_not_saved() {
+        let mut config: Config = toml::from_str(r#"
+            room_id = "123456"
+            cookie_str = "SESSDATA=abc; bili_jct=def"
+            csrf = "def"
+            refresh_token = "token"
+        "#).unwrap();
+        let env = EnvOverrides::from_lookup(|name| match name {
+            COOKIE_ENV => Some("SESSDATA=xyz; bili_jct=jct; DedeUserID=42".to_string()),
+            ROOM_ID_ENV => Some(" ".to_string()),
+            _ => None,
+        });
+        assert_eq!(env.room_id, None);
+        config.merge_env(env);
+        assert!(config.has_env_credentials());
+        assert_eq!(config.csrf.as_deref(), Some("jct"));
+        assert_eq!(config.refresh_token, None);
+        assert_eq!(config.env_room_uid(), Some(42));
+        assert!(!config.has_credentials());
+
+        config.reconcile_room_id(7);
+        assert_eq!(config.env_room_uid(), None);
+        assert_eq!(config.get_credentials().unwrap().cookie_str, "SESSDATA=xyz; bili_jct=jct; DedeUserID=42");
+
+        let stored = config.to_stored(None).unwrap();
+        assert_eq!(stored.room_id.as_deref(), Some("123456"));
+        assert_eq!(stored.cookie_str.as_deref(), Some("SESSDATA=abc; bili_jct=def"));
+        assert_eq!(stored.refresh_token.as_deref(), Some("token"));
+
+        // 显式提供的csrf和房间号优先于从cookie推出的值
+        let mut config = Config::default();
+        config.merge_env(EnvOverrides {
+            cookie_str: Some("bili_jct=jct; DedeUserID=42".to_string()),
+            room_id: Some("99".to_string()),
+            csrf: Some("explicit".to_string()),
+        });
+        assert_eq!(config.get_room_id(), Some(99));
+        assert_eq!(config.csrf.as_deref(), Some("explicit"));
+        assert_eq!(config.env_room_uid(), None);
+    }
+
+    #[test]
+    fn test_single_account_config_becomes_default_profile() {
+        let legacy = r#"
+            room_id = "123456"
+            cookie_str = "SESSDATA=abc; bili_jct=def"
+            csrf = "def"
+        "#;
+        let mut config: Config = toml::from_str(legacy).unwrap();
+        assert_eq!(config.list_profiles(), vec![DEFAULT_PROFILE]);
+
+        // 切换到新账号后旧账号保留在default档位
+        config.use_profile("alt");
+        assert!(!config.has_credentials());
+        config.set_credentials("654321".to_string(), "SESSDATA=xyz".to_string(), "xyz".to_string());
+        assert_eq!(config.list_profiles(), vec!["alt", DEFAULT_PROFILE]);
+
+        let saved = toml::to_string_pretty(&config).unwrap();
+        let mut config: Config = toml::from_str(&saved).unwrap();
+        config.use_profile(DEFAULT_PROFILE);
+        assert_eq!(config.get_room_id(), Some(123456));
+        assert_eq!(config.profiles["alt"].room_id, "654321");
+
+        assert!(config.remove_profile("alt"));
+        assert!(!config.remove_profile("alt"));
+        assert_eq!(config.list_profiles(), vec![DEFAULT_PROFILE]);
+    }
+
+    #[test]
+    fn test_plaintext_config_upgrades_to_encrypted() {
+        let legacy = r#"
+            encrypt = true
+            room_id = "123456"
+            cookie_str = "SESSDATA=abc; bili_jct=def"
+            csrf = "def"
+            refresh_token = "refresh-abc"
+            
+            [profiles.alt]
+            room_id = "654321"
+            cookie_str = "SESSDATA=xyz; bili_jct=uvw"
+            csrf = "uvw"
+            refresh_token = "refresh-xyz"
+        "#;
+        let mut config: Config = toml::from_str(legacy).unwrap();
+        config.open_secrets(None).unwrap();
+        assert_eq!(config.csrf.as_deref(), Some("def"));
+
+        // 未设置口令时仍以明文保存
+        assert_eq!(config.to_stored(None).unwrap().csrf.as_deref(), Some("def"));
+
+        let saved = toml::to_string_pretty(&config.to_stored(Some("口令")).unwrap()).unwrap();
+        assert!(!saved.contains("SESSDATA"));
+        assert!(!saved.contains("refresh-"));
+        let mut loaded: Config = toml::from_str(&saved).unwrap();
+        assert!(loaded.clone().open_secrets(None).is_err());
+        loaded.open_secrets(Some("口令")).unwrap();
+        assert_eq!(loaded.cookie_str, config.cookie_str);
+        assert_eq!(loaded.csrf.as_deref(), Some("def"));
+        assert_eq!(loaded.refresh_token.as_deref(), Some("refresh-abc"));
+        assert_eq!(loaded.profiles["alt"].refresh_token.as_deref(), Some("refresh-xyz"));
+    }
+
+    #[test]
+    fn test_custom_config_path_is_kept_for_saves() {
+        let dir = std::env::temp_dir().join(format!("bilibili_live_tool_config_{}", std::process::id()));
+        let path = dir.join("custom.toml");
+        let mut config = Config::load_from(&path).unwrap();
+        assert!(path.exists());
+        assert_eq!(config.config_path(), path);
+
+        config.room_id = Some("42".to_string());
+        config.save().unwrap();
+        config.save_stream_info("rtmp://server/".to_string(), StreamKey::new("key")).unwrap();
+
+        let loaded = Config::load_from(&path).unwrap();
+        assert_eq!(loaded.room_id.as_deref(), Some("42"));
+        assert_eq!(loaded.stream_server.as_deref(), Some("rtmp://server/"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stream_info_keeps_unsaved_settings() {
+        let dir = std::env::temp_dir().join(format!("bilibili_live_tool_stream_{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let mut config = Config::load_from(&path).unwrap();
+        config.last_settings = Some(LastSettings {
+            live_title: "测试直播".to_string(),
+            area_id: Some(1),
+            sub_area_id: Some(86),
+            last_used: None,
+        });
+
+        config.save_stream_info("rtmp://server/".to_string(), StreamKey::new("key")).unwrap();
+        let loaded = Config::load_from(&path).unwrap();
+        assert_eq!(loaded.last_settings.as_ref().map(|s| s.live_title.as_str()), Some("测试直播"));
+        assert!(loaded.has_stream_info());
+
+        config.clear_stream_info().unwrap();
+        let loaded = Config::load_from(&path).unwrap();
+        assert!(!loaded.has_stream_info());
+        assert_eq!(loaded.last_settings.and_then(|s| s.sub_area_id), Some(86));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remember_live_settings() {
+        let dir = std::env::temp_dir().join(format!("bilibili_live_tool_last_{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let mut config = Config::load_from(&path).unwrap();
+        assert!(config.last_live_settings().is_none());
+        config.last_settings = Some(LastSettings {
+            live_title: String::new(),
+            area_id: None,
+            sub_area_id: None,
+            last_used: None,
+        });
+        assert!(config.last_live_settings().is_none());
+
+        let room_info: RoomInfo = serde_json::from_value(serde_json::json!({
+            "room_id": 1000, "title": "今晚打原神", "area_id": "321", "parent_area_id": "3",
+        })).unwrap();
+        config.remember_live_settings(&room_info).unwrap();
+
+        let loaded = Config::load_from(&path).unwrap();
+        let settings = loaded.last_live_settings().unwrap();
+        assert_eq!(settings.live_title, "今晚打原神");
+        assert_eq!(settings.area(), Some(321));
+        assert_eq!(settings.area_id, Some(3));
+        assert!(settings.last_used.is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_last_area_to_apply() {
+        let mut config = Config {
+            auto_apply_last_area: true,
+            last_settings: Some(LastSettings {
+                live_title: String::new(),
+                area_id: Some(6),
+                sub_area_id: Some(235),
+                last_used: None,
+            }),
+            ..Config::default()
+        };
+        let exists = |id: u32| id == 235;
+
+        // 与当前分区不同时恢复，相同时跳过
+        assert_eq!(config.last_area_to_apply(744, exists), Some(235));
+        assert_eq!(config.last_area_to_apply(235, exists), None);
+        // 保存的分区已下线
+        assert_eq!(config.last_area_to_apply(744, |_| false), None);
+
+        config.auto_apply_last_area = false;
+        assert_eq!(config.last_area_to_apply(744, exists), None);
+
+        config.auto_apply_last_area = true;
+        config.last_settings = None;
+        assert_eq!(config.last_area_to_apply(744, exists), None);
+    }
+
+    #[test]
+    fn test_configured_proxy_overrides_env() {
+        assert_eq!(
+            Config::resolve_proxy(Some("socks5://127.0.0.1:1080"), Some("http://env:8080")).as_deref(),
+            Some("socks5://127.0.0.1:1080")
+        );
+        assert_eq!(Config::resolve_proxy(Some("  "), Some("http://env:8080")).as_deref(), Some("http://env:8080"));
+        assert_eq!(Config::resolve_proxy(None, None), None);
+    }
+
+    #[test]
+    fn test_reconcile_room_id() {
+        let mut config = Config {
+            room_id: Some("1000".to_string()),
+            ..Config::default()
+        };
+        
+        assert!(config.reconcile_room_id(2000));
+        assert_eq!(config.get_room_id(), Some(2000));
+        
+        assert!(!config.reconcile_room_id(2000));
+        assert_eq!(config.room_id.as_deref(), Some("2000"));
+    }
+
+    #[test]
+    fn test_stream_info_timestamp_and_staleness() {
+        let max_age = chrono::Duration::hours(STREAM_INFO_MAX_AGE_HOURS);
+        let mut config = Config::default();
+        assert!(!config.is_stream_info_stale(max_age));
+        
+        config.apply_stream_info("rtmp://example/live/".to_string(), StreamKey::new("?streamname=live_1"));
+        let saved_at = config.stream_saved_at.expect("应记录保存时间");
+        assert!(chrono::Utc::now() - saved_at < chrono::Duration::seconds(5));
+        assert!(!config.is_stream_info_stale(max_age));
+        
+        config.stream_saved_at = Some(chrono::Utc::now() - chrono::Duration::hours(STREAM_INFO_MAX_AGE_HOURS + 1));
+        assert!(config.is_stream_info_stale(max_age));
+        
+        config.stream_saved_at = None;
+        assert!(config.is_stream_info_stale(max_age));
+    }
+}
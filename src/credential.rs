@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use crate::config::Config;
+use crate::error::{Result, BiliError};
+
+/// 登录凭证
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credentials {
+    pub room_id: String,
+    pub cookie_str: String,
+    pub csrf: String,
+    /// 刷新cookie用的令牌
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+/// 凭证存储后端
+///
+/// 默认实现为 [`TomlStore`]，钥匙串、加密或不落盘等后端只需实现该 trait 即可接入。
+pub trait CredentialStore: Send + Sync {
+    /// 读取凭证，未保存时返回 `BiliError::NotFound`
+    fn load(&self) -> Result<Credentials>;
+
+    /// 保存凭证
+    fn save(&self, creds: &Credentials) -> Result<()>;
+
+    /// 清除凭证
+    fn clear(&self) -> Result<()>;
+}
+
+/// 基于 config.toml 的凭证存储
+#[derive(Debug, Clone, Default)]
+pub struct TomlStore {
+    /// 配置文件路径，为None时使用默认路径
+    path: Option<PathBuf>,
+}
+
+impl TomlStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 使用指定路径的配置文件
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self { path: Some(path.into()) }
+    }
+    
+    fn load_config(&self) -> Result<Config> {
+        match &self.path {
+            Some(path) => Config::load_from(path),
+            None => Config::load(),
+        }
+    }
+}
+
+impl CredentialStore for TomlStore {
+    fn load(&self) -> Result<Credentials> {
+        let config = self.load_config()?;
+        config.get_credentials()
+            .ok_or_else(|| BiliError::not_found("未保存认证信息"))
+    }
+
+    fn save(&self, creds: &Credentials) -> Result<()> {
+        let mut config = self.load_config()?;
+        config.set_credentials(creds.room_id.clone(), creds.cookie_str.clone(), creds.csrf.clone());
+        config.refresh_token = creds.refresh_token.clone();
+        config.save()
+    }
+
+    fn clear(&self) -> Result<()> {
+        let mut config = self.load_config()?;
+        config.clear_credentials();
+        config.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        creds: Mutex<Option<Credentials>>,
+    }
+
+    impl CredentialStore for MemoryStore {
+        fn load(&self) -> Result<Credentials> {
+            self.creds.lock().unwrap().clone()
+                .ok_or_else(|| BiliError::not_found("未保存认证信息"))
+        }
+
+        fn save(&self, creds: &Credentials) -> Result<()> {
+            *self.creds.lock().unwrap() = Some(creds.clone());
+            Ok(())
+        }
+
+        fn clear(&self) -> Result<()> {
+            *self.creds.lock().unwrap() = None;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_memory_store_roundtrip() {
+        let store: Box<dyn CredentialStore> = Box::new(MemoryStore::default());
+        assert!(matches!(store.load(), Err(BiliError::NotFound(_))));
+
+        let creds = Credentials {
+            room_id: "123456".to_string(),
+            cookie_str: "SESSDATA=abc; bili_jct=def".to_string(),
+            csrf: "def".to_string(),
+            refresh_token: None,
+        };
+        store.save(&creds).unwrap();
+        assert_eq!(store.load().unwrap(), creds);
+
+        store.clear().unwrap();
+        assert!(store.load().is_err());
+    }
+}
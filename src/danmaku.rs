@@ -0,0 +1,407 @@
+use std::fmt;
+use std::io::Read;
+use std::time::Duration;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use crate::error::{Result, BiliError};
+
+/// 没有可用的host_list时使用的弹幕服务器
+pub const DEFAULT_DANMAKU_URL: &str = "wss://broadcastlv.chat.bilibili.com/sub";
+
+/// 心跳间隔，超过约70秒未发送心跳服务端会断开连接
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 等待认证回复的超时时间
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 接收端的缓冲条数
+const CHANNEL_CAPACITY: usize = 256;
+
+/// 包头长度
+const HEADER_LEN: usize = 16;
+
+/// 包头中的协议版本
+const VER_JSON: u16 = 0;
+const VER_HEARTBEAT: u16 = 1;
+const VER_ZLIB: u16 = 2;
+const VER_BROTLI: u16 = 3;
+
+/// 包头中的操作码
+const OP_HEARTBEAT: u32 = 2;
+const OP_HEARTBEAT_REPLY: u32 = 3;
+const OP_MESSAGE: u32 = 5;
+const OP_AUTH: u32 = 7;
+const OP_AUTH_REPLY: u32 = 8;
+
+/// 实时收到的直播间消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DanmakuMessage {
+    /// 弹幕
+    Danmaku { uid: u64, uname: String, text: String },
+    /// 用户进入直播间
+    Enter { uid: u64, uname: String },
+    /// 礼物
+    Gift { uid: u64, uname: String, gift_name: String, num: u32 },
+    /// 心跳回复中的人气值
+    Popularity(u32),
+}
+
+impl fmt::Display for DanmakuMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DanmakuMessage::Danmaku { uname, text, .. } => write!(f, "{}: {}", uname, text),
+            DanmakuMessage::Enter { uname, .. } => write!(f, "{} 进入直播间", uname),
+            DanmakuMessage::Gift { uname, gift_name, num, .. } => write!(f, "{} 赠送 {} ×{}", uname, gift_name, num),
+            DanmakuMessage::Popularity(value) => write!(f, "人气值: {}", value),
+        }
+    }
+}
+
+/// 一个协议包
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub ver: u16,
+    pub op: u32,
+    pub body: Vec<u8>,
+}
+
+impl Packet {
+    pub fn new(ver: u16, op: u32, body: Vec<u8>) -> Self {
+        Self { ver, op, body }
+    }
+
+    /// 编码为大端序的包头加包体
+    pub fn encode(&self) -> Vec<u8> {
+        let total = (HEADER_LEN + self.body.len()) as u32;
+        let mut buf = Vec::with_capacity(total as usize);
+        buf.extend_from_slice(&total.to_be_bytes());
+        buf.extend_from_slice(&(HEADER_LEN as u16).to_be_bytes());
+        buf.extend_from_slice(&self.ver.to_be_bytes());
+        buf.extend_from_slice(&self.op.to_be_bytes());
+        buf.extend_from_slice(&1u32.to_be_bytes());
+        buf.extend_from_slice(&self.body);
+        buf
+    }
+
+    /// 解码一帧数据，压缩包会被解压并展开为其中的多个包
+    pub fn decode_all(data: &[u8]) -> Result<Vec<Packet>> {
+        let mut packets = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let rest = &data[offset..];
+            if rest.len() < HEADER_LEN {
+                return Err(BiliError::Bullet(format!("弹幕数据包不完整: 剩余{}字节", rest.len())));
+            }
+            let total = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let header_len = u16::from_be_bytes([rest[4], rest[5]]) as usize;
+            if total < header_len || header_len < HEADER_LEN || total > rest.len() {
+                return Err(BiliError::Bullet(format!("弹幕数据包长度错误: {}", total)));
+            }
+            let ver = u16::from_be_bytes([rest[6], rest[7]]);
+            let op = u32::from_be_bytes([rest[8], rest[9], rest[10], rest[11]]);
+            let body = &rest[header_len..total];
+
+            match ver {
+                VER_ZLIB => packets.extend(Self::decode_all(&inflate(flate2::read::ZlibDecoder::new(body))?)?),
+                VER_BROTLI => packets.extend(Self::decode_all(&inflate(brotli::Decompressor::new(body, 4096))?)?),
+                _ => packets.push(Packet::new(ver, op, body.to_vec())),
+            }
+            offset += total;
+        }
+        Ok(packets)
+    }
+}
+
+/// 读出解压后的全部数据
+fn inflate(mut reader: impl Read) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)
+        .map_err(|e| BiliError::Bullet(format!("解压弹幕数据失败: {}", e)))?;
+    Ok(buf)
+}
+
+/// 认证包内容
+pub fn auth_packet(uid: u64, room_id: u64, token: &str, buvid: &str) -> Packet {
+    let body = serde_json::json!({
+        "uid": uid,
+        "roomid": room_id,
+        "protover": VER_BROTLI,
+        "buvid": buvid,
+        "platform": "web",
+        "type": 2,
+        "key": token,
+    });
+    Packet::new(VER_HEARTBEAT, OP_AUTH, body.to_string().into_bytes())
+}
+
+pub fn heartbeat_packet() -> Packet {
+    Packet::new(VER_HEARTBEAT, OP_HEARTBEAT, b"[object Object]".to_vec())
+}
+
+/// 把协议包转换为消息，不关心的命令返回None
+pub fn parse_packet(packet: &Packet) -> Option<DanmakuMessage> {
+    match packet.op {
+        OP_HEARTBEAT_REPLY if packet.body.len() >= 4 => {
+            let body = &packet.body;
+            Some(DanmakuMessage::Popularity(u32::from_be_bytes([body[0], body[1], body[2], body[3]])))
+        }
+        OP_MESSAGE if packet.ver == VER_JSON => {
+            let value: serde_json::Value = serde_json::from_slice(&packet.body).ok()?;
+            parse_command(&value)
+        }
+        _ => None,
+    }
+}
+
+/// 按cmd解析消息，cmd可能带有 `DANMU_MSG:4:0:2:2:2:0` 这样的后缀
+fn parse_command(value: &serde_json::Value) -> Option<DanmakuMessage> {
+    let cmd = value.get("cmd")?.as_str()?;
+    let data = value.get("data");
+    match cmd.split(':').next()? {
+        "DANMU_MSG" => {
+            let info = value.get("info")?;
+            Some(DanmakuMessage::Danmaku {
+                uid: info.pointer("/2/0")?.as_u64()?,
+                uname: info.pointer("/2/1")?.as_str()?.to_string(),
+                text: info.get(1)?.as_str()?.to_string(),
+            })
+        }
+        // msg_type为1表示进场，其他为关注、分享等互动
+        "INTERACT_WORD" if data?.get("msg_type")?.as_u64()? == 1 => {
+            let data = data?;
+            Some(DanmakuMessage::Enter {
+                uid: data.get("uid")?.as_u64()?,
+                uname: data.get("uname")?.as_str()?.to_string(),
+            })
+        }
+        "SEND_GIFT" => {
+            let data = data?;
+            Some(DanmakuMessage::Gift {
+                uid: data.get("uid")?.as_u64()?,
+                uname: data.get("uname")?.as_str()?.to_string(),
+                gift_name: data.get("giftName")?.as_str()?.to_string(),
+                num: data.get("num")?.as_u64()? as u32,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// getDanmuInfo返回的弹幕服务器
+#[derive(Debug, Clone, Deserialize)]
+pub struct DanmakuHost {
+    pub host: String,
+    pub wss_port: u16,
+}
+
+/// getDanmuInfo返回的连接信息
+#[derive(Debug, Clone, Deserialize)]
+pub struct DanmakuInfo {
+    pub token: String,
+    #[serde(default)]
+    pub host_list: Vec<DanmakuHost>,
+}
+
+impl DanmakuInfo {
+    /// 按host_list顺序生成候选地址，列表为空时使用默认服务器
+    pub fn urls(&self) -> Vec<String> {
+        let mut urls: Vec<String> = self.host_list.iter()
+            .map(|host| format!("wss://{}:{}/sub", host.host, host.wss_port))
+            .collect();
+        if urls.is_empty() {
+            urls.push(DEFAULT_DANMAKU_URL.to_string());
+        }
+        urls
+    }
+}
+
+/// 连接弹幕服务器并完成认证，之后在后台发送心跳并转发消息
+///
+/// 接收端被丢弃或连接断开时后台任务退出，接收端随之返回None；无法解码的数据帧会被忽略。
+pub async fn connect(url: &str, auth: Packet) -> Result<mpsc::Receiver<DanmakuMessage>> {
+    let (ws, _) = tokio_tungstenite::connect_async(url).await
+        .map_err(|e| BiliError::Bullet(format!("连接弹幕服务器失败: {}", e)))?;
+    let (mut sink, mut stream) = ws.split();
+
+    sink.send(Message::binary(auth.encode())).await
+        .map_err(|e| BiliError::Bullet(format!("发送认证包失败: {}", e)))?;
+
+    // 认证回复之前的消息直接丢弃，回复体为 {"code":0}
+    let authorized = tokio::time::timeout(AUTH_TIMEOUT, async {
+        while let Some(frame) = stream.next().await {
+            let Ok(Message::Binary(data)) = frame else {
+                continue;
+            };
+            if let Some(reply) = Packet::decode_all(&data)?.into_iter().find(|p| p.op == OP_AUTH_REPLY) {
+                let code = serde_json::from_slice::<serde_json::Value>(&reply.body).ok()
+                    .and_then(|v| v.get("code").and_then(|c| c.as_i64()));
+                return match code {
+                    Some(0) => Ok(()),
+                    _ => Err(BiliError::Bullet(format!("弹幕服务器认证失败: {}", String::from_utf8_lossy(&reply.body)))),
+                };
+            }
+        }
+        Err(BiliError::Bullet("弹幕服务器在认证前断开连接".to_string()))
+    }).await;
+    match authorized {
+        Ok(result) => result?,
+        Err(_) => return Err(BiliError::timeout("等待弹幕服务器认证超时")),
+    }
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = heartbeat.tick() => {
+                    if sink.send(Message::binary(heartbeat_packet().encode())).await.is_err() {
+                        break;
+                    }
+                }
+                _ = tx.closed() => break,
+                frame = stream.next() => {
+                    let data = match frame {
+                        Some(Ok(Message::Binary(data))) => data,
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        Some(Ok(_)) => continue,
+                    };
+                    // 无法解析的帧直接丢弃，不影响后续消息
+                    let Ok(packets) = Packet::decode_all(&data) else {
+                        continue;
+                    };
+                    for message in packets.iter().filter_map(parse_packet) {
+                        if tx.send(message).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tokio::net::TcpListener;
+
+    fn danmu_packet(text: &str) -> Packet {
+        let body = serde_json::json!({
+            "cmd": "DANMU_MSG:4:0:2:2:2:0",
+            "info": [[0], text, [42, "观众"]],
+        });
+        Packet::new(VER_JSON, OP_MESSAGE, body.to_string().into_bytes())
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut output, 4096, 5, 22);
+            writer.write_all(data).unwrap();
+        }
+        output
+    }
+
+    #[test]
+    fn test_packet_roundtrip() {
+        let packet = heartbeat_packet();
+        let encoded = packet.encode();
+        assert_eq!(&encoded[..4], &(encoded.len() as u32).to_be_bytes());
+        assert_eq!(Packet::decode_all(&encoded).unwrap(), vec![packet]);
+    }
+
+    #[test]
+    fn test_decode_compressed_packets() {
+        let inner = [danmu_packet("你好").encode(), danmu_packet("主播好").encode()].concat();
+
+        for (ver, body) in [(VER_ZLIB, zlib(&inner)), (VER_BROTLI, brotli(&inner))] {
+            let packets = Packet::decode_all(&Packet::new(ver, OP_MESSAGE, body).encode()).unwrap();
+            let messages: Vec<_> = packets.iter().filter_map(parse_packet).collect();
+            assert_eq!(messages, vec![
+                DanmakuMessage::Danmaku { uid: 42, uname: "观众".to_string(), text: "你好".to_string() },
+                DanmakuMessage::Danmaku { uid: 42, uname: "观众".to_string(), text: "主播好".to_string() },
+            ]);
+        }
+    }
+
+    #[test]
+    fn test_parse_enter_gift_and_popularity() {
+        let enter = serde_json::json!({"cmd": "INTERACT_WORD", "data": {"uid": 7, "uname": "路人", "msg_type": 1}});
+        assert_eq!(parse_command(&enter), Some(DanmakuMessage::Enter { uid: 7, uname: "路人".to_string() }));
+
+        let follow = serde_json::json!({"cmd": "INTERACT_WORD", "data": {"uid": 7, "uname": "路人", "msg_type": 2}});
+        assert_eq!(parse_command(&follow), None);
+
+        let gift = serde_json::json!({"cmd": "SEND_GIFT", "data": {"uid": 8, "uname": "老板", "giftName": "小心心", "num": 3}});
+        assert_eq!(parse_command(&gift), Some(DanmakuMessage::Gift {
+            uid: 8,
+            uname: "老板".to_string(),
+            gift_name: "小心心".to_string(),
+            num: 3,
+        }));
+
+        let popularity = Packet::new(VER_HEARTBEAT, OP_HEARTBEAT_REPLY, 1234u32.to_be_bytes().to_vec());
+        assert_eq!(parse_packet(&popularity), Some(DanmakuMessage::Popularity(1234)));
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_packet() {
+        let encoded = danmu_packet("你好").encode();
+        assert!(Packet::decode_all(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_urls_fall_back_to_default() {
+        let info: DanmakuInfo = serde_json::from_str(r#"{"token":"t","host_list":[{"host":"a.chat.bilibili.com","wss_port":443}]}"#).unwrap();
+        assert_eq!(info.urls(), vec!["wss://a.chat.bilibili.com:443/sub".to_string()]);
+
+        let info: DanmakuInfo = serde_json::from_str(r#"{"token":"t"}"#).unwrap();
+        assert_eq!(info.urls(), vec![DEFAULT_DANMAKU_URL.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_connect_authenticates_and_receives() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/sub", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+
+            let Some(Ok(Message::Binary(data))) = ws.next().await else {
+                panic!("应先收到认证包");
+            };
+            let auth = Packet::decode_all(&data).unwrap().remove(0);
+            assert_eq!(auth.op, OP_AUTH);
+            let body: serde_json::Value = serde_json::from_slice(&auth.body).unwrap();
+            assert_eq!(body["key"], "token");
+
+            let reply = Packet::new(VER_HEARTBEAT, OP_AUTH_REPLY, br#"{"code":0}"#.to_vec());
+            ws.send(Message::binary(reply.encode())).await.unwrap();
+            // 无法解码的帧被跳过，连接保持
+            ws.send(Message::binary(vec![0u8; 3])).await.unwrap();
+            let message = Packet::new(VER_ZLIB, OP_MESSAGE, zlib(&danmu_packet("你好").encode()));
+            ws.send(Message::binary(message.encode())).await.unwrap();
+            // 保持连接直到客户端断开
+            while ws.next().await.is_some() {}
+        });
+
+        let mut rx = connect(&url, auth_packet(0, 1, "token", "")).await.unwrap();
+        assert_eq!(rx.recv().await, Some(DanmakuMessage::Danmaku {
+            uid: 42,
+            uname: "观众".to_string(),
+            text: "你好".to_string(),
+        }));
+    }
+}
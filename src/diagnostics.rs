@@ -0,0 +1,481 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use crate::auth::Auth;
+use crate::client::BilibiliClient;
+use crate::config::{Config, Endpoints};
+use crate::live::StreamKey;
+use crate::error::{Result, BiliError};
+use crate::sign::{Signer, NAV_URL};
+
+/// 需要在报告中脱敏展示的关键 cookie
+const SENSITIVE_COOKIES: [&str; 3] = ["SESSDATA", "bili_jct", "DedeUserID"];
+
+/// 用于探测风控的WBI签名接口，只读且不影响账号
+const WBI_PROBE_URL: &str = "https://api.bilibili.com/x/web-interface/wbi/search/default";
+
+/// 表示触发风控的返回码（含HTTP 412）
+const RISK_CODES: [i32; 3] = [-352, -412, 412];
+
+/// 风控风险等级
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl RiskLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RiskLevel::Low => "低",
+            RiskLevel::Medium => "中",
+            RiskLevel::High => "高",
+        }
+    }
+}
+
+/// 风控评估依据
+#[derive(Debug, Clone, Default)]
+pub struct RiskSignals {
+    /// 探测请求返回的风控码
+    pub risk_codes: Vec<i32>,
+    pub has_buvid: bool,
+    pub has_csrf: bool,
+    pub wbi_ok: bool,
+}
+
+impl RiskSignals {
+    /// 出现风控码为高风险，buvid/WBI/csrf有缺失为中风险
+    pub fn verdict(&self) -> RiskLevel {
+        if !self.risk_codes.is_empty() {
+            RiskLevel::High
+        } else if self.has_buvid && self.has_csrf && self.wbi_ok {
+            RiskLevel::Low
+        } else {
+            RiskLevel::Medium
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut notes = Vec::new();
+        if !self.risk_codes.is_empty() {
+            let codes: Vec<String> = self.risk_codes.iter().map(i32::to_string).collect();
+            notes.push(format!("触发风控码 {}", codes.join(", ")));
+        }
+        if !self.has_buvid {
+            notes.push("缺少buvid3".to_string());
+        }
+        if !self.has_csrf {
+            notes.push("缺少csrf".to_string());
+        }
+        if !self.wbi_ok {
+            notes.push("WBI签名不可用".to_string());
+        }
+
+        let mut detail = format!("风控风险：{}", self.verdict().label());
+        if !notes.is_empty() {
+            detail.push_str(&format!("（{}）", notes.join("；")));
+        }
+        detail
+    }
+}
+
+/// 单项检查结果
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: impl Into<String>, ok: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ok,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// 接口连通性
+#[derive(Debug, Clone)]
+pub struct EndpointStatus {
+    /// Endpoints中的名称，如live-api
+    pub name: String,
+    pub url: String,
+    pub reachable: bool,
+    pub latency_ms: Option<u128>,
+    pub detail: String,
+}
+
+/// 诊断报告
+#[derive(Debug, Clone)]
+pub struct DiagnosticReport {
+    pub version: String,
+    pub os: String,
+    pub checks: Vec<CheckResult>,
+    pub endpoints: Vec<EndpointStatus>,
+    pub last_error: Option<String>,
+}
+
+pub struct Diagnostics;
+
+impl Diagnostics {
+    /// 运行全部检查
+    pub async fn run(config: &Config) -> DiagnosticReport {
+        let mut report = Self::run_local(config);
+
+        report.endpoints = Self::probe_endpoints(config).await;
+        for endpoint in &report.endpoints {
+            if !endpoint.reachable {
+                report.last_error = Some(format!("{} 不可达: {}", endpoint.url, endpoint.detail));
+            }
+        }
+
+        let cookie_check = Self::check_cookie_validity(config).await;
+        if !cookie_check.ok {
+            report.last_error = Some(cookie_check.detail.clone());
+        }
+        report.checks.push(cookie_check);
+
+        report.checks.push(Self::check_risk_control(config).await);
+
+        report
+    }
+
+    /// 只运行无需联网的本地检查
+    pub fn run_local(config: &Config) -> DiagnosticReport {
+        let mut checks = Vec::new();
+
+        let config_path = config.config_path();
+        checks.push(CheckResult::new(
+            "配置文件",
+            config_path.exists(),
+            config_path.display().to_string(),
+        ));
+
+        checks.push(CheckResult::new(
+            "房间号",
+            config.room_id.is_some(),
+            config.room_id.clone().unwrap_or_else(|| "未设置".to_string()),
+        ));
+
+        let cookies = config.cookie_str.as_deref()
+            .and_then(|s| BilibiliClient::parse_cookies(s).ok())
+            .unwrap_or_default();
+        for name in SENSITIVE_COOKIES {
+            let value = cookies.get(name).map(String::as_str);
+            checks.push(CheckResult::new(name, value.is_some(), Self::redact(value)));
+        }
+
+        checks.push(CheckResult::new(
+            "CSRF",
+            config.csrf.is_some(),
+            Self::redact(config.csrf.as_deref()),
+        ));
+
+        checks.push(CheckResult::new(
+            "推流码",
+            true,
+            Self::redact(config.stream_key.as_ref().map(StreamKey::reveal)),
+        ));
+
+        let last_error = checks.iter()
+            .find(|c| !c.ok)
+            .map(|c| format!("{}: {}", c.name, c.detail));
+
+        DiagnosticReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: format!("{} ({})", std::env::consts::OS, std::env::consts::ARCH),
+            checks,
+            endpoints: Vec::new(),
+            last_error,
+        }
+    }
+
+    /// 需要检测的地址，WebSocket地址改用同一主机的HTTP(S)探测
+    pub fn probe_targets(endpoints: &Endpoints) -> Vec<(&'static str, String)> {
+        endpoints.entries()
+            .into_iter()
+            .map(|(name, url)| {
+                let url = match url.split_once("://") {
+                    Some(("wss", rest)) => format!("https://{}", rest),
+                    Some(("ws", rest)) => format!("http://{}", rest),
+                    _ => url.to_string(),
+                };
+                (name, url)
+            })
+            .collect()
+    }
+
+    /// 按配置的代理和超时检测Endpoints中各服务是否可达
+    pub async fn probe_endpoints(config: &Config) -> Vec<EndpointStatus> {
+        let targets = Self::probe_targets(&config.endpoints);
+        let client = match BilibiliClient::from_config(config) {
+            Ok(client) => client,
+            Err(e) => {
+                return targets.into_iter()
+                    .map(|(name, url)| EndpointStatus {
+                        name: name.to_string(),
+                        url,
+                        reachable: false,
+                        latency_ms: None,
+                        detail: e.to_string(),
+                    })
+                    .collect();
+            }
+        };
+
+        let mut results = Vec::new();
+        for (name, url) in targets {
+            let start = Instant::now();
+            let response = client.get_client()
+                .get(&url)
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await;
+
+            results.push(match response {
+                Ok(resp) => EndpointStatus {
+                    name: name.to_string(),
+                    url,
+                    reachable: true,
+                    latency_ms: Some(start.elapsed().as_millis()),
+                    detail: format!("HTTP {}", resp.status().as_u16()),
+                },
+                Err(e) => EndpointStatus {
+                    name: name.to_string(),
+                    url,
+                    reachable: false,
+                    latency_ms: None,
+                    detail: e.to_string(),
+                },
+            });
+        }
+
+        results
+    }
+
+    /// 检查已保存的cookie是否有效
+    async fn check_cookie_validity(config: &Config) -> CheckResult {
+        let Some(cookie_str) = config.cookie_str.as_deref() else {
+            return CheckResult::new("登录状态", false, "未保存cookie");
+        };
+
+        let result = async {
+            let cookies = Auth::parse_cookie_string(cookie_str)?;
+            Auth::with_client(BilibiliClient::from_config(config)?)
+                .with_endpoints(config.endpoints.clone())
+                .validate_cookies(&cookies)
+                .await
+        }.await;
+
+        match result {
+            Ok(true) => CheckResult::new("登录状态", true, "cookie有效"),
+            Ok(false) => CheckResult::new("登录状态", false, "cookie已失效"),
+            Err(e) => CheckResult::new("登录状态", false, format!("验证失败: {}", e)),
+        }
+    }
+
+    /// 发起几次无副作用的请求，综合风控码和buvid/WBI/csrf情况给出风控风险
+    async fn check_risk_control(config: &Config) -> CheckResult {
+        let cookies = config.cookie_str.as_deref()
+            .and_then(|s| BilibiliClient::parse_cookies(s).ok())
+            .unwrap_or_default();
+        let client = match BilibiliClient::from_config(config) {
+            Ok(client) => client,
+            Err(e) => return CheckResult::new("风控风险", false, format!("创建客户端失败: {}", e)),
+        };
+        client.add_cookies(&cookies);
+
+        let mut signals = RiskSignals {
+            has_buvid: cookies.contains_key("buvid3"),
+            has_csrf: config.csrf.is_some() || cookies.contains_key("bili_jct"),
+            ..RiskSignals::default()
+        };
+
+        let mut urls = vec![NAV_URL.to_string()];
+        if config.disable_signing {
+            // 关闭签名时不探测WBI，避免把签名问题误判为风控
+            signals.wbi_ok = true;
+        } else if let Ok((img_key, sub_key)) = Signer::fetch_wbi_keys(&client).await {
+            let params = HashMap::from([("platform".to_string(), "web".to_string())]);
+            if let Ok(signed) = Signer::wbi_sign(params, &img_key, &sub_key) {
+                signals.wbi_ok = true;
+                urls.push(format!("{}?{}", WBI_PROBE_URL, Signer::to_query(&signed)));
+            }
+        }
+        signals.risk_codes = collect_risk_codes(&client, &urls).await;
+
+        CheckResult::new("风控风险", signals.verdict() == RiskLevel::Low, signals.describe())
+    }
+
+    /// 脱敏显示：只保留是否存在和末4位
+    pub fn redact(value: Option<&str>) -> String {
+        match value {
+            None => "未设置".to_string(),
+            Some(v) => {
+                let chars: Vec<char> = v.chars().collect();
+                if chars.len() <= 8 {
+                    "已设置 (****)".to_string()
+                } else {
+                    let tail: String = chars[chars.len() - 4..].iter().collect();
+                    format!("已设置 (末4位: {})", tail)
+                }
+            }
+        }
+    }
+}
+
+impl DiagnosticReport {
+    /// 生成可分享的文本报告
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str("=== 哔哩哔哩直播工具诊断报告 ===\n");
+        out.push_str(&format!("版本: {}\n", self.version));
+        out.push_str(&format!("系统: {}\n", self.os));
+        out.push('\n');
+
+        out.push_str("[检查项]\n");
+        for check in &self.checks {
+            let mark = if check.ok { "✓" } else { "✗" };
+            out.push_str(&format!("{} {}: {}\n", mark, check.name, check.detail));
+        }
+
+        if !self.endpoints.is_empty() {
+            out.push('\n');
+            out.push_str("[接口连通性]\n");
+            for endpoint in &self.endpoints {
+                let mark = if endpoint.reachable { "✓" } else { "✗" };
+                let latency = endpoint.latency_ms
+                    .map(|ms| format!("{}ms", ms))
+                    .unwrap_or_else(|| "-".to_string());
+                out.push_str(&format!("{} {} ({}) {} {}\n", mark, endpoint.name, endpoint.url, latency, endpoint.detail));
+            }
+        }
+
+        out.push('\n');
+        out.push_str(&format!("最近错误: {}\n", self.last_error.as_deref().unwrap_or("无")));
+        out
+    }
+
+    /// 保存报告到文件
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.render())
+            .map_err(|e| BiliError::general(format!("写入诊断报告失败: {}", e)))
+    }
+}
+
+/// 依次请求各地址，收集触发风控的返回码
+async fn collect_risk_codes(client: &BilibiliClient, urls: &[String]) -> Vec<i32> {
+    let mut codes = Vec::new();
+    for url in urls {
+        let Ok(response) = client.get_client()
+            .get(url)
+            .headers(BilibiliClient::get_default_headers())
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+        else {
+            continue;
+        };
+
+        let status = response.status().as_u16() as i32;
+        if RISK_CODES.contains(&status) {
+            codes.push(status);
+            continue;
+        }
+
+        let code = response.json::<serde_json::Value>().await.ok()
+            .and_then(|value| value.get("code").and_then(|c| c.as_i64()))
+            .map(|c| c as i32);
+        if let Some(code) = code
+            && RISK_CODES.contains(&code)
+        {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::DEFAULT_TIMEOUT_MS;
+    use crate::test_support::serve_once;
+
+    #[tokio::test]
+    async fn test_cookie_check_uses_configured_endpoints() {
+        let (addr, request) = serve_once(r#"{"code":0,"message":"0","data":{"isLogin":true}}"#).await;
+        let config = Config {
+            cookie_str: Some("SESSDATA=abc; bili_jct=csrf".to_string()),
+            endpoints: Endpoints { main_api: addr, ..Endpoints::default() },
+            ..Config::default()
+        };
+
+        let check = Diagnostics::check_cookie_validity(&config).await;
+        assert!(check.ok, "{}", check.detail);
+        let request = request.await.unwrap();
+        assert!(request.head.starts_with("GET /x/web-interface/nav"));
+    }
+
+    #[tokio::test]
+    async fn test_risk_codes_give_high_verdict() {
+        let body = r#"{"code":-352,"message":"风控校验失败","ttl":1}"#;
+        let (first, _) = serve_once(body).await;
+        let (second, _) = serve_once(body).await;
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+
+        let signals = RiskSignals {
+            risk_codes: collect_risk_codes(&client, &[first, second]).await,
+            has_buvid: true,
+            has_csrf: true,
+            wbi_ok: true,
+        };
+
+        assert_eq!(signals.risk_codes, vec![-352, -352]);
+        assert_eq!(signals.verdict(), RiskLevel::High);
+        assert!(signals.describe().starts_with("风控风险：高"));
+    }
+
+    #[test]
+    fn test_probe_targets_follow_endpoints() {
+        let endpoints = Endpoints {
+            live_api: "https://live.example.com".to_string(),
+            danmaku_ws: "wss://chat.example.com".to_string(),
+            ..Endpoints::default()
+        };
+
+        let targets = Diagnostics::probe_targets(&endpoints);
+        let names: Vec<_> = targets.iter().map(|(name, _)| *name).collect();
+        let expected: Vec<_> = endpoints.entries().iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, expected);
+        assert!(targets.contains(&("live-api", "https://live.example.com".to_string())));
+        assert!(targets.contains(&("danmaku-ws", "https://chat.example.com".to_string())));
+        assert!(targets.contains(&("passport", endpoints.passport.clone())));
+    }
+
+    #[test]
+    fn test_report_redacts_secrets() {
+        let sessdata = "a1b2c3d4e5f6%2C1735689600%2Cabcd";
+        let stream_key = "?streamname=live_123456_7890123&key=0123456789abcdef";
+        let config = Config {
+            room_id: Some("123456".to_string()),
+            cookie_str: Some(format!("SESSDATA={}; bili_jct=0123456789abcdef0123; DedeUserID=42424242", sessdata)),
+            csrf: Some("0123456789abcdef0123".to_string()),
+            stream_key: Some(StreamKey::new(stream_key)),
+            ..Config::default()
+        };
+
+        let report = Diagnostics::run_local(&config).render();
+
+        let decoded = urlencoding::decode(sessdata).unwrap();
+        assert!(!report.contains(sessdata));
+        assert!(!report.contains(decoded.as_ref()));
+        assert!(!report.contains(stream_key));
+        assert!(report.contains("SESSDATA: 已设置"));
+        assert!(report.contains("推流码: 已设置 (末4位: cdef)"));
+    }
+}
@@ -5,7 +5,7 @@ pub type Result<T> = std::result::Result<T, BiliError>;
 #[derive(Error, Debug)]
 pub enum BiliError {
     #[error("网络请求错误: {0}")]
-    Network(#[from] reqwest::Error),
+    Network(#[source] reqwest::Error),
     
     #[error("JSON解析错误: {0}")]
     Json(#[from] serde_json::Error),
@@ -52,14 +52,151 @@ pub enum BiliError {
     #[error("资源不存在: {0}")]
     NotFound(String),
     
+    /// 风控拦截（-352风控校验失败、-412请求被拦截），与登录态无关，稍后重试即可
+    #[error("触发风控（code={code}）: {message}，请稍后重试")]
+    RiskControl {
+        code: i32,
+        message: String,
+    },
+    
     #[error("内部错误: {0}")]
     Internal(String),
     
+    /// 开播接口返回need_face_auth，内容为人脸认证二维码链接
+    #[error("开播需要人脸认证，请使用B站App扫码完成认证后重新开播")]
+    FaceAuthRequired(String),
+    
     #[error("{0}")]
     General(String),
+    
+    /// 接口响应中的错误，附带接口路径，显示为 `API响应错误: ...（接口: /room/v1/Room/update）`
+    #[error("{source}（接口: {path}）")]
+    ApiWithContext {
+        path: String,
+        #[source]
+        source: Box<BiliError>,
+    },
+    
+    /// 附带操作名的错误，显示为 `开始直播失败 → 网络请求错误: ...`
+    #[error("{op}失败 → {source}")]
+    WithContext {
+        op: String,
+        #[source]
+        source: Box<BiliError>,
+    },
+}
+
+/// 为Result中的错误附加操作名
+pub trait ResultExt<T> {
+    fn context(self, op: impl Into<String>) -> Result<T>;
+}
+
+impl<T, E: Into<BiliError>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, op: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().context(op))
+    }
+}
+
+/// B站维护期间的提示信息
+pub const MAINTENANCE_MESSAGE: &str = "B站正在维护，请稍后重试";
+
+/// 请求被拦截（-412）时重试等待时间的上限
+const MAX_BLOCKED_BACKOFF: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// 维护页面/维护提示中常见的关键字
+const MAINTENANCE_MARKERS: [&str; 3] = ["系统维护", "正在维护", "maintenance"];
+
+/// 请求超时单独归为Timeout，其余网络错误归为Network
+impl From<reqwest::Error> for BiliError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            BiliError::Timeout(e.to_string())
+        } else {
+            BiliError::Network(e)
+        }
+    }
 }
 
 impl BiliError {
+    /// 附加操作名
+    pub fn context(self, op: impl Into<String>) -> Self {
+        BiliError::WithContext { op: op.into(), source: Box::new(self) }
+    }
+    
+    /// 附加出错的接口路径
+    pub fn with_endpoint(self, path: impl Into<String>) -> Self {
+        BiliError::ApiWithContext { path: path.into(), source: Box::new(self) }
+    }
+    
+    /// 去掉所有操作名和接口路径后的原始错误，判断错误类型时使用
+    pub fn root(&self) -> &BiliError {
+        match self {
+            BiliError::WithContext { source, .. } | BiliError::ApiWithContext { source, .. } => source.root(),
+            e => e,
+        }
+    }
+    
+    /// 出错的接口路径，错误不是来自接口响应时返回None
+    pub fn endpoint(&self) -> Option<&str> {
+        match self {
+            BiliError::ApiWithContext { path, .. } => Some(path),
+            BiliError::WithContext { source, .. } => source.endpoint(),
+            _ => None,
+        }
+    }
+    
+    /// 判断文本中是否包含维护标记
+    pub fn has_maintenance_marker(text: &str) -> bool {
+        let lower = text.to_lowercase();
+        MAINTENANCE_MARKERS.iter().any(|marker| lower.contains(marker))
+    }
+    
+    /// 创建维护错误
+    pub fn maintenance(code: i32) -> Self {
+        BiliError::Api(code, MAINTENANCE_MESSAGE.to_string())
+    }
+    
+    /// 判断是否为维护错误
+    pub fn is_maintenance(&self) -> bool {
+        matches!(self.root(), BiliError::Api(_, message) if message == MAINTENANCE_MESSAGE)
+    }
+    
+    /// 按B站返回码创建错误，常见的登录和风控码翻译为对应的变体，其余归为Api
+    pub fn from_bili_code(code: i32, message: impl Into<String>) -> Self {
+        let message = message.into();
+        match code {
+            -101 => BiliError::Auth(message),
+            -403 => BiliError::Permission(message),
+            -404 => BiliError::NotFound(message),
+            -352 | -412 | 412 => BiliError::RiskControl { code, message },
+            _ => BiliError::Api(code, message),
+        }
+    }
+    
+    /// 判断是否为风控校验失败（-352），此时应补充WBI签名后再请求
+    pub fn is_risk_check(&self) -> bool {
+        matches!(self.root(), BiliError::RiskControl { code: -352, .. } | BiliError::Api(-352, _))
+    }
+    
+    /// 判断是否为请求被拦截（-412）
+    pub fn is_request_blocked(&self) -> bool {
+        matches!(
+            self.root(),
+            BiliError::RiskControl { code: -412 | 412, .. } | BiliError::Api(-412 | 412, _)
+        )
+    }
+    
+    /// 重试前应等待的时间，维护期间使用更长的退避，请求被拦截时加倍但不超过 [`MAX_BLOCKED_BACKOFF`]
+    pub fn retry_backoff(&self, base: std::time::Duration) -> std::time::Duration {
+        if self.is_maintenance() {
+            base * 10
+        } else if self.is_request_blocked() {
+            base.saturating_mul(2).min(MAX_BLOCKED_BACKOFF)
+        } else {
+            base
+        }
+    }
+    
     /// 创建API错误
     pub fn api_error(code: i32, message: impl Into<String>) -> Self {
         BiliError::Api(code, message.into())
@@ -122,34 +259,85 @@ impl BiliError {
     
     /// 判断是否为网络错误
     pub fn is_network_error(&self) -> bool {
-        matches!(self, BiliError::Network(_))
+        matches!(self.root(), BiliError::Network(_))
     }
     
     /// 判断是否为认证错误
     pub fn is_auth_error(&self) -> bool {
-        matches!(self, BiliError::Auth(_) | BiliError::Login(_) | BiliError::Permission(_))
+        matches!(self.root(), BiliError::Auth(_) | BiliError::Login(_) | BiliError::Permission(_))
+    }
+    
+    /// 需要人脸认证时返回认证二维码链接
+    pub fn face_auth_qr(&self) -> Option<&str> {
+        match self.root() {
+            BiliError::FaceAuthRequired(qr) => Some(qr),
+            _ => None,
+        }
     }
     
     /// 判断是否为API错误
     pub fn is_api_error(&self) -> bool {
-        matches!(self, BiliError::Api(_, _))
+        matches!(self.root(), BiliError::Api(_, _))
     }
     
-    /// 获取错误代码（如果是API错误）
+    /// 获取错误代码（如果是API错误或风控错误）
     pub fn error_code(&self) -> Option<i32> {
-        if let BiliError::Api(code, _) = self {
-            Some(*code)
-        } else {
-            None
+        match self.root() {
+            BiliError::Api(code, _) | BiliError::RiskControl { code, .. } => Some(*code),
+            _ => None,
         }
     }
     
     /// 判断是否为可重试的错误
+    ///
+    /// 风控校验失败（-352）原样重试不会通过，不重试，由调用方决定是否补充WBI签名后再请求。
     pub fn is_retryable(&self) -> bool {
-        match self {
+        match self.root() {
             BiliError::Network(_) | BiliError::Timeout(_) => true,
             BiliError::Api(code, _) if *code == 503 || *code == 429 => true,
+            e if e.is_maintenance() || e.is_request_blocked() => true,
             _ => false,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_display_and_root() {
+        let result: Result<()> = Err(BiliError::timeout("operation timed out"));
+        let err = result.context("开始直播").unwrap_err();
+        assert_eq!(err.to_string(), "开始直播失败 → 超时错误: operation timed out");
+        assert!(err.is_retryable());
+
+        let err = BiliError::api_error(-101, "账号未登录").context("查询直播状态").context("结束直播");
+        assert_eq!(err.to_string(), "结束直播失败 → 查询直播状态失败 → API响应错误: code=-101, message=账号未登录");
+        assert_eq!(err.error_code(), Some(-101));
+        assert!(matches!(err.root(), BiliError::Api(-101, _)));
+    }
+
+    #[test]
+    fn test_from_bili_code_risk_control() {
+        let err = BiliError::from_bili_code(-352, "风控校验失败");
+        assert!(matches!(&err, BiliError::RiskControl { code: -352, message } if message == "风控校验失败"));
+        assert!(err.is_risk_check());
+        assert!(!err.is_retryable());
+        assert!(!err.is_auth_error());
+        assert_eq!(err.retry_backoff(std::time::Duration::from_secs(1)), std::time::Duration::from_secs(1));
+
+        let err = BiliError::from_bili_code(-412, "请求被拦截").context("获取分区列表");
+        assert!(err.is_request_blocked());
+        assert!(err.is_retryable());
+        assert!(!err.is_auth_error());
+        assert!(!matches!(err.root(), BiliError::Timeout(_)));
+        assert_eq!(err.error_code(), Some(-412));
+        assert!(err.to_string().contains("请求被拦截"));
+        assert_eq!(err.retry_backoff(std::time::Duration::from_secs(1)), std::time::Duration::from_secs(2));
+        assert_eq!(err.retry_backoff(std::time::Duration::from_secs(8)), MAX_BLOCKED_BACKOFF);
+
+        assert!(matches!(BiliError::from_bili_code(-101, "账号未登录"), BiliError::Auth(_)));
+        assert!(matches!(BiliError::from_bili_code(86038, "二维码已失效"), BiliError::Api(86038, _)));
+    }
+}
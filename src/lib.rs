@@ -3,10 +3,21 @@ pub mod client;
 pub mod auth;
 pub mod live;
 pub mod bullet;
+pub mod danmaku;
 pub mod qr;
 pub mod error;
 pub mod tui;
 pub mod sign;
+pub mod credential;
+pub mod diagnostics;
+pub mod notify;
+pub mod clipboard;
+pub mod util;
+pub mod secret;
+pub mod trace;
+
+#[cfg(test)]
+mod test_support;
 
 pub use config::Config;
 pub use client::BilibiliClient;
@@ -14,5 +25,9 @@ pub use auth::Auth;
 pub use live::Live;
 pub use bullet::Bullet;
 pub use qr::QRCode;
-pub use error::{Result, BiliError};
-pub use sign::Signer; 
+pub use error::{Result, BiliError, ResultExt};
+pub use sign::Signer;
+pub use credential::{CredentialStore, Credentials, TomlStore};
+pub use diagnostics::Diagnostics;
+pub use notify::{Notifier, StreamEvent};
+
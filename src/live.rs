@@ -1,7 +1,226 @@
 use serde::{Deserialize, Serialize, Deserializer};
 use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
 use crate::client::{BilibiliClient, ApiResponse};
-use crate::error::Result;
+use crate::config::{Config, Endpoints, LastSettings};
+use crate::error::{BiliError, Result, ResultExt};
+use crate::sign::Signer;
+use crate::util::{json_i64, json_u64};
+use reqwest::multipart::{Form, Part};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// 直播间信息缓存有效期
+const ROOM_INFO_TTL: Duration = Duration::from_secs(5);
+
+/// 直播标题最大长度（按字符簇计算）
+pub const MAX_TITLE_LEN: usize = 40;
+
+/// 开播接口
+const START_LIVE_URL: &str = "https://api.live.bilibili.com/room/v1/Room/startLive";
+
+/// 修改直播间标题、分区和封面的接口
+const ROOM_UPDATE_PATH: &str = "/room/v1/Room/update";
+
+/// 直播间信息接口
+const ROOM_INFO_PATH: &str = "/room/v1/Room/get_info";
+
+/// 分区列表接口
+const AREA_LIST_URL: &str = "https://api.live.bilibili.com/room/v1/Area/getList";
+
+/// 分区列表缓存的有效期
+const AREA_LIST_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// 切换分区被拒绝时按返回信息识别原因，依次为关键词和说明
+const AREA_REJECT_REASONS: [(&[&str], &str); 3] = [
+    (&["人脸"], "需要先完成人脸认证"),
+    (&["实名"], "需要先完成实名认证"),
+    (&["锁", "下线", "不可用", "暂停"], "该分区已被锁定"),
+];
+
+/// 签名校验失败的返回码，出现时改用不签名的请求重试
+const SIGN_REJECTED_CODES: [i32; 1] = [-3];
+
+/// 开播前人脸认证检查接口
+const FACE_AUTH_URL: &str = "https://api.live.bilibili.com/xlive/app-blink/v1/preLive/IsUserIdentifiedByFaceAuth";
+
+/// 人脸认证接口表示“需要完成人脸认证”的返回码
+const FACE_AUTH_REQUIRED_CODE: i32 = 60024;
+
+/// 主站实名认证状态接口，status为1表示已实名
+const REALNAME_STATUS_URL: &str = "https://api.bilibili.com/x/member/realname/status";
+
+/// 等待人脸认证完成时的查询间隔和最长等待时间
+pub const FACE_AUTH_POLL_INTERVAL: Duration = Duration::from_secs(3);
+pub const FACE_AUTH_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// 推流未就绪时按服务端提示等待的最长时间
+const MAX_READY_WAIT: Duration = Duration::from_secs(10);
+
+/// 默认推流协议，偏好的协议不可用时回落到该协议
+pub const DEFAULT_STREAM_PROTOCOL: &str = "rtmp";
+
+/// 封面图片上传接口
+const COVER_UPLOAD_URL: &str = "https://api.bilibili.com/x/upload/web/image";
+
+/// 封面图片大小上限
+pub const MAX_COVER_SIZE: usize = 10 * 1024 * 1024;
+
+/// 直播间统计信息接口
+const LIVE_STATS_URL: &str = "https://api.live.bilibili.com/xlive/web-room/v1/index/getInfoByRoom";
+
+/// 直播心跳接口
+const HEARTBEAT_URL: &str = "https://api.live.bilibili.com/User/userOnlineHeart";
+
+/// 直播心跳的上报间隔
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 心跳连续失败达到该次数时提示用户
+pub const HEARTBEAT_MAX_FAILURES: u32 = 3;
+
+/// 直播间实时数据，接口未返回的字段为None
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LiveStats {
+    /// 在线人数（人气值）
+    pub online: Option<u64>,
+    /// 看过的人数
+    pub watched: Option<u64>,
+    /// 主播粉丝数
+    pub followers: Option<u64>,
+}
+
+impl LiveStats {
+    /// 从getInfoByRoom返回的data中提取实时数据
+    pub fn from_json(stats: &serde_json::Value) -> Self {
+        Self {
+            online: json_u64(stats, "room_info.online"),
+            watched: json_u64(stats, "watched_show.num"),
+            followers: json_u64(stats, "anchor_info.relation_info.attention"),
+        }
+    }
+
+    /// 用新数据更新，新数据中缺少的字段保留旧值
+    pub fn merge(self, newer: LiveStats) -> Self {
+        Self {
+            online: newer.online.or(self.online),
+            watched: newer.watched.or(self.watched),
+            followers: newer.followers.or(self.followers),
+        }
+    }
+}
+
+/// 推流码
+///
+/// `Debug` 输出固定为 `live_***`，防止通过日志或 `{:?}` 泄露，需要原文时显式调用 [`StreamKey::reveal`]。
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StreamKey(String);
+
+impl StreamKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// 获取推流码原文
+    pub fn reveal(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for StreamKey {
+    fn from(key: String) -> Self {
+        Self(key)
+    }
+}
+
+impl fmt::Debug for StreamKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("live_***")
+    }
+}
+
+/// 开播返回的直播间状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum StreamLifecycle {
+    /// 准备中，推流码可能尚未生效
+    Preparing,
+    /// 直播中
+    Live,
+    /// 轮播中
+    Round,
+    /// 未知状态，保留原始字符串
+    Other(String),
+}
+
+impl StreamLifecycle {
+    pub fn is_live(&self) -> bool {
+        *self == StreamLifecycle::Live
+    }
+}
+
+impl From<String> for StreamLifecycle {
+    fn from(status: String) -> Self {
+        match status.as_str() {
+            "PREPARING" => StreamLifecycle::Preparing,
+            "LIVE" => StreamLifecycle::Live,
+            "ROUND" => StreamLifecycle::Round,
+            _ => StreamLifecycle::Other(status),
+        }
+    }
+}
+
+impl From<StreamLifecycle> for String {
+    fn from(status: StreamLifecycle) -> Self {
+        match status {
+            StreamLifecycle::Preparing => "PREPARING".to_string(),
+            StreamLifecycle::Live => "LIVE".to_string(),
+            StreamLifecycle::Round => "ROUND".to_string(),
+            StreamLifecycle::Other(s) => s,
+        }
+    }
+}
+
+/// 服务端给出的重试时间提示
+///
+/// 可能是秒数，也可能是北京时间 `YYYY-MM-DD HH:MM:SS`，`0000-00-00 00:00:00` 表示无需等待。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RetryHint(String);
+
+impl RetryHint {
+    pub fn new(raw: impl Into<String>) -> Self {
+        Self(raw.into())
+    }
+
+    /// 距离now还需要等待的时间，没有提示或已过期时返回None
+    pub fn delay_from(&self, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+        let raw = self.0.trim();
+        if let Ok(secs) = raw.parse::<u64>() {
+            return (secs > 0).then(|| Duration::from_secs(secs));
+        }
+
+        let beijing = chrono::FixedOffset::east_opt(8 * 3600)?;
+        let at = chrono::NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S").ok()?
+            .and_local_timezone(beijing)
+            .single()?;
+        (at.with_timezone(&chrono::Utc) - now).to_std().ok()
+            .filter(|delay| !delay.is_zero())
+    }
+
+    pub fn delay(&self) -> Option<Duration> {
+        self.delay_from(chrono::Utc::now())
+    }
+}
 
 // 自定义反序列化函数，用于将字符串转换为数字
 fn deserialize_string_to_u32<'de, D>(deserializer: D) -> std::result::Result<u32, D::Error>
@@ -35,12 +254,65 @@ pub struct LiveStreamData {
     pub rtmp: RtmpData,
     pub rtmp_backup: Option<serde_json::Value>,
     pub service_source: String,
-    pub status: String,
+    pub status: StreamLifecycle,
     pub sub_session_key: String,
-    pub try_time: String,
+    pub try_time: RetryHint,
     pub up_stream_extra: UpStreamExtra,
 }
 
+impl LiveStreamData {
+    /// 推流码可用前需要等待的时间，已在直播或没有提示时返回None
+    pub fn ready_delay(&self) -> Option<Duration> {
+        if self.status.is_live() {
+            return None;
+        }
+        self.try_time.delay().map(|delay| delay.min(MAX_READY_WAIT))
+    }
+}
+
+/// OBS可直接导入的推流服务配置（service.json），使用自定义服务
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObsServiceProfile {
+    pub settings: ObsServiceSettings,
+    #[serde(rename = "type")]
+    pub service_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObsServiceSettings {
+    pub server: String,
+    pub key: String,
+    pub use_auth: bool,
+    pub bwtest: bool,
+}
+
+impl ObsServiceProfile {
+    /// OBS自定义推流服务的类型名，rtmp和srt地址都使用该类型
+    pub const CUSTOM_SERVICE: &'static str = "rtmp_custom";
+    
+    pub fn new(server: &str, key: &StreamKey) -> Self {
+        Self {
+            settings: ObsServiceSettings {
+                server: server.to_string(),
+                key: key.reveal().to_string(),
+                use_auth: false,
+                bwtest: false,
+            },
+            service_type: Self::CUSTOM_SERVICE.to_string(),
+        }
+    }
+    
+    /// 写入JSON文件，父目录不存在时自动创建
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_string_pretty(self)?).await?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NoticeData {
     pub button_text: String,
@@ -60,29 +332,43 @@ pub struct UpStreamExtra {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RtmpData {
     pub addr: String,
-    pub code: String,
+    pub code: StreamKey,
     pub new_link: String,
     pub provider: String,
     #[serde(rename = "type")]
     pub rtmp_type: i32,
 }
 
+/// 当前推流地址
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpStreamAddrData {
+    pub addr: UpStreamAddr,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpStreamAddr {
+    pub addr: String,
+    pub code: StreamKey,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Protocol {
     pub protocol: String,
     pub addr: String,
-    pub code: String,
+    pub code: StreamKey,
     pub new_link: String,
     pub provider: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AreaData {
-    #[serde(deserialize_with = "deserialize_string_to_u32")]
+    #[serde(alias = "area_id", deserialize_with = "deserialize_string_to_u32")]
     pub id: u32,
+    #[serde(alias = "area_name")]
     pub name: String,
-    #[serde(deserialize_with = "deserialize_string_to_u32")]
+    #[serde(alias = "parent_area_id", deserialize_with = "deserialize_string_to_u32")]
     pub parent_id: u32,
+    #[serde(alias = "parent_area_name")]
     pub parent_name: String,
     #[serde(deserialize_with = "deserialize_string_to_u32")]
     pub act_id: u32,
@@ -113,10 +399,121 @@ pub struct AreaCategory {
     pub list: Vec<AreaData>,
 }
 
+impl AreaData {
+    /// 带父分区的完整名称，如 `网游 - 英雄联盟`
+    pub fn full_name(&self) -> String {
+        format!("{} - {}", self.parent_name, self.name)
+    }
+    
+    /// 分区是否被锁定，锁定的分区无法开播
+    pub fn is_locked(&self) -> bool {
+        self.lock_status != 0
+    }
+    
+    /// 分区路径
+    pub fn path(&self) -> AreaPath {
+        AreaPath {
+            parent_id: self.parent_id,
+            parent_name: self.parent_name.clone(),
+            id: self.id,
+            name: self.name.clone(),
+        }
+    }
+}
+
+/// 子分区及其父分区，显示为 `网游 > 英雄联盟`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AreaPath {
+    pub parent_id: u32,
+    pub parent_name: String,
+    pub id: u32,
+    pub name: String,
+}
+
+impl std::fmt::Display for AreaPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} > {}", self.parent_name, self.name)
+    }
+}
+
+/// 切换分区被拒绝时把原因（分区被锁/需要实名/需要人脸）解析为Permission错误，其余错误原样返回
+fn explain_area_error(error: BiliError) -> BiliError {
+    let BiliError::Api(code, message) = error.root() else {
+        return error;
+    };
+    match AREA_REJECT_REASONS.iter().find(|(keywords, _)| keywords.iter().any(|keyword| message.contains(keyword))) {
+        Some((_, reason)) => BiliError::Permission(format!("无法切换到该分区，{}（code={}, {}）", reason, code, message)),
+        None => error,
+    }
+}
+
+/// 在分区列表中按子分区ID查找分区
+pub fn find_area(areas: &[AreaCategory], area_id: u32) -> Option<&AreaData> {
+    areas.iter()
+        .flat_map(|category| category.list.iter())
+        .find(|area| area.id == area_id)
+}
+
+/// 拼音是否匹配关键词：包含关键词，或关键词按顺序出现在拼音中且首字母相同（如"ys"匹配"yuanshen"）
+fn pinyin_matches(pinyin: &str, keyword: &str) -> bool {
+    if pinyin.is_empty() {
+        return false;
+    }
+    if pinyin.contains(keyword) {
+        return true;
+    }
+    let mut letters = pinyin.chars();
+    let mut wanted = keyword.chars();
+    if wanted.next() != letters.next() {
+        return false;
+    }
+    wanted.all(|c| letters.any(|l| l == c))
+}
+
+/// 按关键词搜索子分区，匹配分区名、父分区名或拼音（不区分大小写）
+///
+/// 空格分隔的多个关键词需要同时匹配，关键词为空时返回全部。
+pub fn search_areas(areas: &[AreaCategory], keyword: &str) -> Vec<AreaData> {
+    let keyword = keyword.to_lowercase();
+    let keywords: Vec<&str> = keyword.split_whitespace().collect();
+    areas.iter()
+        .flat_map(|category| category.list.iter())
+        .filter(|area| {
+            let name = area.name.to_lowercase();
+            let parent_name = area.parent_name.to_lowercase();
+            let pinyin = area.pinyin.to_lowercase();
+            keywords.iter().all(|keyword| {
+                name.contains(keyword)
+                    || parent_name.contains(keyword)
+                    || pinyin_matches(&pinyin, keyword)
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// 修改标题的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TitleUpdate {
+    /// 实际设置的标题
+    pub title: String,
+    /// 标题过长，已被自动截断
+    pub truncated: bool,
+}
+
+impl TitleUpdate {
+    /// 标题被截断时给用户的提示
+    pub fn truncation_notice(&self) -> Option<String> {
+        self.truncated.then(|| format!("标题超过{}个字，已截断为: {}", MAX_TITLE_LEN, self.title))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LiveStartData {
+    #[serde(alias = "roomid")]
     pub room_id: u64,
     pub platform: String,
+    #[serde(alias = "area_id")]
     pub area_v2: u32,
     pub backup_stream: String,
     pub csrf_token: String,
@@ -125,14 +522,145 @@ pub struct LiveStartData {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LiveStopData {
+    #[serde(alias = "roomid")]
     pub room_id: u64,
     pub platform: String,
     pub csrf_token: String,
     pub csrf: String,
 }
 
+/// 下播接口返回的数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LiveStopResult {
+    #[serde(default)]
+    pub change: i32,
+    #[serde(default)]
+    pub status: String,
+}
+
+/// 开播过程中的回退信息，便于复现被拦截账号的偶发失败
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartDiagnostics {
+    /// 最终成功的请求是否带App签名
+    pub signed: bool,
+    /// 重试次数
+    pub retried: u32,
+    /// 最终使用的接口地址
+    pub endpoint: String,
+}
+
+/// 图片上传接口返回的数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverUploadData {
+    #[serde(default)]
+    pub location: String,
+}
+
+/// 人脸认证检查接口返回的数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FaceAuthData {
+    #[serde(default)]
+    pub is_identified: bool,
+}
+
+/// 开播前的人脸认证状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceAuthStatus {
+    /// 账号无需人脸认证
+    NotRequired,
+    /// 需要先在B站App完成人脸认证才能开播
+    Required,
+    /// 已完成人脸认证
+    Completed,
+}
+
+impl FaceAuthStatus {
+    /// 根据接口返回判断状态：返回码60024表示需要认证，成功但没有数据表示无需认证
+    pub fn from_response(response: &ApiResponse<FaceAuthData>) -> Result<Self> {
+        match response.code {
+            0 => Ok(match &response.data {
+                Some(data) if data.is_identified => FaceAuthStatus::Completed,
+                Some(_) => FaceAuthStatus::Required,
+                None => FaceAuthStatus::NotRequired,
+            }),
+            FACE_AUTH_REQUIRED_CODE => Ok(FaceAuthStatus::Required),
+            code => Err(crate::error::BiliError::api_error(code, response.get_message())),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FaceAuthStatus::NotRequired => "无需认证",
+            FaceAuthStatus::Required => "需要人脸认证",
+            FaceAuthStatus::Completed => "已认证",
+        }
+    }
+}
+
+/// 开播所需的账号和直播间条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LivePermission {
+    /// 是否已开通直播间
+    pub has_room: bool,
+    /// 是否已完成实名认证
+    pub real_name_verified: bool,
+    /// 直播间是否被封禁
+    pub room_locked: bool,
+}
+
+impl LivePermission {
+    /// 根据直播间号、实名接口的data和直播间信息判断
+    pub fn from_parts(room_id: u64, realname: &serde_json::Value, room_info: &RoomInfo) -> Self {
+        Self {
+            has_room: room_id != 0,
+            real_name_verified: json_i64(realname, "status") == Some(1),
+            room_locked: room_info.lock_status != 0,
+        }
+    }
+    
+    /// 当前是否允许开播
+    pub fn can_start(&self) -> bool {
+        self.has_room && self.real_name_verified && !self.room_locked
+    }
+    
+    /// 不满足的条件，全部满足时为空
+    pub fn problems(&self) -> Vec<&'static str> {
+        let mut problems = Vec::new();
+        if !self.has_room {
+            problems.push("尚未开通直播间，请先在B站直播中心开通");
+        }
+        if !self.real_name_verified {
+            problems.push("账号尚未实名认证，请先在B站App完成实名认证");
+        }
+        if self.room_locked {
+            problems.push("直播间已被封禁，暂时无法开播");
+        }
+        problems
+    }
+}
+
+/// 下播结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopOutcome {
+    /// 直播间已下播
+    Stopped,
+    /// 请求已受理，但直播间仍处于直播状态
+    Pending,
+}
+
+impl LiveStopResult {
+    pub fn outcome(&self) -> StopOutcome {
+        if self.status == "LIVE" {
+            StopOutcome::Pending
+        } else {
+            StopOutcome::Stopped
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TitleUpdateData {
+    #[serde(alias = "roomid")]
     pub room_id: u64,
     pub platform: String,
     pub title: String,
@@ -140,214 +668,724 @@ pub struct TitleUpdateData {
     pub csrf: String,
 }
 
+/// 分区列表缓存，分区很少变化，过期或手动刷新前不再重复请求
+#[derive(Debug, Default)]
+pub struct AreaListCache {
+    entry: Mutex<Option<(Instant, Vec<AreaCategory>)>>,
+}
+
+impl AreaListCache {
+    /// 获取未过期的缓存
+    pub fn get(&self) -> Option<Vec<AreaCategory>> {
+        let entry = self.entry.lock().unwrap();
+        entry.as_ref()
+            .filter(|(at, _)| at.elapsed() < AREA_LIST_TTL)
+            .map(|(_, areas)| areas.clone())
+    }
+    
+    /// 写入缓存
+    pub fn put(&self, areas: Vec<AreaCategory>) {
+        *self.entry.lock().unwrap() = Some((Instant::now(), areas));
+    }
+    
+    /// 使缓存失效，下次读取会重新请求
+    pub fn invalidate(&self) {
+        *self.entry.lock().unwrap() = None;
+    }
+}
+
+/// 直播间信息（room/v1/Room/get_info），B站偶尔少返回字段，缺失的字段取默认值
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomInfo {
+    pub room_id: u64,
+    pub uid: u64,
+    pub title: String,
+    /// 0未开播，1直播中，2轮播中
+    pub live_status: i32,
+    #[serde(deserialize_with = "deserialize_string_to_u32")]
+    pub area_id: u32,
+    pub area_name: String,
+    #[serde(deserialize_with = "deserialize_string_to_u32")]
+    pub parent_area_id: u32,
+    pub parent_area_name: String,
+    /// 人气值
+    pub online: u64,
+    /// 关注数
+    pub attention: u64,
+    /// 非0表示直播间被封禁
+    pub lock_status: i64,
+}
+
+impl RoomInfo {
+    pub fn is_live(&self) -> bool {
+        self.live_status == 1
+    }
+}
+
+/// 手动指定的直播间不是登录账号的直播间时返回警告，账号直播间未知时不判断
+pub fn room_mismatch_warning(own_room_id: u64, target_room_id: u64) -> Option<String> {
+    (own_room_id != 0 && own_room_id != target_room_id).then(|| {
+        format!("直播间{}不是当前登录账号的直播间（{}），可能无权操作", target_room_id, own_room_id)
+    })
+}
+
+/// 确定要操作的直播间：未手动指定时使用登录账号的直播间，指定的短号转换为真实房间号
+///
+/// 转换失败时按指定的房间号原样使用，并在返回的警告中说明。
+pub async fn resolve_target_room(client: &BilibiliClient, endpoints: &Endpoints, explicit: Option<u64>, own_room_id: u64) -> (u64, Option<String>) {
+    let Some(explicit) = explicit else {
+        return (own_room_id, None);
+    };
+    match Live::resolve_room_id(client, endpoints, explicit).await {
+        Ok(room_id) => (room_id, room_mismatch_warning(own_room_id, room_id)),
+        Err(e) => (explicit, Some(format!("解析直播间号{}失败: {}，按原样使用", explicit, e))),
+    }
+}
+
+/// 直播间信息缓存，避免短时间内重复请求 get_info
+#[derive(Debug, Default)]
+pub struct RoomInfoCache {
+    entry: Mutex<Option<(Instant, RoomInfo)>>,
+}
+
+impl RoomInfoCache {
+    /// 获取未过期的缓存
+    pub fn get(&self) -> Option<RoomInfo> {
+        let entry = self.entry.lock().unwrap();
+        entry.as_ref()
+            .filter(|(at, _)| at.elapsed() < ROOM_INFO_TTL)
+            .map(|(_, value)| value.clone())
+    }
+    
+    /// 写入缓存
+    pub fn put(&self, value: RoomInfo) {
+        *self.entry.lock().unwrap() = Some((Instant::now(), value));
+    }
+    
+    /// 使缓存失效，下次读取会重新请求
+    pub fn invalidate(&self) {
+        *self.entry.lock().unwrap() = None;
+    }
+    
+    /// 命中缓存则直接返回，否则调用fetch并缓存结果
+    pub async fn get_or_fetch<F>(&self, fetch: F) -> Result<RoomInfo>
+    where
+        F: Future<Output = Result<RoomInfo>>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+        
+        let value = fetch.await?;
+        self.put(value.clone());
+        Ok(value)
+    }
+}
+
 pub struct Live {
     client: BilibiliClient,
     room_id: u64,
+    /// 创建时传入的CSRF token，csrf_from_jar为true时只在jar中没有 `bili_jct` 时使用
     csrf: String,
+    csrf_from_jar: bool,
+    room_info_cache: RoomInfoCache,
+    area_list_cache: AreaListCache,
+    /// 标题过长时是否自动截断，关闭时返回错误
+    auto_truncate_title: bool,
+    /// 调试用：关闭App签名，请求参数原样发送
+    disable_signing: bool,
+    /// 偏好的推流协议，如rtmp、srt
+    stream_protocol: String,
+    /// 正在运行的心跳任务，下播时取消
+    heartbeat: Mutex<Option<CancellationToken>>,
+    /// 直播接口的服务地址，目前用于直播间信息和修改直播间的接口
+    live_api: String,
+}
+
+/// 心跳失败时发出的提示，failures为连续失败的次数
+#[derive(Debug)]
+pub struct HeartbeatAlert {
+    pub failures: u32,
+    pub error: BiliError,
+}
+
+impl HeartbeatAlert {
+    /// 是否刚好连续失败 [`HEARTBEAT_MAX_FAILURES`] 次，此时直播可能被判定异常，需要醒目地提示用户
+    pub fn reached_limit(&self) -> bool {
+        self.failures == HEARTBEAT_MAX_FAILURES
+    }
+}
+
+impl fmt::Display for HeartbeatAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.failures >= HEARTBEAT_MAX_FAILURES {
+            write!(f, "直播心跳连续{}次失败，直播可能被判定异常: {}", self.failures, self.error)
+        } else {
+            write!(f, "直播心跳失败（连续{}次）: {}", self.failures, self.error)
+        }
+    }
+}
+
+/// 后台心跳任务的句柄，调用stop、下播或drop时停止
+pub struct HeartbeatHandle {
+    cancel: CancellationToken,
+    alerts: mpsc::Receiver<HeartbeatAlert>,
+}
+
+impl HeartbeatHandle {
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+    
+    pub fn is_stopped(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+    
+    /// 取出已发生的失败提示，不等待
+    pub fn try_alert(&mut self) -> Option<HeartbeatAlert> {
+        self.alerts.try_recv().ok()
+    }
+    
+    /// 等待下一次失败提示，任务停止后返回None
+    pub async fn next_alert(&mut self) -> Option<HeartbeatAlert> {
+        self.alerts.recv().await
+    }
+}
+
+impl Drop for HeartbeatHandle {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
 }
 
 impl Live {
+    /// 使用默认配置（代理取自HTTP_PROXY环境变量）创建，CSRF token固定为传入的值
     pub fn new(room_id: u64, csrf: String, cookie_str: &str) -> Result<Self> {
-        let client = BilibiliClient::with_cookies(cookie_str)?;
-        Ok(Self {
-            client,
-            room_id,
-            csrf,
-        })
+        Self::from_config(&Config::default(), room_id, csrf, cookie_str)
+    }
+    
+    /// 按配置文件的超时、代理和限速创建，CSRF token固定为传入的值
+    pub fn from_config(config: &Config, room_id: u64, csrf: String, cookie_str: &str) -> Result<Self> {
+        Self::new_with_cookies_map_and_config(config, room_id, csrf, &BilibiliClient::parse_cookies(cookie_str)?)
     }
     
     pub fn new_with_cookies_map(room_id: u64, csrf: String, cookies: &std::collections::HashMap<String, String>) -> Result<Self> {
-        let client = BilibiliClient::with_cookies_map(cookies)?;
-        Ok(Self {
-            client,
-            room_id,
-            csrf,
-        })
+        Self::new_with_cookies_map_and_config(&Config::default(), room_id, csrf, cookies)
+    }
+    
+    fn new_with_cookies_map_and_config(config: &Config, room_id: u64, csrf: String, cookies: &HashMap<String, String>) -> Result<Self> {
+        let client = BilibiliClient::from_config(config)?;
+        client.add_cookies(cookies);
+        let mut live = Self::with_client(client, room_id, csrf);
+        live.csrf_from_jar = false;
+        Ok(live)
     }
     
+    /// 使用共享客户端创建，CSRF token随jar中的 `bili_jct` 更新
     pub fn with_client(client: BilibiliClient, room_id: u64, csrf: String) -> Self {
         Self {
             client,
             room_id,
             csrf,
+            csrf_from_jar: true,
+            room_info_cache: RoomInfoCache::default(),
+            area_list_cache: AreaListCache::default(),
+            auto_truncate_title: false,
+            disable_signing: false,
+            stream_protocol: DEFAULT_STREAM_PROTOCOL.to_string(),
+            heartbeat: Mutex::new(None),
+            live_api: Endpoints::default().live_api,
         }
     }
     
-    /// 开始直播
-    pub async fn start_live(&self, area_id: u32) -> Result<LiveStreamData> {
-        let url = "https://api.live.bilibili.com/room/v1/Room/startLive";
-        
-        let mut params = HashMap::new();
-        params.insert("room_id".to_string(), self.room_id.to_string());
-        params.insert("area_v2".to_string(), area_id.to_string());
-        params.insert("platform".to_string(), "pc_link".to_string());
-        params.insert("backup_stream".to_string(), "0".to_string());
-        params.insert("type".to_string(), "2".to_string());
-        params.insert("csrf_token".to_string(), self.csrf.clone());
-        params.insert("csrf".to_string(), self.csrf.clone());
-        
-        // 使用App签名增强安全性
-        let signed_params = crate::sign::Signer::sign_live_request(params);
+    /// 设置标题过长时是否自动截断
+    pub fn with_auto_truncate_title(mut self, enabled: bool) -> Self {
+        self.auto_truncate_title = enabled;
+        self
+    }
+    
+    /// 关闭App签名，用于区分认证问题和签名问题
+    pub fn with_signing_disabled(mut self, disabled: bool) -> Self {
+        self.disable_signing = disabled;
+        self
+    }
+    
+    /// 使用配置中的服务地址
+    pub fn with_endpoints(mut self, endpoints: &Endpoints) -> Self {
+        self.live_api = endpoints.live_api.clone();
+        self
+    }
+    
+    /// 设置偏好的推流协议
+    pub fn with_stream_protocol(mut self, protocol: impl Into<String>) -> Self {
+        self.stream_protocol = protocol.into();
+        self
+    }
+    
+    /// 对请求参数做App签名，关闭签名时原样返回
+    pub fn sign_params(&self, params: HashMap<String, String>) -> HashMap<String, String> {
+        if self.disable_signing {
+            params
+        } else {
+            Signer::sign_live_request(params)
+        }
+    }
+    
+    /// 签名后发送POST，签名结果缺少csrf或sign参数时提前报错
+    async fn post_signed<T: for<'de> Deserialize<'de>>(&self, url: &str, params: HashMap<String, String>) -> Result<ApiResponse<T>> {
+        let signed_params = self.sign_params(params);
+        check_signed_form(&signed_params, !self.disable_signing)?;
         let data: Vec<_> = signed_params.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
         
-        let response: ApiResponse<LiveStreamData> = self.client.post(url, &data).await?;
-        let stream_data = response.data.ok_or_else(|| crate::error::BiliError::Live("获取推流信息失败".to_string()))?;
+        self.client.post(url, &data).await
+    }
+    
+    /// 开始直播
+    ///
+    /// 直播间尚未进入直播状态时推流码需要等待 [`LiveStreamData::ready_delay`] 后才生效，
+    /// 该函数不会等待，由调用方决定如何等待和提示。
+    pub async fn start_live(&self, area_id: u32) -> Result<(LiveStreamData, StartDiagnostics)> {
+        let mut params = HashMap::new();
+        params.insert("room_id".to_string(), self.room_id.to_string());
+        params.insert("area_v2".to_string(), area_id.to_string());
+        params.insert("platform".to_string(), "pc_link".to_string());
+        params.insert("backup_stream".to_string(), "0".to_string());
+        params.insert("type".to_string(), "2".to_string());
+        self.insert_csrf(&mut params);
+        
+        // 优先使用App签名增强安全性，签名被拒时回退为不签名
+        let (stream_data, diagnostics) = start_with_fallback(START_LIVE_URL, !self.disable_signing, |signed| {
+            let params = params.clone();
+            async move {
+                let params = if signed {
+                    Signer::sign_live_request(params)
+                } else {
+                    params
+                };
+                check_signed_form(&params, signed)?;
+                let data: Vec<_> = params.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+                
+                let response: ApiResponse<LiveStreamData> = self.client.post(START_LIVE_URL, &data).await?;
+                response.data.ok_or_else(|| crate::error::BiliError::Live("获取推流信息失败".to_string()))
+            }
+        }).await?;
+        self.room_info_cache.invalidate();
+        
+        // 需要人脸认证时这次开播并未生效，推流码也无法使用
+        if stream_data.need_face_auth {
+            return Err(crate::error::BiliError::FaceAuthRequired(stream_data.qr));
+        }
+        
+        Ok((stream_data, diagnostics))
+    }
+    
+    /// 获取当前推流地址和推流码，不会重置推流码
+    pub async fn get_stream_addr(&self) -> Result<(String, StreamKey)> {
+        let url = "https://api.live.bilibili.com/xlive/app-blink/v1/live/getWebUpStreamAddr?platform=pc";
+        
+        let response: ApiResponse<UpStreamAddrData> = self.client.get(url).await?;
+        let data = response.data.ok_or_else(|| crate::error::BiliError::Live("获取推流地址失败".to_string()))?;
         
-        Ok(stream_data)
+        Ok((data.addr.addr, data.addr.code))
+    }
+    
+    /// 开播前查询人脸认证状态，避免在开播接口返回 need_face_auth 后才发现
+    pub async fn face_auth_status(&self) -> Result<FaceAuthStatus> {
+        let mut params = HashMap::from([
+            ("room_id".to_string(), self.room_id.to_string()),
+            ("face_auth_code".to_string(), FACE_AUTH_REQUIRED_CODE.to_string()),
+        ]);
+        self.insert_csrf(&mut params);
+        let data: Vec<_> = params.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        
+        let response: ApiResponse<FaceAuthData> = self.client.post(FACE_AUTH_URL, &data).await?;
+        FaceAuthStatus::from_response(&response)
+    }
+    
+    /// 检查实名认证和直播间状态，判断当前是否允许开播
+    pub async fn check_live_permission(&self) -> Result<LivePermission> {
+        let response: ApiResponse<serde_json::Value> = self.client.get(REALNAME_STATUS_URL).await.context("获取实名认证状态")?;
+        let realname = response.data.unwrap_or_default();
+        let room_info = if self.room_id == 0 {
+            RoomInfo::default()
+        } else {
+            self.get_room_info().await?
+        };
+        
+        Ok(LivePermission::from_parts(self.room_id, &realname, &room_info))
+    }
+    
+    /// 轮询人脸认证状态直到完成，超时返回Timeout错误
+    pub async fn wait_face_auth(&self, timeout: Duration) -> Result<()> {
+        poll_face_auth(|| self.face_auth_status(), FACE_AUTH_POLL_INTERVAL, timeout).await
+    }
+    
+    /// 获取正在直播时当前使用的推流地址和推流码，不会重新开播导致推流码变更；未在直播时返回None
+    pub async fn get_active_stream_info(&self) -> Result<Option<(String, StreamKey)>> {
+        if !self.is_live().await.context("查询直播状态")? {
+            return Ok(None);
+        }
+        self.get_stream_addr().await.map(Some)
+    }
+    
+    /// 上次开播在保存推流码之前中断时，通过fetch找回现有推流码
+    ///
+    /// 只有存在待确认标记且房间正在直播时才会调用fetch，避免再次开播导致推流码变更；返回None表示无需恢复。
+    pub async fn recover_pending_start<F, Fut>(config: &Config, is_live: bool, fetch: F) -> Option<Result<(String, StreamKey)>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(String, StreamKey)>>,
+    {
+        if !config.has_pending_start() || !is_live {
+            return None;
+        }
+        Some(fetch().await)
     }
     
     /// 停止直播
-    pub async fn stop_live(&self) -> Result<()> {
+    pub async fn stop_live(&self) -> Result<StopOutcome> {
         let url = "https://api.live.bilibili.com/room/v1/Room/stopLive";
         
         let mut params = HashMap::new();
         params.insert("room_id".to_string(), self.room_id.to_string());
         params.insert("platform".to_string(), "pc_link".to_string());
-        params.insert("csrf_token".to_string(), self.csrf.clone());
-        params.insert("csrf".to_string(), self.csrf.clone());
+        self.insert_csrf(&mut params);
         
         // 使用App签名增强安全性
-        let signed_params = crate::sign::Signer::sign_live_request(params);
-        let data: Vec<_> = signed_params.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let response: ApiResponse<LiveStopResult> = self.post_signed(url, params).await?;
+        self.room_info_cache.invalidate();
         
-        let _response: ApiResponse<serde_json::Value> = self.client.post(url, &data).await?;
+        let mut outcome = response.data.unwrap_or_default().outcome();
+        if outcome == StopOutcome::Pending {
+            // 接口仍返回LIVE时再确认一次直播间状态
+            if let Ok(false) = self.is_live().await {
+                outcome = StopOutcome::Stopped;
+            }
+        }
         
-        Ok(())
+        // 确认下播后才停止心跳，下播失败时直播间仍需保活
+        if outcome == StopOutcome::Stopped {
+            self.stop_heartbeat();
+        }
+        Ok(outcome)
     }
     
-    /// 设置直播标题
-    pub async fn set_title(&self, title: &str) -> Result<()> {
-        let url = "https://api.live.bilibili.com/room/v1/Room/update";
+    /// 开播后按 [`HEARTBEAT_INTERVAL`] 在后台上报直播心跳，替换掉之前的心跳任务
+    ///
+    /// 每次失败都通过句柄发出提示，连续失败 [`HEARTBEAT_MAX_FAILURES`] 次时 [`HeartbeatAlert::reached_limit`] 为true；
+    /// [`Live::stop_live`] 确认下播后会停止心跳。
+    /// 每次心跳都重新读取CSRF token，cookie刷新后仍然有效。
+    pub fn start_heartbeat(&self) -> HeartbeatHandle {
+        let cancel = CancellationToken::new();
+        if let Some(previous) = self.heartbeat.lock().unwrap().replace(cancel.clone()) {
+            previous.cancel();
+        }
+        
+        let client = self.client.clone();
+        let room_id = self.room_id.to_string();
+        let (fixed_csrf, csrf_from_jar) = (self.csrf.clone(), self.csrf_from_jar);
+        let alerts = spawn_heartbeat_with(move || {
+            let client = client.clone();
+            let room_id = room_id.clone();
+            let csrf = current_csrf(&client, &fixed_csrf, csrf_from_jar);
+            async move {
+                let data = [("room_id", room_id.as_str()), ("csrf_token", csrf.as_str()), ("csrf", csrf.as_str())];
+                client.post::<serde_json::Value>(HEARTBEAT_URL, &data).await.map(|_| ())
+            }
+        }, HEARTBEAT_INTERVAL, cancel.clone());
+        HeartbeatHandle { cancel, alerts }
+    }
+    
+    /// 停止正在运行的心跳任务
+    pub fn stop_heartbeat(&self) {
+        if let Some(cancel) = self.heartbeat.lock().unwrap().take() {
+            cancel.cancel();
+        }
+    }
+    
+    /// 设置直播标题，返回实际设置的标题，开启自动截断时由调用方提示截断结果
+    pub async fn set_title(&self, title: &str) -> Result<TitleUpdate> {
+        let url = format!("{}{}", self.live_api, ROOM_UPDATE_PATH);
+        
+        let update = prepare_title(title, self.auto_truncate_title)?;
         
         let mut params = HashMap::new();
         params.insert("room_id".to_string(), self.room_id.to_string());
         params.insert("platform".to_string(), "pc_link".to_string());
-        params.insert("title".to_string(), title.to_string());
-        params.insert("csrf_token".to_string(), self.csrf.clone());
-        params.insert("csrf".to_string(), self.csrf.clone());
+        params.insert("title".to_string(), update.title.clone());
+        self.insert_csrf(&mut params);
         
         // 使用App签名增强安全性
-        let signed_params = crate::sign::Signer::sign_live_request(params);
-        let data: Vec<_> = signed_params.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let _response: ApiResponse<serde_json::Value> = self.post_signed(&url, params).await?;
+        self.room_info_cache.invalidate();
         
-        let _response: ApiResponse<serde_json::Value> = self.client.post(url, &data).await?;
+        Ok(update)
+    }
+    
+    /// 上传图片并设置为直播间封面，返回封面地址
+    pub async fn upload_cover(&self, image_path: &str) -> Result<String> {
+        let image = tokio::fs::read(image_path).await?;
+        let mime = check_cover(&image)?;
+        let file_name = std::path::Path::new(image_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "cover".to_string());
         
-        Ok(())
+        let response: ApiResponse<CoverUploadData> = self.client.post_multipart(COVER_UPLOAD_URL, || {
+            let part = Part::bytes(image.clone())
+                .file_name(file_name.clone())
+                .mime_str(mime)?;
+            Ok(Form::new()
+                .text("bucket", "live")
+                .text("dir", "new_room_cover")
+                .text("csrf", self.get_csrf())
+                .part("file", part))
+        }).await.context("上传封面图片")?;
+        let cover_url = cover_location(response)?;
+        
+        let url = format!("{}{}", self.live_api, ROOM_UPDATE_PATH);
+        let mut params = HashMap::new();
+        params.insert("room_id".to_string(), self.room_id.to_string());
+        params.insert("platform".to_string(), "pc_link".to_string());
+        params.insert("cover".to_string(), cover_url.clone());
+        self.insert_csrf(&mut params);
+        
+        let _response: ApiResponse<serde_json::Value> = self.post_signed(&url, params).await?;
+        self.room_info_cache.invalidate();
+        
+        Ok(cover_url)
     }
     
     /// 设置直播分区
     pub async fn set_area(&self, area_id: u32) -> Result<()> {
-        let url = "https://api.live.bilibili.com/room/v1/Room/update";
+        let url = format!("{}{}", self.live_api, ROOM_UPDATE_PATH);
         
         let mut params = HashMap::new();
         params.insert("room_id".to_string(), self.room_id.to_string());
         params.insert("area_id".to_string(), area_id.to_string());
         params.insert("activity_id".to_string(), "0".to_string());
         params.insert("platform".to_string(), "pc_link".to_string());
-        params.insert("csrf_token".to_string(), self.csrf.clone());
-        params.insert("csrf".to_string(), self.csrf.clone());
+        self.insert_csrf(&mut params);
         
         // 使用App签名增强安全性
-        let signed_params = crate::sign::Signer::sign_live_request(params);
-        let data: Vec<_> = signed_params.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let _response: ApiResponse<serde_json::Value> = self.post_signed(&url, params).await
+            .map_err(explain_area_error)?;
+        self.room_info_cache.invalidate();
         
-        let _response: ApiResponse<serde_json::Value> = self.client.post(url, &data).await?;
+        Ok(())
+    }
+    
+    /// 直接按分区ID设置分区，不获取分区列表，返回B站确认后的分区
+    pub async fn set_area_raw(&self, area_id: u32) -> Result<(u32, String)> {
+        set_area_and_confirm(area_id, |id| self.set_area(id), || self.get_current_area()).await
+    }
+    
+    /// 开启 auto_apply_last_area 时把分区恢复为上次使用的分区，返回恢复后的分区
+    ///
+    /// 当前分区已经一致或保存的分区已不在分区列表中时不做修改。
+    pub async fn apply_last_area(&self, config: &Config, areas: &[AreaCategory]) -> Result<Option<(u32, String)>> {
+        if !config.auto_apply_last_area {
+            return Ok(None);
+        }
         
+        let (current, _) = self.get_current_area().await.context("获取当前分区")?;
+        match config.last_area_to_apply(current, |id| find_area(areas, id).is_some()) {
+            Some(area_id) => self.set_area_raw(area_id).await.map(Some),
+            None => Ok(None),
+        }
+    }
+    
+    /// 恢复上次直播的标题和分区，标题为空时只恢复分区
+    pub async fn apply_last_settings(&self, settings: &LastSettings) -> Result<()> {
+        if !settings.live_title.is_empty() {
+            self.set_title(&settings.live_title).await.context("恢复直播标题")?;
+        }
+        if let Some(area_id) = settings.area() {
+            self.set_area_raw(area_id).await.context("恢复直播分区")?;
+        }
         Ok(())
     }
     
+    /// 开播成功后把直播间当前的标题和分区记为上次直播设置
+    pub async fn remember_settings(&self, config: &mut Config) -> Result<()> {
+        self.room_info_cache.invalidate();
+        let room_info = self.get_room_info().await?;
+        config.remember_live_settings(&room_info)
+    }
+    
     /// 获取直播分区列表
+    ///
+    /// 结果会缓存一段时间，需要最新列表时调用 [`Live::refresh_area_list`]。
     pub async fn get_area_list(&self) -> Result<Vec<AreaCategory>> {
-        let url = "https://api.live.bilibili.com/room/v1/Area/getList?show_pinyin=1";
+        if let Some(areas) = self.area_list_cache.get() {
+            return Ok(areas);
+        }
+        self.refresh_area_list().await
+    }
+    
+    /// 忽略缓存重新获取分区列表
+    pub async fn refresh_area_list(&self) -> Result<Vec<AreaCategory>> {
+        self.area_list_cache.invalidate();
+        let areas = Self::fetch_area_list(&self.client, !self.disable_signing).await?;
+        self.area_list_cache.put(areas.clone());
+        Ok(areas)
+    }
+    
+    /// 按子分区ID反查分区
+    pub async fn find_area_by_id(&self, area_id: u32) -> Result<Option<AreaData>> {
+        Ok(find_area(&self.get_area_list().await?, area_id).cloned())
+    }
+    
+    /// 按子分区ID解析分区路径，未知ID返回None
+    pub async fn area_path_for_id(&self, area_id: u32) -> Result<Option<AreaPath>> {
+        Ok(self.find_area_by_id(area_id).await?.as_ref().map(AreaData::path))
+    }
+    
+    /// 按关键词搜索分区，匹配分区名或父分区名
+    pub async fn find_areas_by_name(&self, keyword: &str) -> Result<Vec<AreaData>> {
+        Ok(search_areas(&self.get_area_list().await?, keyword))
+    }
+
+    /// 使用任意客户端获取分区列表，该接口无需登录，可在扫码期间预取
+    ///
+    /// wbi为true时附带WBI签名，获取密钥失败则退回不签名的请求。
+    pub async fn fetch_area_list(client: &BilibiliClient, wbi: bool) -> Result<Vec<AreaCategory>> {
+        let params = HashMap::from([("show_pinyin".to_string(), "1".to_string())]);
+        let params = if wbi {
+            match Signer::fetch_wbi_keys(client).await {
+                Ok((img_key, sub_key)) => Signer::wbi_sign(params.clone(), &img_key, &sub_key).unwrap_or(params),
+                Err(_) => params,
+            }
+        } else {
+            params
+        };
+        let url = format!("{}?{}", AREA_LIST_URL, Signer::to_query(&params));
         
-        let response: ApiResponse<Vec<AreaCategory>> = self.client.get(url).await?;
+        let response: ApiResponse<Vec<AreaCategory>> = client.get(&url).await?;
         let area_data = response.data.ok_or_else(|| crate::error::BiliError::Live("获取分区列表失败".to_string()))?;
         
         Ok(area_data)
     }
     
     /// 获取直播间信息
-    pub async fn get_room_info(&self) -> Result<serde_json::Value> {
-        let url = format!("https://api.live.bilibili.com/room/v1/Room/get_info?room_id={}", self.room_id);
+    pub async fn get_room_info(&self) -> Result<RoomInfo> {
+        self.room_info_cache.get_or_fetch(self.fetch_room_info()).await
+    }
+    
+    /// 请求直播间信息（不经过缓存）
+    async fn fetch_room_info(&self) -> Result<RoomInfo> {
+        Self::fetch_room_info_by_id(&self.client, &self.live_api, self.room_id).await
+    }
+    
+    /// 按房间号请求直播间信息，房间号可以是短号
+    async fn fetch_room_info_by_id(client: &BilibiliClient, live_api: &str, room_id: u64) -> Result<RoomInfo> {
+        let url = format!("{}{}?room_id={}", live_api, ROOM_INFO_PATH, room_id);
         
-        let response: ApiResponse<serde_json::Value> = self.client.get(&url).await?;
+        let response: ApiResponse<RoomInfo> = client.get(&url).await.context("获取直播间信息")?;
         let room_info = response.data.ok_or_else(|| crate::error::BiliError::Live("获取直播间信息失败".to_string()))?;
         
         Ok(room_info)
     }
     
+    /// 把直播间短号转换为真实房间号，传入的已是真实房间号时原样返回
+    pub async fn resolve_room_id(client: &BilibiliClient, endpoints: &Endpoints, room_id: u64) -> Result<u64> {
+        let room_info = Self::fetch_room_info_by_id(client, &endpoints.live_api, room_id).await?;
+        if room_info.room_id == 0 {
+            return Err(crate::error::BiliError::not_found(format!("直播间{}", room_id)));
+        }
+        Ok(room_info.room_id)
+    }
+    
+    /// 使直播间信息缓存失效
+    pub fn invalidate_room_info(&self) {
+        self.room_info_cache.invalidate();
+    }
+    
     /// 获取直播状态
     pub async fn get_live_status(&self) -> Result<i32> {
-        let room_info = self.get_room_info().await?;
-        
-        if let Some(live_status) = room_info.get("live_status") {
-            if let Some(status) = live_status.as_i64() {
-                return Ok(status as i32);
-            }
-        }
-        
-        Err(crate::error::BiliError::Live("获取直播状态失败".to_string()))
+        Ok(self.get_room_info().await?.live_status)
     }
     
     /// 检查是否正在直播
     pub async fn is_live(&self) -> Result<bool> {
-        let status = self.get_live_status().await?;
-        Ok(status == 1)
+        Ok(self.get_room_info().await?.is_live())
     }
     
     /// 获取当前直播标题
     pub async fn get_current_title(&self) -> Result<String> {
+        Ok(self.get_room_info().await?.title)
+    }
+    
+    /// 获取当前直播分区，未返回分区ID时报错
+    pub async fn get_current_area(&self) -> Result<(u32, String)> {
         let room_info = self.get_room_info().await?;
-        
-        if let Some(title) = room_info.get("title") {
-            if let Some(title_str) = title.as_str() {
-                return Ok(title_str.to_string());
-            }
+        if room_info.area_id == 0 {
+            return Err(crate::error::BiliError::Live("获取分区ID失败".to_string()));
         }
         
-        Err(crate::error::BiliError::Live("获取直播标题失败".to_string()))
+        Ok((room_info.area_id, room_info.area_name))
     }
     
-    /// 获取当前直播分区
-    pub async fn get_current_area(&self) -> Result<(u32, String)> {
+    /// 获取当前分区的完整路径，如 `网游 > 英雄联盟`
+    ///
+    /// 直播间信息中没有父分区名时从分区列表反查，反查失败只返回分区名。
+    pub async fn get_current_area_path(&self) -> Result<String> {
+        let (area_id, area_name) = self.get_current_area().await?;
         let room_info = self.get_room_info().await?;
-        
-        let area_id = room_info.get("area_id")
-            .and_then(|v| v.as_u64())
-            .ok_or_else(|| crate::error::BiliError::Live("获取分区ID失败".to_string()))?;
-        
-        let area_name = room_info.get("area_name")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| crate::error::BiliError::Live("获取分区名称失败".to_string()))?;
-        
-        Ok((area_id as u32, area_name.to_string()))
+        if !room_info.parent_area_name.is_empty() {
+            return Ok(format!("{} > {}", room_info.parent_area_name, area_name));
+        }
+        Ok(match self.area_path_for_id(area_id).await {
+            Ok(Some(path)) => path.to_string(),
+            _ => area_name,
+        })
     }
     
     /// 获取直播间统计信息
     pub async fn get_live_stats(&self) -> Result<serde_json::Value> {
-        let url = format!("https://api.live.bilibili.com/xlive/web-room/v1/index/getInfoByRoom?room_id={}", self.room_id);
-        
-        let response: ApiResponse<serde_json::Value> = self.client.get(&url).await?;
-        let stats = response.data.ok_or_else(|| crate::error::BiliError::Live("获取直播间统计信息失败".to_string()))?;
-        
-        Ok(stats)
+        fetch_live_stats(&self.client, &self.live_stats_url()).await
+    }
+    
+    fn live_stats_url(&self) -> String {
+        format!("{}?room_id={}", LIVE_STATS_URL, self.room_id)
+    }
+    
+    /// 在后台每隔interval拉取一次直播间实时数据
+    ///
+    /// 拉取失败时跳过本次、不发送任何数据，接收端关闭后任务自动退出。
+    pub fn spawn_stats_poller(&self, interval: Duration) -> mpsc::Receiver<LiveStats> {
+        spawn_stats_poller_with(self.client.clone(), self.live_stats_url(), interval)
     }
     
     /// 获取推流地址和推流码
-    pub fn parse_stream_info(&self, stream_data: &LiveStreamData) -> (String, String) {
+    pub fn parse_stream_info(&self, stream_data: &LiveStreamData) -> (String, StreamKey) {
         let server = stream_data.rtmp.addr.clone();
         let stream_key = stream_data.rtmp.code.clone();
         (server, stream_key)
     }
     
+    /// 在protocols中按协议名（不区分大小写）查找推流地址和推流码
+    pub fn parse_stream_info_by_protocol(&self, stream_data: &LiveStreamData, protocol: &str) -> Option<(String, StreamKey)> {
+        stream_data.protocols.iter()
+            .find(|p| p.protocol.eq_ignore_ascii_case(protocol) && !p.addr.is_empty())
+            .map(|p| (p.addr.clone(), p.code.clone()))
+    }
+    
+    /// 按偏好的协议解析推流信息，直播间未提供该协议时回落到rtmp
+    pub fn preferred_stream_info(&self, stream_data: &LiveStreamData) -> (String, StreamKey) {
+        self.parse_stream_info_by_protocol(stream_data, &self.stream_protocol)
+            .unwrap_or_else(|| self.parse_stream_info(stream_data))
+    }
+    
     /// 格式化推流信息输出
     pub fn format_stream_info(&self, stream_data: &LiveStreamData) -> String {
-        let (server, stream_key) = self.parse_stream_info(stream_data);
+        let (server, stream_key) = self.preferred_stream_info(stream_data);
         
         format!(
             "推流服务器: {}\n推流码: {}",
             server,
-            stream_key
+            stream_key.reveal()
         )
     }
     
@@ -358,13 +1396,783 @@ impl Live {
         Ok(())
     }
     
+    /// 按偏好的推流协议导出OBS的service.json，可在OBS中直接导入服务配置
+    pub async fn export_obs_profile(&self, stream_data: &LiveStreamData, path: impl AsRef<Path>) -> Result<()> {
+        let (server, stream_key) = self.preferred_stream_info(stream_data);
+        ObsServiceProfile::new(&server, &stream_key).save(path).await
+    }
+    
+    /// 获取底层客户端
+    pub fn get_client(&self) -> &BilibiliClient {
+        &self.client
+    }
+    
     /// 获取房间号
     pub fn get_room_id(&self) -> u64 {
         self.room_id
     }
     
     /// 获取CSRF token
-    pub fn get_csrf(&self) -> &str {
-        &self.csrf
+    ///
+    /// 通过共享客户端创建时优先读取jar中当前的 `bili_jct`，cookie刷新后无需重建Live。
+    pub fn get_csrf(&self) -> String {
+        current_csrf(&self.client, &self.csrf, self.csrf_from_jar)
+    }
+    
+    /// 写入请求需要的 `csrf_token` 和 `csrf` 参数
+    fn insert_csrf(&self, params: &mut HashMap<String, String>) {
+        let csrf = self.get_csrf();
+        params.insert("csrf_token".to_string(), csrf.clone());
+        params.insert("csrf".to_string(), csrf);
+    }
+}
+
+/// 共享客户端时优先使用jar中最新的 `bili_jct`，否则使用固定的CSRF token
+fn current_csrf(client: &BilibiliClient, fixed: &str, from_jar: bool) -> String {
+    if from_jar
+        && let Some(csrf) = client.get_cookie("bili_jct")
+    {
+        return csrf;
+    }
+    fixed.to_string()
+}
+
+/// 获取一次直播间统计数据
+async fn fetch_live_stats(client: &BilibiliClient, url: &str) -> Result<serde_json::Value> {
+    let response: ApiResponse<serde_json::Value> = client.get(url).await?;
+    response.data.ok_or_else(|| crate::error::BiliError::Live("获取直播间统计信息失败".to_string()))
+}
+
+fn spawn_stats_poller_with(client: BilibiliClient, url: String, interval: Duration) -> mpsc::Receiver<LiveStats> {
+    let (tx, rx) = mpsc::channel(4);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if tx.is_closed() {
+                break;
+            }
+            if let Ok(stats) = fetch_live_stats(&client, &url).await
+                && tx.send(LiveStats::from_json(&stats)).await.is_err()
+            {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// 按间隔循环执行心跳，每次失败都发送提示，成功后重新计数
+fn spawn_heartbeat_with<F, Fut>(mut beat: F, interval: Duration, cancel: CancellationToken) -> mpsc::Receiver<HeartbeatAlert>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    let (tx, rx) = mpsc::channel(4);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut failures = 0;
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = ticker.tick() => {}
+            }
+            let result = tokio::select! {
+                _ = cancel.cancelled() => break,
+                result = beat() => result,
+            };
+            match result {
+                Ok(()) => failures = 0,
+                Err(error) => {
+                    failures += 1;
+                    let _ = tx.try_send(HeartbeatAlert { failures, error });
+                }
+            }
+        }
+    });
+    rx
+}
+
+/// 检查标题长度，超出时按设置截断（在字符簇边界截断并追加…）或返回错误
+fn prepare_title(title: &str, auto_truncate: bool) -> Result<TitleUpdate> {
+    let graphemes: Vec<&str> = title.graphemes(true).collect();
+    if graphemes.len() <= MAX_TITLE_LEN {
+        return Ok(TitleUpdate { title: title.to_string(), truncated: false });
     }
-} 
\ No newline at end of file
+    
+    if !auto_truncate {
+        return Err(crate::error::BiliError::validation(format!("标题过长，最多{}个字", MAX_TITLE_LEN)));
+    }
+    
+    let truncated = format!("{}…", graphemes[..MAX_TITLE_LEN - 1].concat());
+    Ok(TitleUpdate { title: truncated, truncated: true })
+}
+
+/// 检查封面图片的大小，并按文件头识别格式，只接受jpg和png
+fn check_cover(image: &[u8]) -> Result<&'static str> {
+    if image.len() > MAX_COVER_SIZE {
+        return Err(crate::error::BiliError::validation(format!(
+            "封面图片过大: {}KB，最大{}MB",
+            image.len() / 1024,
+            MAX_COVER_SIZE / 1024 / 1024
+        )));
+    }
+    
+    if image.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Ok("image/jpeg")
+    } else if image.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Ok("image/png")
+    } else {
+        Err(crate::error::BiliError::validation("封面图片只支持jpg和png格式"))
+    }
+}
+
+/// 取出上传后的图片地址，地址为空视为上传失败
+fn cover_location(response: ApiResponse<CoverUploadData>) -> Result<String> {
+    response.data
+        .map(|data| data.location)
+        .filter(|location| !location.is_empty())
+        .ok_or_else(|| crate::error::BiliError::live("上传封面后未返回图片地址"))
+}
+
+/// 检查签名后的表单仍带有csrf，开启签名时还需带有sign，缺少时会被B站以-400拒绝
+fn check_signed_form(params: &HashMap<String, String>, signed: bool) -> Result<()> {
+    if params.get("csrf").is_none_or(|csrf| csrf.is_empty()) {
+        return Err(BiliError::validation("POST表单缺少csrf参数，请检查请求参数的构造"));
+    }
+    if signed && !params.contains_key("sign") {
+        return Err(BiliError::validation("签名后缺少sign参数，请检查签名参数的构造"));
+    }
+    Ok(())
+}
+
+/// 按间隔查询人脸认证状态，不再需要认证时返回，查询出错时直接返回错误
+async fn poll_face_auth<F, Fut>(mut fetch: F, interval: Duration, timeout: Duration) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<FaceAuthStatus>>,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        if fetch().await? != FaceAuthStatus::Required {
+            return Ok(());
+        }
+        if Instant::now() + interval > deadline {
+            return Err(crate::error::BiliError::timeout("等待人脸认证超时"));
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// 先发送签名请求，签名被拒时再发送一次不签名的请求，并记录过程
+async fn start_with_fallback<T, F, Fut>(endpoint: &str, sign_first: bool, mut attempt: F) -> Result<(T, StartDiagnostics)>
+where
+    F: FnMut(bool) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut diagnostics = StartDiagnostics {
+        signed: sign_first,
+        retried: 0,
+        endpoint: endpoint.to_string(),
+    };
+    
+    if !sign_first {
+        return attempt(false).await.map(|data| (data, diagnostics));
+    }
+
+    match attempt(true).await {
+        Ok(data) => Ok((data, diagnostics)),
+        Err(e) if e.error_code().is_some_and(|code| SIGN_REJECTED_CODES.contains(&code)) => {
+            diagnostics.signed = false;
+            diagnostics.retried += 1;
+            attempt(false).await.map(|data| (data, diagnostics))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 校验分区ID后设置分区，再读取当前分区作为确认
+async fn set_area_and_confirm<S, SFut, C, CFut>(area_id: u32, set: S, current: C) -> Result<(u32, String)>
+where
+    S: FnOnce(u32) -> SFut,
+    SFut: Future<Output = Result<()>>,
+    C: FnOnce() -> CFut,
+    CFut: Future<Output = Result<(u32, String)>>,
+{
+    if area_id == 0 {
+        return Err(crate::error::BiliError::validation("分区ID不能为0"));
+    }
+    set(area_id).await?;
+    current().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::DEFAULT_TIMEOUT_MS;
+    use crate::test_support::area_json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn stream_data_with_srt() -> LiveStreamData {
+        serde_json::from_str(r#"{
+            "change": 1, "live_key": "key", "need_face_auth": false,
+            "notice": {"button_text": "", "button_url": "", "msg": "", "status": 0, "title": "", "type": 0},
+            "protocols": [
+                {"protocol": "rtmp", "addr": "rtmp://live-push.bilivideo.com/live-bvc/", "code": "?streamname=live_1&key=rtmp", "new_link": "", "provider": "txy"},
+                {"protocol": "SRT", "addr": "srt://live-push.bilivideo.com:1937", "code": "?streamid=#!::h=live-push&r=live_1&key=srt", "new_link": "", "provider": "txy"}
+            ],
+            "qr": "", "room_type": 0,
+            "rtmp": {"addr": "rtmp://live-push.bilivideo.com/live-bvc/", "code": "?streamname=live_1&key=rtmp", "new_link": "", "provider": "txy", "type": 1},
+            "rtmp_backup": null, "service_source": "", "status": "LIVE", "sub_session_key": "",
+            "try_time": "0000-00-00 00:00:00", "up_stream_extra": {"isp": ""}
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn test_stream_info_by_protocol_falls_back_to_rtmp() {
+        let data = stream_data_with_srt();
+        let live = Live::new(1, "csrf".to_string(), "SESSDATA=abc").unwrap();
+
+        let (addr, code) = live.parse_stream_info_by_protocol(&data, "srt").unwrap();
+        assert_eq!(addr, "srt://live-push.bilivideo.com:1937");
+        assert!(code.reveal().ends_with("key=srt"));
+        assert!(live.parse_stream_info_by_protocol(&data, "hls").is_none());
+
+        let (addr, _) = live.with_stream_protocol("srt").preferred_stream_info(&data);
+        assert_eq!(addr, "srt://live-push.bilivideo.com:1937");
+
+        let live = Live::new(1, "csrf".to_string(), "SESSDATA=abc").unwrap().with_stream_protocol("hls");
+        let (addr, code) = live.preferred_stream_info(&data);
+        assert_eq!(addr, "rtmp://live-push.bilivideo.com/live-bvc/");
+        assert!(code.reveal().ends_with("key=rtmp"));
+    }
+
+    #[tokio::test]
+    async fn test_export_obs_profile_creates_parent_dir() {
+        let dir = std::env::temp_dir().join(format!("bilibili_live_tool_obs_{}", std::process::id()));
+        let path = dir.join("nested").join("service.json");
+        let live = Live::new(1, "csrf".to_string(), "SESSDATA=abc").unwrap().with_stream_protocol("srt");
+        live.export_obs_profile(&stream_data_with_srt(), &path).await.unwrap();
+
+        let profile: ObsServiceProfile = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(profile.service_type, "rtmp_custom");
+        assert_eq!(profile.settings.server, "srt://live-push.bilivideo.com:1937");
+        assert!(profile.settings.key.ends_with("key=srt"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_cover_format_and_size() {
+        assert_eq!(check_cover(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]).unwrap(), "image/jpeg");
+        assert_eq!(check_cover(b"\x89PNG\r\n\x1a\n\x00\x00").unwrap(), "image/png");
+        assert!(matches!(check_cover(b"GIF89a"), Err(crate::error::BiliError::Validation(_))));
+
+        let mut large = vec![0xFF, 0xD8, 0xFF];
+        large.resize(MAX_COVER_SIZE + 1, 0);
+        assert!(matches!(check_cover(&large), Err(crate::error::BiliError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_upload_cover_errors() {
+        let live = Live::new(1, "csrf".to_string(), "SESSDATA=abc").unwrap();
+        let result = live.upload_cover("/nonexistent/cover.png").await;
+        assert!(matches!(result, Err(crate::error::BiliError::Io(_))));
+
+        let response: ApiResponse<CoverUploadData> = serde_json::from_str(r#"{"code":0,"message":"0","data":{"location":""}}"#).unwrap();
+        assert!(matches!(cover_location(response), Err(crate::error::BiliError::Live(_))));
+
+        let response: ApiResponse<CoverUploadData> = serde_json::from_str(r#"{"code":0,"message":"0","data":{"location":"https://i0.hdslb.com/bfs/live/cover.jpg"}}"#).unwrap();
+        assert_eq!(cover_location(response).unwrap(), "https://i0.hdslb.com/bfs/live/cover.jpg");
+    }
+
+    #[tokio::test]
+    async fn test_target_room_and_mismatch_warning() {
+        assert_eq!(room_mismatch_warning(1000, 1000), None);
+        assert_eq!(room_mismatch_warning(0, 1000), None);
+        assert!(room_mismatch_warning(1000, 2000).unwrap().contains("2000"));
+
+        // 未手动指定时不发请求
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+        assert_eq!(resolve_target_room(&client, &Endpoints::default(), None, 1000).await, (1000, None));
+
+        // 短号通过配置的接口地址转换
+        let (addr, request) = crate::test_support::serve_once(r#"{"code":0,"message":"0","data":{"room_id":5440}}"#).await;
+        let endpoints = Endpoints { live_api: addr, ..Endpoints::default() };
+        assert_eq!(resolve_target_room(&client, &endpoints, Some(1), 5440).await, (5440, None));
+        assert!(request.await.unwrap().head.contains("room_id=1 "));
+    }
+
+    #[test]
+    fn test_live_permission_from_parts() {
+        let verified = serde_json::json!({"status": 1});
+        let permission = LivePermission::from_parts(1, &verified, &RoomInfo::default());
+        assert!(permission.can_start());
+        assert!(permission.problems().is_empty());
+
+        let locked = RoomInfo { lock_status: 1, ..RoomInfo::default() };
+        let permission = LivePermission::from_parts(1, &serde_json::json!({"status": 0}), &locked);
+        assert!(!permission.can_start());
+        assert_eq!(permission.problems().len(), 2);
+
+        let permission = LivePermission::from_parts(0, &verified, &RoomInfo::default());
+        assert!(!permission.has_room);
+        assert!(permission.problems()[0].contains("开通直播间"));
+    }
+
+    #[tokio::test]
+    async fn test_poll_face_auth_until_completed() {
+        let calls = AtomicUsize::new(0);
+        let fetch = || async {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            Ok(if n < 2 { FaceAuthStatus::Required } else { FaceAuthStatus::Completed })
+        };
+        poll_face_auth(fetch, Duration::from_millis(1), Duration::from_secs(1)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let err = poll_face_auth(|| async { Ok(FaceAuthStatus::Required) }, Duration::from_millis(5), Duration::from_millis(12))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, crate::error::BiliError::Timeout(_)));
+    }
+
+    #[test]
+    fn test_face_auth_status_from_response() {
+        let cases = [
+            (r#"{"code":0,"message":"0","data":{"is_identified":true}}"#, FaceAuthStatus::Completed),
+            (r#"{"code":0,"message":"0","data":{"is_identified":false}}"#, FaceAuthStatus::Required),
+            (r#"{"code":0,"message":"0","data":null}"#, FaceAuthStatus::NotRequired),
+            (r#"{"code":60024,"message":"请先完成人脸认证","data":null}"#, FaceAuthStatus::Required),
+        ];
+        for (json, expected) in cases {
+            let response: ApiResponse<FaceAuthData> = serde_json::from_str(json).unwrap();
+            assert_eq!(FaceAuthStatus::from_response(&response).unwrap(), expected);
+        }
+
+        let response: ApiResponse<FaceAuthData> = serde_json::from_str(r#"{"code":-101,"message":"账号未登录","data":null}"#).unwrap();
+        assert!(FaceAuthStatus::from_response(&response).is_err());
+    }
+
+    #[test]
+    fn test_up_stream_addr_payload() {
+        let json = r#"{
+            "code": 0,
+            "message": "0",
+            "data": {
+                "addr": {
+                    "addr": "rtmp://live-push.bilivideo.com/live-bvc/",
+                    "code": "?streamname=live_123456_7890&key=abcdef&schedule=rtmp&pflag=1"
+                },
+                "line": [],
+                "srt_addr": {}
+            }
+        }"#;
+        let response: ApiResponse<UpStreamAddrData> = serde_json::from_str(json).unwrap();
+        let addr = response.data.unwrap().addr;
+        
+        assert_eq!(addr.addr, "rtmp://live-push.bilivideo.com/live-bvc/");
+        assert!(addr.code.reveal().starts_with("?streamname=live_123456"));
+    }
+
+    #[test]
+    fn test_area_data_accepts_both_field_spellings() {
+        let common = r#""act_id": 0, "hot_status": 0, "lock_status": 0, "pic": "", "area_type": 0"#;
+        let short = format!(r#"{{"id": "235", "name": "其他单机", "parent_id": 6, "parent_name": "单机游戏", {}}}"#, common);
+        let long = format!(r#"{{"area_id": 235, "area_name": "其他单机", "parent_area_id": "6", "parent_area_name": "单机游戏", {}}}"#, common);
+
+        for json in [short, long] {
+            let area: AreaData = serde_json::from_str(&json).unwrap();
+            assert_eq!(area.id, 235);
+            assert_eq!(area.name, "其他单机");
+            assert_eq!(area.parent_id, 6);
+            assert_eq!(area.parent_name, "单机游戏");
+        }
+    }
+
+    #[test]
+    fn test_live_start_data_accepts_both_field_spellings() {
+        let rest = r#""platform": "pc", "backup_stream": "0", "csrf_token": "t", "csrf": "t""#;
+        for json in [
+            format!(r#"{{"room_id": 123456, "area_v2": 235, {}}}"#, rest),
+            format!(r#"{{"roomid": 123456, "area_id": 235, {}}}"#, rest),
+        ] {
+            let data: LiveStartData = serde_json::from_str(&json).unwrap();
+            assert_eq!(data.room_id, 123456);
+            assert_eq!(data.area_v2, 235);
+        }
+    }
+
+    #[test]
+    fn test_title_truncated_on_grapheme_boundary() {
+        // 前38个字符后接一个由多个码点组成的表情，截断位置恰好落在它之后
+        let title = format!("{}{}{}", "标".repeat(38), "👨‍👩‍👧", "尾部超出的内容");
+        
+        let update = prepare_title(&title, true).unwrap();
+        assert!(update.truncated);
+        assert_eq!(update.title.graphemes(true).count(), MAX_TITLE_LEN);
+        assert!(update.title.ends_with("👨‍👩‍👧…"));
+        assert!(update.truncation_notice().unwrap().contains(&update.title));
+        
+        assert!(matches!(prepare_title(&title, false), Err(crate::error::BiliError::Validation(_))));
+        let short = prepare_title("短标题", false).unwrap();
+        assert_eq!(short.title, "短标题");
+        assert!(short.truncation_notice().is_none());
+    }
+
+    #[test]
+    fn test_signing_disabled_passes_params_through() {
+        let params = HashMap::from([
+            ("room_id".to_string(), "123456".to_string()),
+            ("csrf".to_string(), "csrf".to_string()),
+        ]);
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+        
+        let signed = Live::with_client(client.clone(), 123456, "csrf".to_string()).sign_params(params.clone());
+        assert!(signed.contains_key("sign"));
+        
+        let live = Live::with_client(client, 123456, "csrf".to_string()).with_signing_disabled(true);
+        let passed = live.sign_params(params.clone());
+        assert!(!passed.contains_key("sign"));
+        assert!(!passed.contains_key("appkey"));
+        assert_eq!(passed, params);
+    }
+
+    #[test]
+    fn test_signed_form_requires_csrf_and_sign() {
+        let params = HashMap::from([
+            ("room_id".to_string(), "123456".to_string()),
+            ("csrf".to_string(), "csrf".to_string()),
+        ]);
+        assert!(check_signed_form(&params, false).is_ok());
+        assert!(check_signed_form(&Signer::sign_live_request(params.clone()), true).is_ok());
+        assert!(check_signed_form(&params, true).unwrap_err().to_string().contains("sign"));
+        
+        let without_csrf = HashMap::from([("room_id".to_string(), "123456".to_string())]);
+        assert!(check_signed_form(&without_csrf, false).unwrap_err().to_string().contains("csrf"));
+    }
+
+    #[test]
+    fn test_csrf_follows_refreshed_jar() {
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+        client.add_cookies(&HashMap::from([("bili_jct".to_string(), "old".to_string())]));
+        let live = Live::with_client(client.clone(), 123456, "old".to_string());
+        
+        // cookie刷新后jar中的bili_jct已更新，下一次签名请求使用新值
+        client.add_cookies(&HashMap::from([("bili_jct".to_string(), "new".to_string())]));
+        let mut params = HashMap::from([("room_id".to_string(), "123456".to_string())]);
+        live.insert_csrf(&mut params);
+        let signed = live.sign_params(params);
+        assert_eq!(signed.get("csrf").map(String::as_str), Some("new"));
+        assert_eq!(signed.get("csrf_token").map(String::as_str), Some("new"));
+        
+        // 显式传入csrf的构造方式保持不变
+        let standalone = Live::new(123456, "fixed".to_string(), "bili_jct=other").unwrap();
+        assert_eq!(standalone.get_csrf(), "fixed");
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_retry_is_reported() {
+        let attempts = Mutex::new(Vec::new());
+
+        let (data, diagnostics) = start_with_fallback(START_LIVE_URL, true, |signed| {
+            attempts.lock().unwrap().push(signed);
+            async move {
+                if signed {
+                    Err(crate::error::BiliError::api_error(-3, "API校验密匙错误"))
+                } else {
+                    Ok("stream")
+                }
+            }
+        }).await.unwrap();
+
+        assert_eq!(data, "stream");
+        assert_eq!(*attempts.lock().unwrap(), vec![true, false]);
+        assert!(!diagnostics.signed);
+        assert_eq!(diagnostics.retried, 1);
+        assert_eq!(diagnostics.endpoint, START_LIVE_URL);
+    }
+
+    #[tokio::test]
+    async fn test_raw_area_id_skips_area_list() {
+        let sent = Mutex::new(Vec::new());
+
+        let (id, name) = set_area_and_confirm(
+            372,
+            |id| {
+                sent.lock().unwrap().push(id);
+                async { Ok(()) }
+            },
+            || async { Ok((372, "虚拟日常".to_string())) },
+        ).await.unwrap();
+
+        assert_eq!(*sent.lock().unwrap(), vec![372]);
+        assert_eq!((id, name.as_str()), (372, "虚拟日常"));
+
+        let result = set_area_and_confirm(
+            0,
+            |_| async { panic!("分区ID为0时不应发送请求") },
+            || async { Ok((0, String::new())) },
+        ).await;
+        assert!(matches!(result, Err(crate::error::BiliError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_recover_key_after_interrupted_start() {
+        // 开播请求已发出，但进程在保存推流码前退出
+        let config = Config {
+            pending_start_at: Some(chrono::Utc::now()),
+            ..Config::default()
+        };
+        let calls = AtomicUsize::new(0);
+        let fetch = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(("rtmp://live-push.bilivideo.com/live-bvc/".to_string(), StreamKey::new("?streamname=live_1&key=abc")))
+        };
+
+        let (server, key) = Live::recover_pending_start(&config, true, fetch).await.unwrap().unwrap();
+        assert_eq!(server, "rtmp://live-push.bilivideo.com/live-bvc/");
+        assert_eq!(key.reveal(), "?streamname=live_1&key=abc");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 房间未在直播说明开播没有生效，不需要恢复
+        assert!(Live::recover_pending_start(&config, false, fetch).await.is_none());
+        // 没有待确认标记时不恢复
+        assert!(Live::recover_pending_start(&Config::default(), true, fetch).await.is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_parse_lifecycle_and_retry_hint() {
+        assert_eq!(StreamLifecycle::from("LIVE".to_string()), StreamLifecycle::Live);
+        assert_eq!(StreamLifecycle::from("PREPARING".to_string()), StreamLifecycle::Preparing);
+        assert_eq!(StreamLifecycle::from("ROUND".to_string()), StreamLifecycle::Round);
+        assert_eq!(StreamLifecycle::from("BANNED".to_string()), StreamLifecycle::Other("BANNED".to_string()));
+
+        let now = chrono::DateTime::parse_from_rfc3339("2024-01-01T04:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        // 北京时间12:00:03 即 UTC 04:00:03
+        assert_eq!(RetryHint::new("2024-01-01 12:00:03").delay_from(now), Some(Duration::from_secs(3)));
+        assert_eq!(RetryHint::new("5").delay_from(now), Some(Duration::from_secs(5)));
+        assert_eq!(RetryHint::new("0000-00-00 00:00:00").delay_from(now), None);
+        assert_eq!(RetryHint::new("2023-12-31 12:00:00").delay_from(now), None);
+
+        let status: StreamLifecycle = serde_json::from_str(r#""PREPARING""#).unwrap();
+        assert!(!status.is_live());
+        assert_eq!(serde_json::to_string(&status).unwrap(), r#""PREPARING""#);
+    }
+
+    #[test]
+    fn test_stream_key_debug_is_redacted() {
+        let key = StreamKey::new("?streamname=live_123456_7890123&key=0123456789abcdef");
+        let debug = format!("{:?}", key);
+        assert!(!debug.contains("0123456789abcdef"));
+        assert_eq!(debug, "live_***");
+        assert!(key.reveal().contains("0123456789abcdef"));
+    }
+
+    #[test]
+    fn test_stop_result_outcome() {
+        let pending: ApiResponse<LiveStopResult> = serde_json::from_str(
+            r#"{"code":0,"msg":"","message":"","data":{"change":0,"status":"LIVE"}}"#
+        ).unwrap();
+        assert_eq!(pending.data.unwrap().outcome(), StopOutcome::Pending);
+        
+        let stopped: ApiResponse<LiveStopResult> = serde_json::from_str(
+            r#"{"code":0,"msg":"","message":"","data":{"change":1,"status":"PREPARING"}}"#
+        ).unwrap();
+        assert_eq!(stopped.data.unwrap().outcome(), StopOutcome::Stopped);
+    }
+
+    #[test]
+    fn test_shared_client_cookies() {
+        use reqwest::cookie::CookieStore;
+        
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap();
+        let live = Live::with_client(client.clone(), 123456, "csrf".to_string());
+        
+        client.add_cookies(&HashMap::from([("buvid3".to_string(), "abc-123".to_string())]));
+        
+        let url = "https://api.live.bilibili.com".parse().unwrap();
+        let header = live.get_client().get_jar().cookies(&url).unwrap();
+        assert!(header.to_str().unwrap().contains("buvid3=abc-123"));
+    }
+
+    #[tokio::test]
+    async fn test_stats_poller_skips_failed_fetches() {
+        let (addr, _) = crate::test_support::serve_responses(vec![
+            (200, r#"{"code":0,"message":"0","data":{"room_info":{"online":120},"watched_show":{"num":300},"anchor_info":{"relation_info":{"attention":5000}}}}"#),
+            (200, r#"{"code":-400,"message":"请求错误","data":null}"#),
+            (200, r#"{"code":0,"message":"0","data":{"room_info":{"online":150}}}"#),
+        ]).await;
+        let client = BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap().with_retry_config(0, 1);
+        let mut rx = spawn_stats_poller_with(client, addr, Duration::from_millis(10));
+        
+        let first = rx.recv().await.unwrap();
+        assert_eq!(first, LiveStats { online: Some(120), watched: Some(300), followers: Some(5000) });
+        
+        // 第二次拉取失败时不发送，下一次成功的数据缺少的字段沿用旧值
+        let second = rx.recv().await.unwrap();
+        assert_eq!(second, LiveStats { online: Some(150), watched: None, followers: None });
+        assert_eq!(first.merge(second), LiveStats { online: Some(150), watched: Some(300), followers: Some(5000) });
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_alerts_after_consecutive_failures() {
+        let beats = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = beats.clone();
+        let cancel = CancellationToken::new();
+        // 前两次失败后成功一次，之后一直失败
+        let mut alerts = spawn_heartbeat_with(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if n == 2 { Ok(()) } else { Err(BiliError::timeout("心跳超时")) }
+            }
+        }, Duration::from_millis(5), cancel.clone());
+        
+        // 每次失败都提示，中间成功过一次后重新计数
+        let mut failures = Vec::new();
+        let alert = loop {
+            let alert = alerts.recv().await.unwrap();
+            failures.push(alert.failures);
+            if alert.reached_limit() {
+                break alert;
+            }
+            assert!(alert.to_string().starts_with("直播心跳失败"));
+        };
+        assert_eq!(failures, vec![1, 2, 1, 2, 3]);
+        assert!(beats.load(Ordering::SeqCst) >= 6);
+        assert!(alert.to_string().contains("直播可能被判定异常"));
+        assert!(alert.to_string().contains("心跳超时"));
+        
+        cancel.cancel();
+        assert!(alerts.recv().await.is_none());
+        let stopped_at = beats.load(Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(beats.load(Ordering::SeqCst), stopped_at);
+    }
+
+    #[test]
+    fn test_area_search_pinyin_initials_and_case() {
+        assert!(pinyin_matches("yuanshen", "ys"));
+        assert!(pinyin_matches("yuanshen", "yuan"));
+        assert!(pinyin_matches("yuanshen", "shen"));
+        // 首字母必须相同，顺序不能颠倒
+        assert!(!pinyin_matches("yuanshen", "sy"));
+        assert!(!pinyin_matches("yuanshen", "yz"));
+        assert!(!pinyin_matches("", "ys"));
+
+        let areas: Vec<AreaCategory> = serde_json::from_value(serde_json::json!([
+            {"id": 3, "name": "手游", "list": [
+                area_json(321, "原神", 3, "手游", "YuanShen"),
+                area_json(240, "APEX英雄", 3, "手游", "apexyingxiong"),
+            ]},
+        ])).unwrap();
+        let ids = |keyword: &str| search_areas(&areas, keyword).iter().map(|area| area.id).collect::<Vec<_>>();
+        assert_eq!(ids("YS"), vec![321]);
+        assert_eq!(ids("apex"), vec![240]);
+        assert_eq!(ids("Apex 手游"), vec![240]);
+        assert!(ids("ys apex").is_empty());
+    }
+
+    #[test]
+    fn test_area_lookup_by_id_and_name() {
+        let areas: Vec<AreaCategory> = serde_json::from_value(serde_json::json!([
+            {"id": 2, "name": "网游", "list": [
+                area_json(86, "英雄联盟", 2, "网游", "yingxionglianmeng"),
+                area_json(329, "无畏契约", 2, "网游", "wuweiqiyue"),
+            ]},
+            {"id": 6, "name": "单机游戏", "list": [area_json(236, "主机游戏", 6, "单机游戏", "zhujiyouxi")]},
+            {"id": 3, "name": "手游", "list": [area_json(321, "原神", 3, "手游", "yuanshen")]},
+        ])).unwrap();
+        
+        assert_eq!(find_area(&areas, 236).map(AreaData::full_name).as_deref(), Some("单机游戏 - 主机游戏"));
+        assert!(find_area(&areas, 1).is_none());
+
+        
+        let ids = |found: Vec<AreaData>| found.iter().map(|area| area.id).collect::<Vec<_>>();
+        assert_eq!(ids(search_areas(&areas, "英雄")), vec![86]);
+        // 匹配父分区名
+        assert_eq!(ids(search_areas(&areas, "网游")), vec![86, 329]);
+        assert_eq!(ids(search_areas(&areas, "")), vec![86, 329, 236, 321]);
+        assert!(search_areas(&areas, "不存在").is_empty());
+        // 拼音全拼和首字母，不区分大小写
+        assert_eq!(ids(search_areas(&areas, "YuanShen")), vec![321]);
+        assert_eq!(ids(search_areas(&areas, "ys")), vec![321]);
+        assert_eq!(ids(search_areas(&areas, "yxlm")), vec![86]);
+        // 多个关键词同时匹配
+        assert_eq!(ids(search_areas(&areas, "网游 wwqy")), vec![329]);
+        assert_eq!(ids(search_areas(&areas, "  网游   英雄 ")), vec![86]);
+        assert!(search_areas(&areas, "网游 原神").is_empty());
+        
+        let cache = AreaListCache::default();
+        assert!(cache.get().is_none());
+        cache.put(areas);
+        assert_eq!(cache.get().map(|areas| areas.len()), Some(3));
+        cache.invalidate();
+        assert!(cache.get().is_none());
+    }
+
+    #[test]
+    fn test_area_rejection_reason() {
+        let err = explain_area_error(BiliError::api_error(60024, "请先完成人脸认证"));
+        assert!(matches!(&err, BiliError::Permission(message) if message.contains("人脸认证")));
+        let err = explain_area_error(BiliError::api_error(1, "该分区已锁定").context("设置分区"));
+        assert!(err.to_string().contains("该分区已被锁定"));
+        assert!(matches!(explain_area_error(BiliError::api_error(1, "参数错误")), BiliError::Api(1, _)));
+    }
+
+    #[tokio::test]
+    async fn test_area_path_for_id_uses_cached_list() {
+        let areas: Vec<AreaCategory> = serde_json::from_value(serde_json::json!([
+            {"id": 2, "name": "网游", "list": [{
+                "id": "329", "name": "无畏契约", "parent_id": "2", "parent_name": "网游",
+                "act_id": "0", "hot_status": 0, "lock_status": "0", "pic": "", "area_type": 0,
+            }]},
+        ])).unwrap();
+        let live = Live::new(1, "csrf".to_string(), "SESSDATA=abc").unwrap();
+        live.area_list_cache.put(areas);
+        
+        let path = live.area_path_for_id(329).await.unwrap().unwrap();
+        assert_eq!((path.parent_id, path.parent_name.as_str(), path.name.as_str()), (2, "网游", "无畏契约"));
+        assert_eq!(path.to_string(), "网游 > 无畏契约");
+        assert!(live.area_path_for_id(1).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_room_info_tolerates_missing_fields() {
+        let info: RoomInfo = serde_json::from_str(r#"{
+            "room_id": 123, "title": "测试直播", "live_status": 1,
+            "area_id": "86", "area_name": "英雄联盟", "parent_area_name": "网游", "online": 5
+        }"#).unwrap();
+        assert!(info.is_live());
+        assert_eq!((info.area_id, info.area_name.as_str()), (86, "英雄联盟"));
+        assert_eq!(info.attention, 0);
+
+        let response: ApiResponse<RoomInfo> = serde_json::from_str(r#"{"code":0,"message":"0","data":{"title":"只有标题"}}"#).unwrap();
+        let info = response.data.unwrap();
+        assert_eq!(info.title, "只有标题");
+        assert_eq!(info.live_status, 0);
+    }
+
+    #[tokio::test]
+    async fn test_room_info_cache_invalidate() {
+        let (addr, _) = crate::test_support::serve_responses(vec![
+            (200, r#"{"code":0,"message":"0","data":{"title":"旧标题","area_name":"单机游戏"}}"#),
+            (200, r#"{"code":0,"message":"0","data":[]}"#),
+            (200, r#"{"code":0,"message":"0","data":{"title":"新标题","area_name":"单机游戏"}}"#),
+            (200, r#"{"code":0,"message":"0","data":[]}"#),
+            (200, r#"{"code":0,"message":"0","data":{"title":"新标题","area_name":"网游"}}"#),
+        ]).await;
+        let endpoints = Endpoints { live_api: addr, ..Endpoints::default() };
+        let live = Live::with_client(BilibiliClient::new(DEFAULT_TIMEOUT_MS).unwrap(), 1, "csrf".to_string())
+            .with_endpoints(&endpoints);
+        
+        assert_eq!(live.get_room_info().await.unwrap().title, "旧标题");
+        // 命中缓存，不会再次请求（再请求会拿到下一个不是直播间信息的响应）
+        assert_eq!(live.get_room_info().await.unwrap().title, "旧标题");
+        
+        // set_title 和 set_area 成功后会使缓存失效，下一次读取重新请求
+        live.set_title("新标题").await.unwrap();
+        assert_eq!(live.get_room_info().await.unwrap().title, "新标题");
+        live.set_area(3).await.unwrap();
+        assert_eq!(live.get_room_info().await.unwrap().area_name, "网游");
+    }
+}
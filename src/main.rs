@@ -1,11 +1,17 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgMatches, Command};
+use clap_complete::Shell;
 use bilibili_live_tool::*;
 use bilibili_live_tool::tui::TuiApp;
+use std::collections::HashMap;
 use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use tokio_util::sync::CancellationToken;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let matches = Command::new("bilibili_live_tool")
+fn build_cli() -> Command {
+    Command::new("bilibili_live_tool")
         .about("哔哩哔哩直播推流码获取工具")
         .version("0.1.0")
         .author("FWW")
@@ -16,6 +22,79 @@ async fn main() -> Result<()> {
                 .help("使用传统命令行模式")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("stop")
+                .long("stop")
+                .help("使用已保存的认证信息直接下播，不进入交互")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("area-id")
+                .long("area-id")
+                .help("直接使用指定的分区ID，跳过分区列表选择")
+                .value_name("ID")
+                .value_parser(clap::value_parser!(u32)),
+        )
+        .arg(
+            Arg::new("title")
+                .long("title")
+                .help("直接使用指定的直播标题，跳过标题输入；与--area-id同时指定时开播后直接退出")
+                .value_name("TITLE"),
+        )
+        .arg(
+            Arg::new("last")
+                .long("last")
+                .help("命令行模式下使用上次直播的标题和分区开播，跳过标题和分区输入")
+                .requires("cli")
+                .conflicts_with_all(["title", "area-id"])
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("一次性开播时在最后一行以JSON输出推流信息，默认输出KEY=VALUE")
+                .requires_all(["title", "area-id"])
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("obs-profile")
+                .long("obs-profile")
+                .help("开播后把推流信息导出为OBS可导入的service.json")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("no-sign")
+                .long("no-sign")
+                .help("调试用：关闭App签名和WBI签名")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("room")
+                .long("room")
+                .help("本次操作指定的直播间号（可为短号），不写入配置；覆盖配置中的managed_room_id")
+                .value_name("ROOM_ID")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("protocol")
+                .long("protocol")
+                .help("偏好的推流协议，直播间未提供时回落到rtmp（默认读取配置）")
+                .value_name("PROTOCOL")
+                .value_parser(["rtmp", "srt"]),
+        )
+        .arg(
+            Arg::new("cookie-file")
+                .long("cookie-file")
+                .help("从浏览器导出的cookie文件（cookies.txt或k=v; k=v）导入登录信息，不再扫码")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("profile")
+                .short('p')
+                .long("profile")
+                .help("使用指定的账号档位，不存在时新建并扫码登录")
+                .value_name("NAME"),
+        )
         .arg(
             Arg::new("config")
                 .short('f')
@@ -23,72 +102,259 @@ async fn main() -> Result<()> {
                 .help("配置文件路径")
                 .value_name("FILE"),
         )
-        .get_matches();
+        .subcommand(
+            Command::new("doctor")
+                .about("运行诊断检查")
+                .arg(
+                    Arg::new("report")
+                        .long("report")
+                        .help("将脱敏后的诊断报告写入文件")
+                        .value_name("FILE"),
+                ),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("生成shell补全脚本")
+                .hide(true)
+                .arg(
+                    Arg::new("shell")
+                        .required(true)
+                        .value_parser(clap::value_parser!(Shell)),
+                ),
+        )
+}
+
+/// 将补全脚本写入out
+fn write_completions(shell: Shell, out: &mut dyn Write) {
+    let mut cmd = build_cli();
+    let name = cmd.get_name().to_string();
+    clap_complete::generate(shell, &mut cmd, name, out);
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let matches = build_cli().get_matches();
+    let options = RunOptions::from_matches(&matches);
+    
+    if let Some(profile) = matches.get_one::<String>("profile")
+        && let Err(e) = switch_profile(&options, profile)
+    {
+        eprintln!("切换账号档位失败: {}", e);
+        return Err(e);
+    }
+    
+    if let Some(path) = matches.get_one::<String>("cookie-file")
+        && let Err(e) = import_cookie_file(&options, path).await
+    {
+        eprintln!("导入cookie文件失败: {}", e);
+        return Err(e);
+    }
+    
+    if let Some(doctor_matches) = matches.subcommand_matches("doctor") {
+        return run_doctor(&options, doctor_matches.get_one::<String>("report")).await;
+    }
+    
+    if let Some(completion_matches) = matches.subcommand_matches("completions") {
+        if let Some(shell) = completion_matches.get_one::<Shell>("shell") {
+            write_completions(*shell, &mut io::stdout());
+        }
+        return Ok(());
+    }
+    
+    if matches.get_flag("stop") {
+        return run_stop(&options).await;
+    }
     
     // 如果指定了CLI参数，使用传统命令行模式
     if matches.get_flag("cli") {
-        return run_cli().await;
+        return run_cli(&options).await;
     }
     
     // 默认使用TUI模式
-    run_tui().await
+    run_tui(&options).await
+}
+
+/// 只影响本次运行的命令行选项
+#[derive(Debug, Default)]
+struct RunOptions {
+    area_id: Option<u32>,
+    no_sign: bool,
+    protocol: Option<String>,
+    /// 覆盖认证信息中的直播间号
+    room: Option<u64>,
+    title: Option<String>,
+    /// 使用上次直播的标题和分区开播
+    use_last: bool,
+    /// 一次性开播时以JSON输出推流信息
+    json: bool,
+    /// 开播后导出OBS服务配置的路径
+    obs_profile: Option<String>,
+    /// 配置文件路径，未指定时使用程序所在目录下的config.toml
+    config_file: Option<PathBuf>,
+}
+
+impl RunOptions {
+    fn from_matches(matches: &ArgMatches) -> Self {
+        Self {
+            area_id: matches.get_one::<u32>("area-id").copied(),
+            no_sign: matches.get_flag("no-sign"),
+            protocol: matches.get_one::<String>("protocol").cloned(),
+            room: matches.get_one::<u64>("room").copied(),
+            title: matches.get_one::<String>("title").cloned(),
+            use_last: matches.get_flag("last"),
+            json: matches.get_flag("json"),
+            obs_profile: matches.get_one::<String>("obs-profile").cloned(),
+            config_file: matches.get_one::<String>("config").map(PathBuf::from),
+        }
+    }
+    
+    /// 加载本次运行使用的配置文件
+    fn load_config(&self) -> Result<Config> {
+        match &self.config_file {
+            Some(path) => Config::load_from(path),
+            None => Config::load(),
+        }
+    }
+    
+    /// 加载失败时使用的默认配置，保存时仍写回本次运行的配置文件
+    fn default_config(&self) -> Config {
+        match &self.config_file {
+            Some(path) => Config::default().with_path(path),
+            None => Config::default(),
+        }
+    }
+    
+    /// 与配置文件同路径的凭证存储
+    fn credential_store(&self) -> TomlStore {
+        match &self.config_file {
+            Some(path) => TomlStore::with_path(path),
+            None => TomlStore::new(),
+        }
+    }
+    
+    /// 同时指定了标题和分区时不做任何交互，开播后输出推流信息即退出
+    fn is_one_shot(&self) -> bool {
+        self.title.is_some() && self.area_id.is_some()
+    }
+    
+    /// 手动指定的直播间号，--room优先于配置中的managed_room_id
+    fn explicit_room(&self, config: &Config) -> Option<u64> {
+        self.room.or(config.managed_room_id)
+    }
 }
 
-async fn run_tui() -> Result<()> {
+/// 本次操作的真实直播间号，手动指定的短号会被转换，不属于登录账号时给出警告
+async fn target_room_id(client: &BilibiliClient, user_info: &auth::UserInfo, config: &Config, options: &RunOptions) -> u64 {
+    let (room_id, warning) = live::resolve_target_room(client, &config.endpoints, options.explicit_room(config), user_info.room_id).await;
+    if let Some(warning) = warning {
+        eprintln!("警告: {}", warning);
+    }
+    room_id
+}
+
+/// 按配置和命令行选项创建Live
+fn create_live(client: BilibiliClient, room_id: u64, user_info: &auth::UserInfo, config: &Config, options: &RunOptions) -> Live {
+    Live::with_client(client, room_id, user_info.csrf.clone())
+        .with_auto_truncate_title(config.auto_truncate_title)
+        .with_signing_disabled(config.disable_signing || options.no_sign)
+        .with_stream_protocol(options.protocol.clone().unwrap_or_else(|| config.stream_protocol.clone()))
+        .with_endpoints(&config.endpoints)
+}
+
+/// 从cookie文件构造登录信息并保存，之后的流程直接使用保存的认证信息
+async fn import_cookie_file(options: &RunOptions, path: &str) -> Result<()> {
+    let mut config = options.load_config()?;
+    let auth = Auth::with_client(create_client(&config, &HashMap::new())?);
+    let user_info = auth.from_cookie_file(path).await?;
+    save_credentials(&options.credential_store(), &mut config, &user_info);
+    println!("已从cookie文件导入登录信息，直播间号: {}", user_info.room_id);
+    Ok(())
+}
+
+/// 切换配置文件中的当前账号档位，之后的加载和凭证存储都使用该档位
+fn switch_profile(options: &RunOptions, name: &str) -> Result<()> {
+    let mut config = options.load_config()?;
+    if config.profile != name {
+        config.use_profile(name);
+        config.save()?;
+    }
+    println!("使用账号档位: {}", name);
+    Ok(())
+}
+
+async fn run_tui(options: &RunOptions) -> Result<()> {
     println!("正在启动...");
     
     // 加载配置
-    let mut config = match Config::load() {
+    let mut config = match options.load_config() {
         Ok(cfg) => cfg,
         Err(e) => {
             eprintln!("加载配置失败: {}", e);
             eprintln!("使用默认配置");
-            Config::default()
+            options.default_config()
         }
     };
+    let store = options.credential_store();
+    resolve_env_room(&mut config).await;
 
-
+    // 分区列表无需登录，在验证/扫码期间后台预取
+    let prefetch_config = config.clone();
+    let prefetch_wbi = !(config.disable_signing || options.no_sign);
+    let area_prefetch = tokio::spawn(async move {
+        let client = create_client(&prefetch_config, &HashMap::new())?;
+        Live::fetch_area_list(&client, prefetch_wbi).await
+    });
 
     // 获取认证信息
-    let user_info = if config.has_credentials() {
+    let user_info = if let Ok(creds) = load_credentials(&store, &config) {
         println!("检测到已保存的认证信息，正在验证...");
         
         // 尝试使用已保存的认证信息
-        let auth_result = Auth::new();
-        let cookies_result = Auth::parse_cookie_string(&config.cookie_str.as_ref().unwrap());
-        let room_id_result = config.room_id.as_ref().unwrap().parse::<u64>();
+        let auth_result = create_client(&config, &HashMap::new()).map(Auth::with_client);
+        let cookies_result = Auth::parse_cookie_string(&creds.cookie_str);
+        let room_id_result = creds.room_id.parse::<u64>();
         
         match (auth_result, cookies_result, room_id_result) {
             (Ok(auth), Ok(cookies), Ok(room_id)) => {
-                match auth.validate_cookies(&cookies).await {
-                    Ok(true) => {
-                        println!("认证信息有效，正在启动...");
+                match saved_cookie_action(validate_saved_cookies(&auth, &cookies).await, config.offline_grace) {
+                    action @ (SavedCookieAction::Use | SavedCookieAction::UseUnverified(_)) => {
+                        let room_id = if let SavedCookieAction::UseUnverified(e) = action {
+                            println!("验证认证信息失败: {}，继续使用已保存的认证信息", e);
+                            room_id
+                        } else {
+                            println!("认证信息有效，正在启动...");
+                            reconcile_room_id(&auth, &store, &mut config, &cookies, room_id).await
+                        };
                         auth::UserInfo {
                             uid: 0,
                             room_id,
-                            csrf: config.csrf.as_ref().unwrap().clone(),
+                            csrf: creds.csrf.clone(),
                             cookies,
+                            refresh_token: creds.refresh_token.clone(),
                         }
                     }
-                    Ok(false) => {
-                        println!("认证信息已过期，开始扫码登录");
-                        match login().await {
-                            Ok(user_info) => {
-                                save_credentials(&mut config, &user_info);
-                                user_info
-                            }
-                            Err(e) => {
-                                eprintln!("登录失败: {}", e);
-                                return Err(e);
+                    SavedCookieAction::Refresh => match refresh_credentials(&auth, &store, &mut config, &creds, &cookies, room_id).await {
+                        Some(user_info) => user_info,
+                        None => {
+                            println!("认证信息已过期，开始扫码登录");
+                            match login(&config).await {
+                                Ok(user_info) => {
+                                    save_credentials(&store, &mut config, &user_info);
+                                    user_info
+                                }
+                                Err(e) => {
+                                    eprintln!("登录失败: {}", e);
+                                    return Err(e);
+                                }
                             }
                         }
-                    }
-                    Err(e) => {
+                    },
+                    SavedCookieAction::Login(e) => {
                         println!("验证认证信息失败: {}", e);
                         println!("开始扫码登录");
-                        match login().await {
+                        match login(&config).await {
                             Ok(user_info) => {
-                                save_credentials(&mut config, &user_info);
+                                save_credentials(&store, &mut config, &user_info);
                                 user_info
                             }
                             Err(e) => {
@@ -101,9 +367,9 @@ async fn run_tui() -> Result<()> {
             }
             _ => {
                 println!("解析已保存的认证信息失败，开始扫码登录");
-                match login().await {
+                match login(&config).await {
                     Ok(user_info) => {
-                        save_credentials(&mut config, &user_info);
+                        save_credentials(&store, &mut config, &user_info);
                         user_info
                     }
                     Err(e) => {
@@ -115,9 +381,9 @@ async fn run_tui() -> Result<()> {
         }
     } else {
         println!("扫码登录");
-        match login().await {
+        match login(&config).await {
             Ok(user_info) => {
-                save_credentials(&mut config, &user_info);
+                save_credentials(&store, &mut config, &user_info);
                 user_info
             }
             Err(e) => {
@@ -127,95 +393,324 @@ async fn run_tui() -> Result<()> {
         }
     };
 
-    // 创建Live实例
-    let live = match Live::new_with_cookies_map(user_info.room_id, user_info.csrf.clone(), &user_info.cookies) {
-        Ok(live) => live,
+    // 创建共享的客户端，Live和Bullet共用同一个cookie jar和连接池
+    let client = match create_client(&config, &user_info.cookies) {
+        Ok(client) => client.with_retry_config(config.retry_count, config.retry_delay),
         Err(e) => {
             eprintln!("创建直播客户端失败: {}", e);
             return Err(e);
         }
     };
+    let room_id = target_room_id(&client, &user_info, &config, options).await;
+    let live = create_live(client.clone(), room_id, &user_info, &config, options);
+    if let Some(area_id) = options.area_id
+        && let Err(e) = set_area_by_id(&live, &mut config, area_id).await
+    {
+        eprintln!("设置直播分区失败: {}", e);
+    }
+    let bullet = Bullet::with_client(client, room_id, user_info.csrf.clone())
+        .with_sensitive_words(config.sensitive_words.clone());
 
     // 创建TUI应用（在保存认证信息之后，确保config包含最新的登录信息）
-    let app = TuiApp::new(config);
+    let mut app = TuiApp::new(config).with_credential_store(Box::new(store));
+
+    // 预取已完成则直接使用，否则交给首次修改分区时按需加载；预取失败不影响登录
+    if area_prefetch.is_finished() {
+        if let Ok(Ok(areas)) = area_prefetch.await {
+            app = app.with_area_list(areas);
+        }
+    } else {
+        area_prefetch.abort();
+    }
 
     // 运行TUI应用
-    app.with_live(live, user_info).run().await
+    app.with_live(live, user_info).with_bullet(bullet).run().await
 }
 
-fn save_credentials(config: &mut Config, user_info: &auth::UserInfo) {
-    let cookie_str = Auth::cookies_to_string(&user_info.cookies);
+/// 使用已保存的认证信息直接下播，未在直播时视为成功，认证无效时返回错误
+async fn run_stop(options: &RunOptions) -> Result<()> {
+    let mut config = options.load_config()?;
+    let store = options.credential_store();
+    resolve_env_room(&mut config).await;
+    
+    let creds = load_credentials(&store, &config)
+        .map_err(|_| BiliError::auth("未保存认证信息，请先登录"))?;
+    let cookies = Auth::parse_cookie_string(&creds.cookie_str)?;
+    let room_id: u64 = creds.room_id.parse()
+        .map_err(|_| BiliError::auth("保存的直播间号无效，请重新登录"))?;
+    
+    let auth = Auth::with_client(create_client(&config, &HashMap::new())?);
+    let user_info = match saved_cookie_action(validate_saved_cookies(&auth, &cookies).await, config.offline_grace) {
+        SavedCookieAction::Use | SavedCookieAction::UseUnverified(_) => auth::UserInfo {
+            uid: 0,
+            room_id,
+            csrf: creds.csrf.clone(),
+            cookies,
+            refresh_token: creds.refresh_token.clone(),
+        },
+        SavedCookieAction::Refresh => refresh_credentials(&auth, &store, &mut config, &creds, &cookies, room_id).await
+            .ok_or_else(|| BiliError::auth("认证信息已失效，请重新登录"))?,
+        SavedCookieAction::Login(e) => return Err(e.context("验证认证信息")),
+    };
+    
+    let client = create_client(&config, &user_info.cookies).context("创建直播客户端")?
+        .with_retry_config(config.retry_count, config.retry_delay);
+    let room_id = target_room_id(&client, &user_info, &config, options).await;
+    let live = create_live(client, room_id, &user_info, &config, options);
+    
+    stop_live(&live, &mut config).await
+}
+
+async fn run_doctor(options: &RunOptions, report_path: Option<&String>) -> Result<()> {
+    let config = match options.load_config() {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            eprintln!("加载配置失败: {}", e);
+            options.default_config()
+        }
+    };
+
+    println!("正在运行诊断...");
+    let report = Diagnostics::run(&config).await;
+    println!("{}", report.render());
+
+    if let Some(path) = report_path {
+        report.save(std::path::Path::new(path))?;
+        println!("诊断报告已保存到: {}", path);
+    }
+
+    Ok(())
+}
+
+/// 网络异常时验证cookie的最多尝试次数
+const VALIDATE_ATTEMPTS: u32 = 3;
+const VALIDATE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// 验证已保存cookie后的下一步
+enum SavedCookieAction {
+    /// cookie有效
+    Use,
+    /// 无法验证但开启了offline_grace，继续使用已保存的cookie
+    UseUnverified(BiliError),
+    /// cookie已失效，先尝试刷新，失败再扫码
+    Refresh,
+    /// 无法验证且未开启offline_grace，需要扫码登录
+    Login(BiliError),
+}
+
+fn saved_cookie_action(result: Result<bool>, offline_grace: bool) -> SavedCookieAction {
+    match result {
+        Ok(true) => SavedCookieAction::Use,
+        Ok(false) => SavedCookieAction::Refresh,
+        Err(e) if offline_grace => SavedCookieAction::UseUnverified(e),
+        Err(e) => SavedCookieAction::Login(e),
+    }
+}
+
+/// 验证cookie，网络异常时重试，确认有效或失效时立即返回
+async fn validate_saved_cookies(auth: &Auth, cookies: &HashMap<String, String>) -> Result<bool> {
+    retry_validation(|| auth.validate_cookies(cookies), VALIDATE_ATTEMPTS, VALIDATE_RETRY_DELAY).await
+}
+
+async fn retry_validation<F, Fut>(mut validate: F, attempts: u32, delay: Duration) -> Result<bool>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<bool>>,
+{
+    let mut attempt = 1;
+    loop {
+        match validate().await {
+            Err(e) if attempt < attempts => {
+                println!("验证认证信息失败: {}，{}ms后进行第{}次重试", e, delay.as_millis(), attempt);
+                attempt += 1;
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 环境变量只提供了cookie时，按其中的DedeUserID查询直播间号
+async fn resolve_env_room(config: &mut Config) {
+    let Some(uid) = config.env_room_uid() else {
+        return;
+    };
+    let result = match create_client(config, &HashMap::new()) {
+        Ok(client) => Auth::with_client(client).get_room_id(uid).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(room_id) => {
+            config.reconcile_room_id(room_id);
+        }
+        Err(e) => eprintln!("根据{}查询直播间号失败: {}", config::COOKIE_ENV, e),
+    }
+}
+
+/// 环境变量提供了认证信息时直接使用，否则读取凭证存储
+fn load_credentials(store: &dyn CredentialStore, config: &Config) -> Result<Credentials> {
+    if config.has_env_credentials() {
+        return config.get_credentials()
+            .ok_or_else(|| BiliError::auth("环境变量提供的认证信息不完整"));
+    }
+    store.load()
+}
+
+/// 从B站获取权威的直播间号，与保存的不一致时更新配置
+async fn reconcile_room_id(
+    auth: &Auth,
+    store: &dyn CredentialStore,
+    config: &mut Config,
+    cookies: &HashMap<String, String>,
+    stored: u64,
+) -> u64 {
+    let Some(uid) = cookies.get("DedeUserID").and_then(|id| id.parse::<u64>().ok()) else {
+        return stored;
+    };
+    
+    match auth.get_room_id(uid).await {
+        Ok(fetched) => {
+            if config.reconcile_room_id(fetched) {
+                println!("直播间号已变更: {} -> {}", stored, fetched);
+                if let Some(creds) = config.get_credentials()
+                    && let Err(e) = store.save(&creds)
+                {
+                    eprintln!("保存新的直播间号失败: {}", e);
+                }
+            }
+            fetched
+        }
+        Err(e) => {
+            println!("获取直播间号失败，继续使用已保存的房间号: {}", e);
+            stored
+        }
+    }
+}
+
+/// cookie过期后用refresh_token换取新cookie并保存，没有令牌或刷新失败时返回None以便重新扫码
+async fn refresh_credentials(
+    auth: &Auth,
+    store: &dyn CredentialStore,
+    config: &mut Config,
+    creds: &Credentials,
+    cookies: &HashMap<String, String>,
+    room_id: u64,
+) -> Option<auth::UserInfo> {
+    let refresh_token = creds.refresh_token.as_deref()?;
+    println!("认证信息已过期，正在尝试刷新...");
+    
+    match auth.refresh_cookie(cookies, refresh_token).await {
+        Ok(refreshed) => {
+            println!("刷新认证信息成功");
+            if let Some(reason) = &refreshed.confirm_error {
+                println!("警告: {}", reason);
+            }
+            let user_info = auth::UserInfo {
+                uid: 0,
+                room_id,
+                csrf: refreshed.cookies.get("bili_jct").cloned().unwrap_or_else(|| creds.csrf.clone()),
+                cookies: refreshed.cookies,
+                refresh_token: Some(refreshed.refresh_token),
+            };
+            save_credentials(store, config, &user_info);
+            Some(user_info)
+        }
+        Err(e) => {
+            println!("刷新认证信息失败: {}", e);
+            None
+        }
+    }
+}
+
+fn save_credentials(store: &dyn CredentialStore, config: &mut Config, user_info: &auth::UserInfo) {
+    let creds = Credentials {
+        room_id: user_info.room_id.to_string(),
+        cookie_str: Auth::cookies_to_string(&user_info.cookies),
+        csrf: user_info.csrf.clone(),
+        refresh_token: user_info.refresh_token.clone(),
+    };
     config.set_credentials(
-        user_info.room_id.to_string(),
-        cookie_str,
-        user_info.csrf.clone(),
+        creds.room_id.clone(),
+        creds.cookie_str.clone(),
+        creds.csrf.clone(),
     );
-    if let Err(e) = config.save() {
+    config.refresh_token = creds.refresh_token.clone();
+    if let Err(e) = store.save(&creds) {
         eprintln!("保存认证信息失败: {}", e);
         eprintln!("程序将继续运行，但下次启动时需要重新登录");
     }
 }
 
-async fn run_cli() -> Result<()> {
+async fn run_cli(options: &RunOptions) -> Result<()> {
     println!("=== 哔哩哔哩直播推流码获取工具 ===");
     println!("版本: 0.1.0");
     println!("作者: Chace");
     println!();
     
     // 加载配置
-    let mut config = match Config::load() {
+    let mut config = match options.load_config() {
         Ok(cfg) => cfg,
         Err(e) => {
             println!("加载配置失败: {}", e);
             println!("使用默认配置");
-            Config::default()
+            options.default_config()
         }
     };
-    
-
+    let store = options.credential_store();
+    resolve_env_room(&mut config).await;
     
     // 获取认证信息
-    let user_info = if config.has_credentials() {
+    let user_info = if let Ok(creds) = load_credentials(&store, &config) {
         println!("检测到已保存的认证信息，正在验证...");
         
         // 尝试使用已保存的认证信息
-        let auth_result = Auth::new();
-        let cookies_result = Auth::parse_cookie_string(&config.cookie_str.as_ref().unwrap());
-        let room_id_result = config.room_id.as_ref().unwrap().parse::<u64>();
+        let auth_result = create_client(&config, &HashMap::new()).map(Auth::with_client);
+        let cookies_result = Auth::parse_cookie_string(&creds.cookie_str);
+        let room_id_result = creds.room_id.parse::<u64>();
         
         match (auth_result, cookies_result, room_id_result) {
             (Ok(auth), Ok(cookies), Ok(room_id)) => {
-                match auth.validate_cookies(&cookies).await {
-                    Ok(true) => {
-                        println!("认证信息有效");
+                match saved_cookie_action(validate_saved_cookies(&auth, &cookies).await, config.offline_grace) {
+                    action @ (SavedCookieAction::Use | SavedCookieAction::UseUnverified(_)) => {
+                        let room_id = if let SavedCookieAction::UseUnverified(e) = action {
+                            println!("验证认证信息失败: {}，继续使用已保存的认证信息", e);
+                            room_id
+                        } else {
+                            println!("认证信息有效");
+                            reconcile_room_id(&auth, &store, &mut config, &cookies, room_id).await
+                        };
                         auth::UserInfo {
                             uid: 0,
                             room_id,
-                            csrf: config.csrf.as_ref().unwrap().clone(),
+                            csrf: creds.csrf.clone(),
                             cookies,
+                            refresh_token: creds.refresh_token.clone(),
                         }
                     }
-                    Ok(false) => {
-                        println!("认证信息已过期，开始扫码登录");
-                        match login().await {
-                            Ok(user_info) => {
-                                // 保存新的认证信息
-                                save_credentials(&mut config, &user_info);
-                                user_info
-                            }
-                            Err(e) => {
-                                println!("登录失败: {}", e);
-                                return Err(e);
+                    SavedCookieAction::Refresh => match refresh_credentials(&auth, &store, &mut config, &creds, &cookies, room_id).await {
+                        Some(user_info) => user_info,
+                        None => {
+                            println!("认证信息已过期，开始扫码登录");
+                            match login(&config).await {
+                                Ok(user_info) => {
+                                    // 保存新的认证信息
+                                    save_credentials(&store, &mut config, &user_info);
+                                    user_info
+                                }
+                                Err(e) => {
+                                    println!("登录失败: {}", e);
+                                    return Err(e);
+                                }
                             }
                         }
-                    }
-                    Err(e) => {
+                    },
+                    SavedCookieAction::Login(e) => {
                         println!("验证认证信息失败: {}", e);
                         println!("开始扫码登录");
-                        match login().await {
+                        match login(&config).await {
                             Ok(user_info) => {
                                 // 保存新的认证信息
-                                save_credentials(&mut config, &user_info);
+                                save_credentials(&store, &mut config, &user_info);
                                 user_info
                             }
                             Err(e) => {
@@ -228,10 +723,10 @@ async fn run_cli() -> Result<()> {
             }
             _ => {
                 println!("解析已保存的认证信息失败，开始扫码登录");
-                match login().await {
+                match login(&config).await {
                     Ok(user_info) => {
                         // 保存新的认证信息
-                        save_credentials(&mut config, &user_info);
+                        save_credentials(&store, &mut config, &user_info);
                         user_info
                     }
                     Err(e) => {
@@ -243,10 +738,10 @@ async fn run_cli() -> Result<()> {
         }
     } else {
         println!("扫码登录");
-        match login().await {
+        match login(&config).await {
             Ok(user_info) => {
                 // 保存新的认证信息
-                save_credentials(&mut config, &user_info);
+                save_credentials(&store, &mut config, &user_info);
                 user_info
             }
             Err(e) => {
@@ -257,26 +752,60 @@ async fn run_cli() -> Result<()> {
     };
     
     // 创建Live实例，使用HashMap格式的cookies
-    let live = match Live::new_with_cookies_map(user_info.room_id, user_info.csrf.clone(), &user_info.cookies) {
-        Ok(live) => live,
+    let client = match create_client(&config, &user_info.cookies) {
+        Ok(client) => client.with_retry_config(config.retry_count, config.retry_delay),
         Err(e) => {
             println!("创建直播客户端失败: {}", e);
             return Err(e);
         }
     };
+    let room_id = target_room_id(&client, &user_info, &config, options).await;
+    let bullet = Bullet::with_client(client.clone(), room_id, user_info.csrf.clone())
+        .with_sensitive_words(config.sensitive_words.clone());
+    let live = create_live(client, room_id, &user_info, &config, options);
+    warn_if_cannot_start(&live).await;
     
     // 检查当前直播状态
     match live.is_live().await {
         Ok(is_live) => {
+            // 上次开播中断时找回推流码，而不是重新开播
+            match Live::recover_pending_start(&config, is_live, || live.get_stream_addr()).await {
+                Some(Ok((server, key))) => {
+                    println!("检测到上次开播未完成，已找回推流码");
+                    if let Err(e) = config.save_stream_info(server, key) {
+                        println!("保存推流信息失败: {}", e);
+                    }
+                }
+                Some(Err(e)) => println!("恢复推流码失败: {}", e),
+                None if config.has_pending_start() => {
+                    let _ = config.clear_pending_start();
+                }
+                None => {}
+            }
+            
             if is_live {
                 println!("检测到当前正在直播中");
                 // 如果有保存的推流信息，显示出来
                 if let Some((server, key)) = config.get_stream_info() {
                     println!("当前推流信息:");
                     println!("推流服务器: {}", server);
-                    println!("推流码: {}", key);
+                    println!("推流码: {}", key.reveal());
+                    if config.is_stream_info_stale(chrono::Duration::hours(config::STREAM_INFO_MAX_AGE_HOURS)) {
+                        println!("警告: 保存的推流码已超过{}小时，可能已失效，建议重新获取", config::STREAM_INFO_MAX_AGE_HOURS);
+                    }
                 } else {
-                    println!("但未找到保存的推流信息");
+                    match live.get_active_stream_info().await {
+                        Ok(Some((server, key))) => {
+                            println!("已获取当前推流信息:");
+                            println!("推流服务器: {}", server);
+                            println!("推流码: {}", key.reveal());
+                            if let Err(e) = config.save_stream_info(server, key) {
+                                println!("保存推流信息失败: {}", e);
+                            }
+                        }
+                        Ok(None) => println!("但未找到保存的推流信息"),
+                        Err(e) => println!("未找到保存的推流信息，获取当前推流信息失败: {}", e),
+                    }
                 }
             } else {
                 println!("当前未在直播中");
@@ -290,25 +819,84 @@ async fn run_cli() -> Result<()> {
     // 直接设置直播标题和分区，然后获取推流码
     println!("\n=== 设置直播信息 ===");
     
-    // 设置直播标题
-    if let Err(e) = set_title(&live).await {
-        println!("设置直播标题失败: {}", e);
-        println!("继续使用默认标题...");
-    }
-    
-    // 设置直播分区
-    if let Err(e) = set_area(&live).await {
-        println!("设置直播分区失败: {}", e);
-        println!("继续使用默认分区...");
+    // 使用上次设置时跳过标题和分区输入，恢复失败时改为手动设置
+    let restored = options.use_last && restore_last_settings(&live, &config).await;
+    if !restored {
+        // 设置直播标题，指定了标题时跳过输入
+        let title_result = match &options.title {
+            Some(title) => live.set_title(title).await.map(|update| {
+                if let Some(notice) = update.truncation_notice() {
+                    println!("{}", notice);
+                }
+                println!("标题已设置为: {}", update.title);
+            }),
+            None => set_title(&live).await,
+        };
+        if let Err(e) = title_result {
+            println!("设置直播标题失败: {}", e);
+            if options.is_one_shot() {
+                return Err(e);
+            }
+            println!("继续使用默认标题...");
+        }
+        
+        // 设置直播分区，指定了分区ID时跳过分区列表，已恢复上次分区时不再询问
+        let area_result = match options.area_id {
+            Some(area_id) => set_area_by_id(&live, &mut config, area_id).await,
+            None if apply_last_area(&live, &config).await => Ok(()),
+            None => set_area(&live, &mut config).await,
+        };
+        if let Err(e) = area_result {
+            println!("设置直播分区失败: {}", e);
+            if options.is_one_shot() {
+                return Err(e);
+            }
+            println!("继续使用默认分区...");
+        }
     }
     
     // 获取推流码并开始直播
     println!("\n=== 获取推流码并开始直播 ===");
-    if let Err(e) = start_live(&live, &mut config).await {
-        println!("获取推流码失败: {}", e);
-        return Err(e);
+    let (server, key) = match start_live(&live, &mut config).await {
+        Ok(info) => info,
+        Err(e) => {
+            println!("获取推流码失败: {}", e);
+            return Err(e);
+        }
+    };
+    let started_at = std::time::Instant::now();
+    
+    if let Some(path) = &options.obs_profile {
+        match live::ObsServiceProfile::new(&server, &key).save(path).await {
+            Ok(()) => println!("已导出OBS推流配置: {}", path),
+            Err(e) => println!("导出OBS推流配置失败: {}", e),
+        }
+    }
+    
+    if !config.auto_danmaku.is_empty() {
+        println!("正在发送开播弹幕...");
+        let results = bullet.send_bullets_adaptive(config.auto_danmaku.iter().map(String::as_str).collect()).await;
+        println!("开播弹幕: {}", results.summary());
     }
     
+    // 一次性开播输出可被脚本解析的推流信息后直接退出，保持直播状态
+    if options.is_one_shot() {
+        println!("{}", format_stream_output(live.get_room_id(), &server, &key, options.json));
+        return Ok(());
+    }
+    
+    // 直播期间在后台上报心跳，连续失败时提示；下播时随stop_live停止
+    let mut heartbeat = live.start_heartbeat();
+    tokio::spawn(async move {
+        while let Some(alert) = heartbeat.next_alert().await {
+            if alert.reached_limit() {
+                println!("\n警告: {}", alert);
+            } else {
+                println!("{}", alert);
+            }
+        }
+    });
+    
     // 等待用户输入停止直播
     println!("\n已开启直播，请迅速进入第三方直播软件进行直播！");
     println!("下播时请输入Y或y关闭直播！");
@@ -321,6 +909,10 @@ async fn run_cli() -> Result<()> {
         io::stdin().read_line(&mut input)?;
         
         if input.trim().to_lowercase() == "y" {
+            if started_at.elapsed() < config.toggle_cooldown() {
+                println!("操作过于频繁，请稍后再试");
+                continue;
+            }
             break;
         }
     }
@@ -344,15 +936,69 @@ async fn run_cli() -> Result<()> {
 
 
 
-async fn login() -> Result<auth::UserInfo> {
+/// 按配置创建带cookies的客户端
+fn create_client(config: &Config, cookies: &HashMap<String, String>) -> Result<BilibiliClient> {
+    let client = BilibiliClient::from_config(config)?;
+    client.add_cookies(cookies);
+    client.set_retry_hook(|attempt| println!("{}", attempt));
+    Ok(client)
+}
+
+async fn login(config: &Config) -> Result<auth::UserInfo> {
     println!("=== 登录 ===");
     
-    // 直接使用二维码登录，不再询问
-    qr_login().await
+    // 按配置选择登录方式，不再询问
+    match config.login_method {
+        config::LoginMethod::Qr => qr_login(config).await,
+        config::LoginMethod::Sms => sms_login(config).await,
+    }
 }
 
-async fn qr_login() -> Result<auth::UserInfo> {
-    let auth = match Auth::new() {
+/// 读取一行输入并去掉首尾空白
+fn prompt(message: &str) -> Result<String> {
+    print!("{}", message);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+async fn sms_login(config: &Config) -> Result<auth::UserInfo> {
+    let auth = Auth::with_client(create_client(config, &HashMap::new())?)
+        .with_endpoints(config.endpoints.clone());
+    let tel = prompt("请输入手机号（中国大陆）: ")?;
+    
+    let captcha = auth.request_captcha().await.context("获取人机验证参数")?;
+    println!("发送验证码前需要完成极验人机验证:");
+    println!("gt: {}", captcha.gt);
+    println!("challenge: {}", captcha.challenge);
+    let solution = auth::CaptchaSolution {
+        token: captcha.token,
+        challenge: captcha.challenge,
+        validate: prompt("请输入验证结果 validate: ")?,
+        seccode: prompt("请输入验证结果 seccode: ")?,
+    };
+    
+    let captcha_key = auth.send_sms_code(&tel, auth::DEFAULT_COUNTRY_ID, &solution).await
+        .context("发送短信验证码")?;
+    let code = prompt("请输入收到的短信验证码: ")?;
+    match auth.sms_login(&tel, auth::DEFAULT_COUNTRY_ID, &code, &captcha_key).await {
+        Ok(user_info) => Ok(user_info),
+        Err(e) => {
+            println!("短信登录失败: {}", e);
+            Err(e)
+        }
+    }
+}
+
+async fn qr_login(config: &Config) -> Result<auth::UserInfo> {
+    let auth = match create_client(config, &HashMap::new())
+        .map(|client| {
+            Auth::with_client(client)
+                .with_qr_refresh_limit(config.qr_refresh_limit)
+                .with_qr_viewer(config.qr_open_viewer)
+        })
+    {
         Ok(auth) => auth,
         Err(e) => {
             println!("创建认证客户端失败: {}", e);
@@ -360,7 +1006,17 @@ async fn qr_login() -> Result<auth::UserInfo> {
         }
     };
     
-    match auth.qr_login().await {
+    let cancel = CancellationToken::new();
+    let mut watcher = None;
+    let result = auth.qr_login_with_cancel(&cancel, || {
+        println!("按Esc取消登录");
+        watcher = Some(EscWatcher::spawn(cancel.clone()));
+    }).await;
+    if let Some(watcher) = watcher {
+        watcher.stop().await;
+    }
+    
+    match result {
         Ok(user_info) => Ok(user_info),
         Err(e) => {
             println!("二维码登录失败: {}", e);
@@ -369,7 +1025,44 @@ async fn qr_login() -> Result<auth::UserInfo> {
     }
 }
 
-async fn start_live(live: &Live, config: &mut Config) -> Result<()> {
+/// 等待扫码期间监听Esc（raw模式下Ctrl+C同样生效），按下后取消登录
+struct EscWatcher {
+    stop: CancellationToken,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl EscWatcher {
+    fn spawn(cancel: CancellationToken) -> Self {
+        let stop = CancellationToken::new();
+        let stopped = stop.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            if enable_raw_mode().is_err() {
+                return;
+            }
+            while !stopped.is_cancelled() {
+                if let Ok(true) = event::poll(Duration::from_millis(100))
+                    && let Ok(Event::Key(key)) = event::read()
+                    && key.kind == KeyEventKind::Press
+                    && (key.code == KeyCode::Esc
+                        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)))
+                {
+                    cancel.cancel();
+                    break;
+                }
+            }
+            let _ = disable_raw_mode();
+        });
+        Self { stop, handle }
+    }
+
+    async fn stop(self) {
+        self.stop.cancel();
+        let _ = self.handle.await;
+    }
+}
+
+/// 开播，返回推流服务器和推流码
+async fn start_live(live: &Live, config: &mut Config) -> Result<(String, live::StreamKey)> {
     println!("正在获取推流码，请稍等...");
     
     // 获取当前分区ID
@@ -382,24 +1075,130 @@ async fn start_live(live: &Live, config: &mut Config) -> Result<()> {
     };
     println!("使用分区: {} (ID: {})", current_area_name, current_area_id);
     
-    let stream_data = match live.start_live(current_area_id).await {
-        Ok(data) => data,
+    if let Ok(live::FaceAuthStatus::Required) = live.face_auth_status().await {
+        println!("开播前需要完成人脸认证，请在B站App中打开直播中心完成认证，正在等待认证完成...");
+        if let Err(e) = live.wait_face_auth(live::FACE_AUTH_TIMEOUT).await {
+            println!("等待人脸认证失败: {}", e);
+            return Err(e);
+        }
+        println!("人脸认证已完成");
+    }
+    
+    // 先写入待确认标记，推流码保存后清除
+    if let Err(e) = config.mark_pending_start() {
+        println!("写入开播标记失败: {}", e);
+    }
+    
+    let stream_data = match start_live_with_face_auth(live, current_area_id).await {
+        Ok((data, diagnostics)) => {
+            if diagnostics.retried > 0 {
+                println!("开播经过回退重试: {:?}", diagnostics);
+            }
+            data
+        }
         Err(e) => {
             println!("开始直播失败: {}", e);
             return Err(e);
         }
     };
     
+    // 直播间尚未进入直播状态时，按服务端提示等待推流码生效
+    if let Some(delay) = stream_data.ready_delay() {
+        wait_stream_ready(delay).await;
+    }
+    
     println!("成功获取推流码!");
     println!("{}", live.format_stream_info(&stream_data));
     
     // 保存推流信息到配置文件
-    let (rtmp_url, stream_key) = live.parse_stream_info(&stream_data);
-    if let Err(e) = config.save_stream_info(rtmp_url, stream_key) {
+    let (rtmp_url, stream_key) = live.preferred_stream_info(&stream_data);
+    if let Err(e) = config.save_stream_info(rtmp_url.clone(), stream_key.clone()) {
         println!("保存推流信息失败: {}", e);
     }
+    if let Err(e) = live.remember_settings(config).await {
+        println!("保存本次直播设置失败: {}", e);
+    }
     
-    Ok(())
+    send_notification(config, StreamEvent::StreamStarted { room_id: live.get_room_id() }).await;
+    
+    Ok((rtmp_url, stream_key))
+}
+
+/// 按配置发送直播事件通知，失败只提示，不影响开播/下播
+async fn send_notification(config: &Config, event: StreamEvent) {
+    let result = match notify::from_config(config) {
+        Ok(notifier) => notifier.notify(event).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        println!("{}", e);
+    }
+}
+
+/// 不满足开播条件时提前说明原因，检查失败不影响后续流程
+async fn warn_if_cannot_start(live: &Live) {
+    match live.check_live_permission().await {
+        Ok(permission) if !permission.can_start() => {
+            println!("当前账号暂时无法开播:");
+            for problem in permission.problems() {
+                println!("  - {}", problem);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => println!("检查开播条件失败: {}", e),
+    }
+}
+
+/// 等待推流码生效，每秒刷新一次倒计时
+async fn wait_stream_ready(delay: Duration) {
+    let deadline = tokio::time::Instant::now() + delay;
+    loop {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        print!("\r推流码将在{}秒后生效，请稍候...", remaining.as_secs_f64().ceil() as u64);
+        let _ = io::stdout().flush();
+        tokio::time::sleep(remaining.min(Duration::from_secs(1))).await;
+    }
+    println!();
+}
+
+/// 开播，接口要求人脸认证时打印认证二维码，等待认证完成后重新开播一次
+async fn start_live_with_face_auth(live: &Live, area_id: u32) -> Result<(live::LiveStreamData, live::StartDiagnostics)> {
+    let error = match live.start_live(area_id).await {
+        Err(e) => e,
+        ok => return ok,
+    };
+    let Some(qr) = error.face_auth_qr() else {
+        return Err(error);
+    };
+    
+    println!("{}", error);
+    if !qr.is_empty() {
+        println!("请使用B站App扫描下方二维码完成人脸认证:");
+        QRCode::print_unicode_to_terminal(qr)?;
+    } else {
+        println!("请在B站App中打开直播中心完成人脸认证");
+    }
+    println!("正在等待认证完成...");
+    live.wait_face_auth(live::FACE_AUTH_TIMEOUT).await?;
+    
+    println!("人脸认证已完成，重新开播...");
+    live.start_live(area_id).await
+}
+
+/// 一次性开播的输出，json为false时每行一个 `KEY=VALUE`，否则为单行JSON
+fn format_stream_output(room_id: u64, server: &str, key: &live::StreamKey, json: bool) -> String {
+    if json {
+        serde_json::json!({
+            "room_id": room_id,
+            "stream_server": server,
+            "stream_key": key.reveal(),
+        }).to_string()
+    } else {
+        format!("ROOM_ID={}\nSTREAM_SERVER={}\nSTREAM_KEY={}", room_id, server, key.reveal())
+    }
 }
 
 async fn stop_live(live: &Live, config: &mut Config) -> Result<()> {
@@ -420,13 +1219,19 @@ async fn stop_live(live: &Live, config: &mut Config) -> Result<()> {
     }
     
     match live.stop_live().await {
-        Ok(_) => {
+        Ok(live::StopOutcome::Stopped) => {
             println!("直播已停止");
             
             // 清除配置文件中的推流信息
             if let Err(e) = config.clear_stream_info() {
                 println!("清除推流信息失败: {}", e);
             }
+            
+            send_notification(config, StreamEvent::StreamStopped { room_id: live.get_room_id() }).await;
+        }
+        Ok(live::StopOutcome::Pending) => {
+            println!("下播请求已提交，但直播间仍处于直播状态");
+            println!("请稍后在B站直播间确认是否已停止");
         }
         Err(e) => {
             println!("停止直播失败: {}", e);
@@ -464,7 +1269,12 @@ async fn set_title(live: &Live) -> Result<()> {
     let new_title = input.trim();
     if !new_title.is_empty() {
         match live.set_title(new_title).await {
-            Ok(_) => println!("标题设置成功"),
+            Ok(update) => {
+                if let Some(notice) = update.truncation_notice() {
+                    println!("{}", notice);
+                }
+                println!("标题设置成功");
+            }
             Err(e) => {
                 println!("设置标题失败: {}", e);
                 println!("将继续使用当前标题");
@@ -477,7 +1287,71 @@ async fn set_title(live: &Live) -> Result<()> {
     Ok(())
 }
 
-async fn set_area(live: &Live) -> Result<()> {
+async fn set_area_by_id(live: &Live, config: &mut Config, area_id: u32) -> Result<()> {
+    println!("正在设置分区ID: {}", area_id);
+    let (id, name) = live.set_area_raw(area_id).await?;
+    match live.area_path_for_id(id).await {
+        Ok(Some(path)) => println!("分区已设置为: {} (ID: {})", path, id),
+        _ => println!("分区已设置为: {} (ID: {})", name, id),
+    }
+    if let Err(e) = config.remember_area(None, id) {
+        println!("保存分区设置失败: {}", e);
+    }
+    Ok(())
+}
+
+/// 恢复上次直播的标题和分区，返回是否已恢复
+async fn restore_last_settings(live: &Live, config: &Config) -> bool {
+    let Some(settings) = config.last_live_settings() else {
+        println!("没有上次直播设置的历史记录，请手动设置");
+        return false;
+    };
+    
+    match live.apply_last_settings(settings).await {
+        Ok(()) => {
+            println!("已恢复上次直播设置");
+            if !settings.live_title.is_empty() {
+                println!("标题: {}", settings.live_title);
+            }
+            if let Ok(area_path) = live.get_current_area_path().await {
+                println!("分区: {}", area_path);
+            }
+            true
+        }
+        Err(e) => {
+            println!("恢复上次直播设置失败: {}，请手动设置", e);
+            false
+        }
+    }
+}
+
+/// 按配置恢复上次使用的分区，返回是否已恢复
+async fn apply_last_area(live: &Live, config: &Config) -> bool {
+    if !config.auto_apply_last_area {
+        return false;
+    }
+    
+    let areas = match live.get_area_list().await {
+        Ok(areas) => areas,
+        Err(e) => {
+            println!("获取分区列表失败: {}", e);
+            return false;
+        }
+    };
+    match live.apply_last_area(config, &areas).await {
+        Ok(Some((id, name))) => {
+            println!("已恢复上次使用的分区: {} (ID: {})", name, id);
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            println!("恢复上次使用的分区失败: {}", e);
+            false
+        }
+    }
+}
+
+async fn set_area(live: &Live, config: &mut Config) -> Result<()> {
     println!("=== 设置直播分区 ===");
     
     let (current_area_id, current_area_name) = match live.get_current_area().await {
@@ -512,7 +1386,8 @@ async fn set_area(live: &Live) -> Result<()> {
     for (i, category) in areas.iter().enumerate() {
         println!("{}. {}", i + 1, category.name);
         for (j, area) in category.list.iter().enumerate() {
-            println!("   {}.{} {}", i + 1, j + 1, area.name);
+            let lock = if area.is_locked() { " 🔒已锁定" } else { "" };
+            println!("   {}.{} {}{}", i + 1, j + 1, area.name, lock);
         }
     }
     
@@ -560,10 +1435,15 @@ async fn set_area(live: &Live) -> Result<()> {
         return Ok(());
     }
     
-    let area_id = areas[main_idx].list[sub_idx].id;
+    let area = &areas[main_idx].list[sub_idx];
     
-    match live.set_area(area_id).await {
-        Ok(_) => println!("分区设置成功"),
+    match live.set_area(area.id).await {
+        Ok(_) => {
+            println!("分区设置成功");
+            if let Err(e) = config.remember_area(Some(area.parent_id), area.id) {
+                println!("保存分区设置失败: {}", e);
+            }
+        }
         Err(e) => {
             println!("设置分区失败: {}", e);
             println!("将继续使用当前分区");
@@ -572,3 +1452,127 @@ async fn set_area(live: &Live) -> Result<()> {
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bash_completions_list_subcommands() {
+        let mut out = Vec::new();
+        write_completions(Shell::Bash, &mut out);
+        let script = String::from_utf8(out).unwrap();
+
+        assert!(!script.is_empty());
+        assert!(script.contains("doctor"));
+        assert!(script.contains("completions"));
+    }
+
+    #[test]
+    fn test_stop_flag_parses_with_profile() {
+        let matches = build_cli()
+            .try_get_matches_from(["bilibili_live_tool", "--stop", "--profile", "alt"])
+            .unwrap();
+
+        assert!(matches.get_flag("stop"));
+        assert_eq!(matches.get_one::<String>("profile").map(String::as_str), Some("alt"));
+    }
+
+    #[test]
+    fn test_one_shot_options_and_output() {
+        let matches = build_cli()
+            .try_get_matches_from(["bilibili_live_tool", "-c", "--title", "测试", "--area-id", "236", "--json"])
+            .unwrap();
+        let options = RunOptions::from_matches(&matches);
+        assert!(options.is_one_shot());
+        assert!(options.json);
+        assert!(!RunOptions { title: Some("测试".to_string()), ..Default::default() }.is_one_shot());
+        assert!(build_cli().try_get_matches_from(["bilibili_live_tool", "-c", "--json"]).is_err());
+
+        let matches = build_cli().try_get_matches_from(["bilibili_live_tool", "-c", "--last"]).unwrap();
+        assert!(RunOptions::from_matches(&matches).use_last);
+        assert!(build_cli().try_get_matches_from(["bilibili_live_tool", "--last"]).is_err());
+        assert!(build_cli().try_get_matches_from(["bilibili_live_tool", "-c", "--last", "--title", "测试"]).is_err());
+
+        let key = live::StreamKey::new("?streamname=live_1&key=secret");
+        let json: serde_json::Value = serde_json::from_str(&format_stream_output(123, "rtmp://server/", &key, true)).unwrap();
+        assert_eq!(json["room_id"], 123);
+        assert_eq!(json["stream_key"], "?streamname=live_1&key=secret");
+
+        let plain = format_stream_output(123, "rtmp://server/", &key, false);
+        assert_eq!(plain.lines().collect::<Vec<_>>(), vec![
+            "ROOM_ID=123",
+            "STREAM_SERVER=rtmp://server/",
+            "STREAM_KEY=?streamname=live_1&key=secret",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_room_override_is_used_for_live() {
+        let matches = build_cli()
+            .try_get_matches_from(["bilibili_live_tool", "--room", "123"])
+            .unwrap();
+        let options = RunOptions::from_matches(&matches);
+        let user_info = auth::UserInfo {
+            uid: 1,
+            room_id: 456,
+            csrf: "csrf".to_string(),
+            cookies: HashMap::new(),
+            refresh_token: None,
+        };
+
+        let client = BilibiliClient::new(30_000).unwrap();
+        let config = Config { managed_room_id: Some(789), ..Config::default() };
+        assert_eq!(options.explicit_room(&config), Some(123));
+        assert_eq!(RunOptions::default().explicit_room(&config), Some(789));
+        assert_eq!(RunOptions::default().explicit_room(&Config::default()), None);
+        assert_eq!(target_room_id(&client, &user_info, &Config::default(), &RunOptions::default()).await, 456);
+
+        let live = create_live(client, 123, &user_info, &Config::default(), &options);
+        assert_eq!(live.get_room_id(), 123);
+
+        for invalid in ["0", "abc"] {
+            assert!(build_cli().try_get_matches_from(["bilibili_live_tool", "--room", invalid]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_protocol_flag_accepts_rtmp_and_srt() {
+        let matches = build_cli()
+            .try_get_matches_from(["bilibili_live_tool", "--protocol", "srt"])
+            .unwrap();
+        assert_eq!(matches.get_one::<String>("protocol").map(String::as_str), Some("srt"));
+
+        assert!(build_cli().try_get_matches_from(["bilibili_live_tool", "--protocol", "hls"]).is_err());
+    }
+
+    #[test]
+    fn test_saved_cookie_action_routes_outcomes() {
+        assert!(matches!(saved_cookie_action(Ok(true), false), SavedCookieAction::Use));
+        assert!(matches!(saved_cookie_action(Ok(false), true), SavedCookieAction::Refresh));
+        assert!(matches!(saved_cookie_action(Err(BiliError::timeout("超时")), false), SavedCookieAction::Login(_)));
+        assert!(matches!(saved_cookie_action(Err(BiliError::timeout("超时")), true), SavedCookieAction::UseUnverified(_)));
+    }
+
+    #[tokio::test]
+    async fn test_validation_retries_only_on_error() {
+        let calls = std::cell::Cell::new(0);
+        let result = retry_validation(|| {
+            calls.set(calls.get() + 1);
+            let attempt = calls.get();
+            async move {
+                if attempt == 1 { Err(BiliError::timeout("超时")) } else { Ok(false) }
+            }
+        }, VALIDATE_ATTEMPTS, Duration::ZERO).await;
+        assert!(!result.unwrap());
+        assert_eq!(calls.get(), 2);
+
+        calls.set(0);
+        let result = retry_validation(|| {
+            calls.set(calls.get() + 1);
+            async { Err(BiliError::timeout("超时")) }
+        }, VALIDATE_ATTEMPTS, Duration::ZERO).await;
+        assert!(result.is_err());
+        assert_eq!(calls.get(), VALIDATE_ATTEMPTS);
+    }
+}
@@ -0,0 +1,127 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use serde::Serialize;
+use crate::client::BilibiliClient;
+use crate::config::Config;
+use crate::error::{Result, ResultExt};
+
+/// webhook请求超时，避免通知卡住开播/下播流程
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 直播事件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// 开播成功
+    StreamStarted { room_id: u64 },
+    /// 主动下播
+    StreamStopped { room_id: u64 },
+    /// 直播意外中断
+    StreamDropped { room_id: u64 },
+}
+
+impl StreamEvent {
+    /// 事件描述
+    pub fn describe(&self) -> String {
+        match self {
+            StreamEvent::StreamStarted { room_id } => format!("直播间 {} 已开播", room_id),
+            StreamEvent::StreamStopped { room_id } => format!("直播间 {} 已下播", room_id),
+            StreamEvent::StreamDropped { room_id } => format!("直播间 {} 直播意外中断", room_id),
+        }
+    }
+}
+
+pub type NotifyFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// 直播事件通知
+///
+/// 返回装箱的 future 以便作为 `Box<dyn Notifier>` 使用；通知失败时返回错误，由调用方提示，不影响开播/下播流程。
+pub trait Notifier: Send + Sync {
+    fn notify(&self, event: StreamEvent) -> NotifyFuture<'_>;
+}
+
+/// 输出到终端的默认通知，TUI中改为写入日志面板
+#[derive(Debug, Clone, Default)]
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, event: StreamEvent) -> NotifyFuture<'_> {
+        Box::pin(async move {
+            println!("[通知] {}", event.describe());
+            Ok(())
+        })
+    }
+}
+
+/// 以JSON形式POST到指定地址的通知
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(client: reqwest::Client, url: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into(),
+        }
+    }
+    
+    /// 按配置的代理和超时创建，配置了 webhook_url 时返回Some
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        match config.webhook_url.as_deref().map(str::trim) {
+            Some(url) if !url.is_empty() => {
+                let client = BilibiliClient::from_config(config)?;
+                Ok(Some(Self::new(client.get_client().clone(), url)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, event: StreamEvent) -> NotifyFuture<'_> {
+        Box::pin(async move {
+            self.client
+                .post(&self.url)
+                .json(&event)
+                .timeout(WEBHOOK_TIMEOUT)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+                .context("发送通知")?;
+            Ok(())
+        })
+    }
+}
+
+/// 根据配置选择通知方式，配置了 webhook_url 时使用 webhook，否则输出到终端
+pub fn from_config(config: &Config) -> Result<Box<dyn Notifier>> {
+    Ok(match WebhookNotifier::from_config(config)? {
+        Some(webhook) => Box::new(webhook),
+        None => Box::new(LogNotifier),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve_once;
+
+    #[tokio::test]
+    async fn test_webhook_posts_stream_started() {
+        let (addr, request) = serve_once("{}").await;
+        let config = Config { webhook_url: Some(format!(" {}/hook ", addr)), ..Config::default() };
+        let notifier = WebhookNotifier::from_config(&config).unwrap().unwrap();
+
+        notifier.notify(StreamEvent::StreamStarted { room_id: 123456 }).await.unwrap();
+
+        let request = request.await.unwrap();
+        assert!(request.head.starts_with("POST /hook"));
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["event"], "stream_started");
+        assert_eq!(body["room_id"], 123456);
+    }
+}
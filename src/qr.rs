@@ -1,9 +1,20 @@
 use qrcode::{QrCode as QRCodeLib, Color};
 use image::{Rgb, RgbImage};
+use ratatui::style::{Color as TermColor, Style};
+use ratatui::text::Line;
 use serde::{Deserialize, Serialize};
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use crate::error::{Result, BiliError};
 
+/// SVG默认的模块大小（像素）和静默区宽度（模块数）
+const DEFAULT_MODULE_PX: u32 = 8;
+const DEFAULT_QUIET_ZONE: u32 = 4;
+
+/// TUI中二维码四周留白的模块数，太窄时部分手机无法识别
+const TUI_QUIET_ZONE: usize = 2;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QRCodeData {
     pub url: String,
@@ -12,6 +23,85 @@ pub struct QRCodeData {
 
 pub struct QRCode;
 
+/// 创建临时二维码图片时尝试的随机文件名数量
+const TEMP_FILE_ATTEMPTS: usize = 8;
+
+/// 保存在系统临时目录中的二维码图片，drop时删除
+#[derive(Debug)]
+pub struct QrImageFile {
+    path: PathBuf,
+}
+
+impl QrImageFile {
+    /// 把二维码保存为临时PNG文件
+    ///
+    /// 二维码可换取登录会话，文件名随机生成并以create_new新建（unix下权限0600），
+    /// 其他用户无法预先占用或链接到该路径。
+    pub fn create(data: &str) -> Result<Self> {
+        let image = QRCode::generate_image_with_size(data, 8, 4)?;
+        let (path, file) = create_unique_file(&std::env::temp_dir())
+            .map_err(|e| BiliError::QRCode(format!("创建二维码图片失败: {}", e)))?;
+        // 先持有实例，写入失败时也会删除已创建的文件
+        let qr_file = Self { path };
+        image.write_to(&mut io::BufWriter::new(file), image::ImageFormat::Png)
+            .map_err(|e| BiliError::QRCode(format!("保存二维码图片失败: {}", e)))?;
+        Ok(qr_file)
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for QrImageFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// 在dir下以随机文件名独占创建PNG文件
+fn create_unique_file(dir: &Path) -> io::Result<(PathBuf, std::fs::File)> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    
+    for _ in 0..TEMP_FILE_ATTEMPTS {
+        let path = dir.join(format!("bilibili_live_tool_qr_{:016x}.png", rand::random::<u64>()));
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::AlreadyExists, "无法生成唯一的临时文件名"))
+}
+
+/// 用系统默认程序打开文件的命令
+#[cfg(target_os = "windows")]
+fn viewer_command(path: &Path) -> Command {
+    let mut command = Command::new("cmd");
+    command.args(["/C", "start", ""]).arg(path);
+    command
+}
+
+#[cfg(target_os = "macos")]
+fn viewer_command(path: &Path) -> Command {
+    let mut command = Command::new("open");
+    command.arg(path);
+    command
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn viewer_command(path: &Path) -> Command {
+    let mut command = Command::new("xdg-open");
+    command.arg(path);
+    command
+}
+
 impl QRCode {
     /// 生成二维码ASCII字符串
     pub fn generate_ascii(data: &str) -> Result<String> {
@@ -38,6 +128,70 @@ impl QRCode {
         Ok(image)
     }
     
+    /// 按模块像素大小和静默区宽度（模块数）生成二维码图片
+    pub fn generate_image_with_size(data: &str, module_px: u32, quiet_zone: u32) -> Result<RgbImage> {
+        if module_px == 0 {
+            return Err(BiliError::QRCode("二维码模块大小必须大于0".to_string()));
+        }
+        let qr = QRCodeLib::new(data)
+            .map_err(|e| BiliError::QRCode(format!("生成二维码失败: {}", e)))?;
+        
+        let width = qr.width() as u32;
+        let side = (width + quiet_zone * 2) * module_px;
+        let mut image = RgbImage::from_pixel(side, side, Rgb([255, 255, 255]));
+        for y in 0..width {
+            for x in 0..width {
+                if qr[(x as usize, y as usize)] != Color::Dark {
+                    continue;
+                }
+                let left = (x + quiet_zone) * module_px;
+                let top = (y + quiet_zone) * module_px;
+                for py in top..top + module_px {
+                    for px in left..left + module_px {
+                        image.put_pixel(px, py, Rgb([0, 0, 0]));
+                    }
+                }
+            }
+        }
+        
+        Ok(image)
+    }
+    
+    /// 生成SVG格式的二维码
+    pub fn generate_svg(data: &str) -> Result<String> {
+        Self::generate_svg_with_size(data, DEFAULT_MODULE_PX, DEFAULT_QUIET_ZONE)
+    }
+    
+    /// 按模块像素大小和静默区宽度生成SVG，每个深色模块是一个单位方格
+    pub fn generate_svg_with_size(data: &str, module_px: u32, quiet_zone: u32) -> Result<String> {
+        if module_px == 0 {
+            return Err(BiliError::QRCode("二维码模块大小必须大于0".to_string()));
+        }
+        let qr = QRCodeLib::new(data)
+            .map_err(|e| BiliError::QRCode(format!("生成二维码失败: {}", e)))?;
+        
+        let width = qr.width();
+        let side = width as u32 + quiet_zone * 2;
+        let mut path = String::new();
+        for y in 0..width {
+            for x in 0..width {
+                if qr[(x, y)] == Color::Dark {
+                    path.push_str(&format!("M{} {}h1v1h-1z", x as u32 + quiet_zone, y as u32 + quiet_zone));
+                }
+            }
+        }
+        
+        Ok(format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{px}" height="{px}" viewBox="0 0 {side} {side}" shape-rendering="crispEdges">"#,
+                r##"<rect width="{side}" height="{side}" fill="#fff"/><path d="{path}" fill="#000"/></svg>"##,
+            ),
+            px = side * module_px,
+            side = side,
+            path = path,
+        ))
+    }
+    
     /// 在终端中打印二维码
     pub fn print_to_terminal(data: &str) -> Result<()> {
         let qr = QRCodeLib::new(data)
@@ -53,23 +207,29 @@ impl QRCode {
     }
     
     /// 使用Unicode字符打印更好看的二维码
+    ///
+    /// 行尾输出\r\n，终端处于raw模式时也能正常换行。
     pub fn print_unicode_to_terminal(data: &str) -> Result<()> {
+        let mut stdout = io::stdout();
+        for line in Self::render_unicode(data)? {
+            write!(stdout, "{}\r\n", line)?;
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+    
+    /// 把二维码渲染为带边框的Unicode字符行，每个字符表示上下两个模块
+    pub fn render_unicode(data: &str) -> Result<Vec<String>> {
         let qr = QRCodeLib::new(data)
             .map_err(|e| BiliError::QRCode(format!("生成二维码失败: {}", e)))?;
         
         let width = qr.width();
-        let mut stdout = io::stdout();
-        
-        // 上边框
-        print!("┌");
-        for _ in 0..width {
-            print!("─");
-        }
-        println!("┐");
+        let border = "─".repeat(width);
+        let mut lines = vec![format!("┌{}┐", border)];
         
         // 二维码内容 - 使用半格字符来调整比例，每个模块用一个字符
         for y in (0..width).step_by(2) {
-            print!("│");
+            let mut line = String::from("│");
             for x in 0..width {
                 let top_module = qr[(x, y)];
                 let bottom_module = if y + 1 < width {
@@ -78,26 +238,77 @@ impl QRCode {
                     Color::Light
                 };
                 
-                let char_to_print = match (top_module, bottom_module) {
-                    (Color::Light, Color::Light) => " ",
-                    (Color::Light, Color::Dark) => "▄",
-                    (Color::Dark, Color::Light) => "▀",
-                    (Color::Dark, Color::Dark) => "█",
-                };
-                print!("{}", char_to_print);
+                line.push(match (top_module, bottom_module) {
+                    (Color::Light, Color::Light) => ' ',
+                    (Color::Light, Color::Dark) => '▄',
+                    (Color::Dark, Color::Light) => '▀',
+                    (Color::Dark, Color::Dark) => '█',
+                });
             }
-            println!("│");
+            line.push('│');
+            lines.push(line);
         }
         
-        // 下边框
-        print!("└");
-        for _ in 0..width {
-            print!("─");
-        }
-        println!("┘");
+        lines.push(format!("└{}┘", border));
+        Ok(lines)
+    }
+    
+    /// 把二维码渲染为ratatui文本行，用于在TUI弹窗中显示
+    ///
+    /// 固定为白底黑码并带留白，不受终端配色影响；每行字符数即所需宽度。
+    pub fn render_to_lines(data: &str) -> Result<Vec<Line<'static>>> {
+        let qr = QRCodeLib::new(data)
+            .map_err(|e| BiliError::QRCode(format!("生成二维码失败: {}", e)))?;
         
-        stdout.flush()?;
-        Ok(())
+        let width = qr.width();
+        let side = width + TUI_QUIET_ZONE * 2;
+        let dark = |x: usize, y: usize| {
+            x >= TUI_QUIET_ZONE && y >= TUI_QUIET_ZONE
+                && x - TUI_QUIET_ZONE < width && y - TUI_QUIET_ZONE < width
+                && qr[(x - TUI_QUIET_ZONE, y - TUI_QUIET_ZONE)] == Color::Dark
+        };
+        let style = Style::default().fg(TermColor::Black).bg(TermColor::White);
+        
+        Ok((0..side).step_by(2)
+            .map(|y| {
+                let line: String = (0..side)
+                    .map(|x| match (dark(x, y), dark(x, y + 1)) {
+                        (false, false) => ' ',
+                        (false, true) => '▄',
+                        (true, false) => '▀',
+                        (true, true) => '█',
+                    })
+                    .collect();
+                Line::styled(line, style)
+            })
+            .collect())
+    }
+    
+    /// 按可用区域渲染二维码，放不下时返回提示放大窗口的文字
+    pub fn render_to_lines_within(data: &str, width: u16, height: u16) -> Result<Vec<Line<'static>>> {
+        let lines = Self::render_to_lines(data)?;
+        let needed_width = lines.first().map_or(0, Line::width);
+        if needed_width > width as usize || lines.len() > height as usize {
+            return Ok(vec![
+                Line::from("终端窗口太小，无法显示二维码"),
+                Line::from(format!("请放大到至少 {}×{} 后重试", needed_width, lines.len())),
+            ]);
+        }
+        Ok(lines)
+    }
+    
+    /// 把二维码保存为临时PNG并用系统默认图片查看器打开
+    ///
+    /// 返回的临时文件需要保留到扫码完成，drop时删除。
+    pub fn open_in_viewer(data: &str) -> Result<QrImageFile> {
+        let file = QrImageFile::create(data)?;
+        viewer_command(file.path())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| BiliError::QRCode(format!("打开图片查看器失败: {}", e)))?;
+        Ok(file)
     }
     
     /// 保存二维码图片到文件
@@ -108,6 +319,29 @@ impl QRCode {
         Ok(())
     }
     
+    /// 按自定义尺寸保存二维码，格式由路径后缀决定（.png或.svg）
+    pub fn save_image_with_size(data: &str, path: &str, module_px: u32, quiet_zone: u32) -> Result<()> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        
+        match extension.as_deref() {
+            Some("png") => {
+                Self::generate_image_with_size(data, module_px, quiet_zone)?
+                    .save(path)
+                    .map_err(|e| BiliError::QRCode(format!("保存二维码图片失败: {}", e)))
+            }
+            Some("svg") => {
+                let svg = Self::generate_svg_with_size(data, module_px, quiet_zone)?;
+                std::fs::write(path, svg)
+                    .map_err(|e| BiliError::QRCode(format!("保存二维码图片失败: {}", e)))
+            }
+            Some(ext) => Err(BiliError::QRCode(format!("不支持的二维码图片格式: .{}，请使用.png或.svg", ext))),
+            None => Err(BiliError::QRCode(format!("无法从路径判断二维码图片格式: {}，请使用.png或.svg后缀", path))),
+        }
+    }
+    
     /// 生成带边框的二维码ASCII字符串
     pub fn generate_ascii_with_border(data: &str) -> Result<String> {
         let qr = QRCodeLib::new(data)
@@ -117,32 +351,125 @@ impl QRCode {
         let mut result = String::new();
         
         // 上边框
-        result.push_str("┌");
+        result.push('┌');
         for _ in 0..width {
-            result.push_str("─");
+            result.push('─');
         }
         result.push_str("┐\n");
         
         // 二维码内容
         for y in 0..width {
-            result.push_str("│");
+            result.push('│');
             for x in 0..width {
                 let module = qr[(x, y)];
                 match module {
-                    Color::Light => result.push_str(" "),
-                    Color::Dark => result.push_str("█"),
+                    Color::Light => result.push(' '),
+                    Color::Dark => result.push('█'),
                 }
             }
             result.push_str("│\n");
         }
         
         // 下边框
-        result.push_str("└");
+        result.push('└');
         for _ in 0..width {
-            result.push_str("─");
+            result.push('─');
         }
         result.push_str("┘\n");
         
         Ok(result)
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = "https://passport.bilibili.com/h5-app/passport/login/scan?qrcode_key=abc";
+
+    #[test]
+    fn test_unicode_render_halves_height() {
+        let width = QRCodeLib::new(DATA).unwrap().width();
+        let lines = QRCode::render_unicode(DATA).unwrap();
+        assert_eq!(lines.len(), width.div_ceil(2) + 2);
+        assert!(lines.iter().all(|line| line.chars().count() == width + 2));
+    }
+
+    #[test]
+    fn test_render_to_lines_and_small_area() {
+        let side = QRCodeLib::new(DATA).unwrap().width() + TUI_QUIET_ZONE * 2;
+        let lines = QRCode::render_to_lines(DATA).unwrap();
+        assert_eq!(lines.len(), side.div_ceil(2));
+        assert!(lines.iter().all(|line| line.width() == side));
+        // 第一行是留白
+        assert!(lines[0].to_string().chars().all(|c| c == ' '));
+
+        let fitted = QRCode::render_to_lines_within(DATA, side as u16, lines.len() as u16).unwrap();
+        assert_eq!(fitted.len(), lines.len());
+        let hint = QRCode::render_to_lines_within(DATA, 20, 10).unwrap();
+        assert!(hint[0].to_string().contains("太小"));
+        assert!(hint[1].to_string().contains(&format!("{}×{}", side, lines.len())));
+    }
+
+    #[test]
+    fn test_image_size_follows_module_and_quiet_zone() {
+        let width = QRCodeLib::new(DATA).unwrap().width() as u32;
+        let image = QRCode::generate_image_with_size(DATA, 10, 2).unwrap();
+        assert_eq!(image.dimensions(), ((width + 4) * 10, (width + 4) * 10));
+
+        // 静默区为白色，定位图案左上角为黑色
+        assert_eq!(*image.get_pixel(19, 19), Rgb([255, 255, 255]));
+        assert_eq!(*image.get_pixel(20, 20), Rgb([0, 0, 0]));
+
+        assert!(QRCode::generate_image_with_size(DATA, 0, 2).is_err());
+    }
+
+    #[test]
+    fn test_generate_svg() {
+        let width = QRCodeLib::new(DATA).unwrap().width() as u32;
+        let svg = QRCode::generate_svg(DATA).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(&format!("viewBox=\"0 0 {0} {0}\"", width + 8)));
+        assert!(svg.contains("M4 4h1v1h-1z"));
+    }
+
+    #[test]
+    fn test_save_image_with_size_checks_extension() {
+        let dir = std::env::temp_dir().join(format!("bilibili_live_tool_qr_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let png = dir.join("qr.PNG");
+        QRCode::save_image_with_size(DATA, png.to_str().unwrap(), 4, 1).unwrap();
+        assert!(image::open(&png).is_ok());
+
+        let svg = dir.join("qr.svg");
+        QRCode::save_image_with_size(DATA, svg.to_str().unwrap(), 4, 1).unwrap();
+        assert!(std::fs::read_to_string(&svg).unwrap().starts_with("<svg"));
+
+        let err = QRCode::save_image_with_size(DATA, dir.join("qr.gif").to_str().unwrap(), 4, 1).unwrap_err();
+        assert!(err.to_string().contains(".gif"));
+        assert!(QRCode::save_image_with_size(DATA, dir.join("qr").to_str().unwrap(), 4, 1).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_temp_image_is_removed_on_drop() {
+        let file = QrImageFile::create(DATA).unwrap();
+        let path = file.path().to_path_buf();
+        assert!(path.starts_with(std::env::temp_dir()));
+        assert!(image::open(&path).is_ok());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        
+        // 每次创建的文件互不相同
+        let other = QrImageFile::create(DATA).unwrap();
+        assert_ne!(other.path(), path);
+
+        drop(file);
+        assert!(!path.exists());
+    }
+}
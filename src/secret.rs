@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use crate::error::{Result, BiliError};
+
+/// 加密口令所在的环境变量
+pub const PASSPHRASE_ENV: &str = "BILI_CONFIG_PASSPHRASE";
+
+/// 加密字段的前缀，格式为 `enc:v1:<salt>:<nonce>:<密文>`（均为hex）
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// 从环境变量读取加密口令，未设置或为空时返回None
+pub fn passphrase_from_env() -> Option<String> {
+    std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty())
+}
+
+/// 字段是否为加密格式
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// 基于口令的字段加解密
+///
+/// 密钥由argon2从口令派生，同一次保存的字段共用一个salt，避免重复派生。
+pub struct SecretBox {
+    passphrase: String,
+    salt: [u8; SALT_LEN],
+    keys: HashMap<[u8; SALT_LEN], Key>,
+}
+
+impl SecretBox {
+    pub fn new(passphrase: impl Into<String>) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self {
+            passphrase: passphrase.into(),
+            salt,
+            keys: HashMap::new(),
+        }
+    }
+
+    fn key(&mut self, salt: [u8; SALT_LEN]) -> Result<&Key> {
+        if !self.keys.contains_key(&salt) {
+            let mut key = [0u8; 32];
+            Argon2::default()
+                .hash_password_into(self.passphrase.as_bytes(), &salt, &mut key)
+                .map_err(|e| BiliError::general(format!("派生密钥失败: {}", e)))?;
+            self.keys.insert(salt, Key::from(key));
+        }
+        Ok(&self.keys[&salt])
+    }
+
+    /// 加密明文，返回带前缀的字符串
+    pub fn encrypt(&mut self, plain: &str) -> Result<String> {
+        let salt = self.salt;
+        let cipher = ChaCha20Poly1305::new(self.key(salt)?);
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let sealed = cipher.encrypt(&Nonce::from(nonce), plain.as_bytes())
+            .map_err(|_| BiliError::general("加密配置失败"))?;
+        Ok(format!("{}{}:{}:{}", ENCRYPTED_PREFIX, hex::encode(salt), hex::encode(nonce), hex::encode(sealed)))
+    }
+
+    /// 解密 [`SecretBox::encrypt`] 的结果，口令错误或内容损坏时返回错误
+    pub fn decrypt(&mut self, value: &str) -> Result<String> {
+        let invalid = || BiliError::general("加密字段格式错误");
+        let mut parts = value.strip_prefix(ENCRYPTED_PREFIX).ok_or_else(invalid)?.splitn(3, ':');
+        let mut next = || -> Result<Vec<u8>> {
+            hex::decode(parts.next().ok_or_else(invalid)?).map_err(|_| invalid())
+        };
+        let salt: [u8; SALT_LEN] = next()?.try_into().map_err(|_| invalid())?;
+        let nonce: [u8; NONCE_LEN] = next()?.try_into().map_err(|_| invalid())?;
+        let sealed = next()?;
+
+        let cipher = ChaCha20Poly1305::new(self.key(salt)?);
+        let plain = cipher.decrypt(&Nonce::from(nonce), sealed.as_slice())
+            .map_err(|_| BiliError::Auth("解密配置失败，口令错误或配置已损坏".to_string()))?;
+        String::from_utf8(plain).map_err(|_| invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_wrong_passphrase() {
+        let mut secrets = SecretBox::new("口令");
+        let sealed = secrets.encrypt("SESSDATA=abc; bili_jct=def").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("SESSDATA"));
+        assert_eq!(SecretBox::new("口令").decrypt(&sealed).unwrap(), "SESSDATA=abc; bili_jct=def");
+
+        assert!(SecretBox::new("别的口令").decrypt(&sealed).is_err());
+        assert!(secrets.decrypt("enc:v1:zz").is_err());
+    }
+}
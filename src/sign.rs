@@ -1,14 +1,55 @@
 use std::collections::HashMap;
+use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::NaiveDate;
 use md5::{Md5, Digest};
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use urlencoding::encode;
+use crate::client::{ApiResponse, BilibiliClient};
+use crate::error::{Result, BiliError};
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// 获取WBI密钥的接口，未登录时也会返回wbi_img
+pub const NAV_URL: &str = "https://api.bilibili.com/x/web-interface/nav";
+
+/// 进程内共享的WBI密钥缓存
+static WBI_KEY_CACHE: WbiKeyCache = WbiKeyCache::new();
+
+/// 按日期缓存的WBI密钥，密钥每天轮换，跨天后重新获取
+struct WbiKeyCache {
+    entry: Mutex<Option<(NaiveDate, (String, String))>>,
+}
+
+impl WbiKeyCache {
+    const fn new() -> Self {
+        Self { entry: Mutex::new(None) }
+    }
+
+    /// 取出当天缓存的密钥
+    fn get(&self, today: NaiveDate) -> Option<(String, String)> {
+        let entry = self.entry.lock().unwrap();
+        entry.as_ref()
+            .filter(|(date, _)| *date == today)
+            .map(|(_, keys)| keys.clone())
+    }
+
+    fn set(&self, today: NaiveDate, keys: (String, String)) {
+        *self.entry.lock().unwrap() = Some((today, keys));
+    }
+}
+
 pub struct Signer;
 
+/// 按服务端校验App签名时的规则编码参数值
+///
+/// 与官方App一致：字母数字和 `-_.~` 保持原样，空格编码为 `+`，其余字节编码为大写的 `%XX`。
+/// 标题中含空格时用 `%20` 计算的sign与服务端不一致，导致修改标题偶发签名失败。
+fn encode_form_value(value: &str) -> String {
+    encode(value).replace("%20", "+")
+}
+
 impl Signer {
     /// APP密钥和版本信息
     const APP_KEY: &'static str = "1d8b6e7d45233436";
@@ -33,10 +74,15 @@ impl Signer {
     }
 
     /// App签名 - 对请求数据进行签名
-    pub fn app_sign(mut data: HashMap<String, String>) -> HashMap<String, String> {
+    pub fn app_sign(data: HashMap<String, String>) -> HashMap<String, String> {
+        Self::app_sign_at(data, Self::current_timestamp())
+    }
+
+    /// 使用指定时间戳的App签名，便于用固定输入校验签名结果
+    fn app_sign_at(mut data: HashMap<String, String>, ts: u64) -> HashMap<String, String> {
         // 添加必要的字段
         data.insert("access_key".to_string(), "".to_string());
-        data.insert("ts".to_string(), Self::current_timestamp().to_string());
+        data.insert("ts".to_string(), ts.to_string());
         data.insert("build".to_string(), Self::LIVEHIME_BUILD.to_string());
         data.insert("version".to_string(), Self::LIVEHIME_VERSION.to_string());
         data.insert("appkey".to_string(), Self::APP_KEY.to_string());
@@ -52,7 +98,7 @@ impl Signer {
                 query_string.push('&');
             }
             let value = &data[*key];
-            query_string.push_str(&format!("{}={}", key, encode(value)));
+            query_string.push_str(&format!("{}={}", key, encode_form_value(value)));
         }
 
         // 计算签名
@@ -68,9 +114,9 @@ impl Signer {
     }
 
     /// WBI签名 - 基于img_key和sub_key的混合密钥签名
-    pub fn wbi_sign(mut params: HashMap<String, String>, img_key: &str, sub_key: &str) -> HashMap<String, String> {
+    pub fn wbi_sign(mut params: HashMap<String, String>, img_key: &str, sub_key: &str) -> Result<HashMap<String, String>> {
         // 获取混合密钥
-        let mixin_key = Self::get_mixin_key(img_key, sub_key);
+        let mixin_key = Self::get_mixin_key(img_key, sub_key)?;
         
         // 添加时间戳
         params.insert("wts".to_string(), Self::current_timestamp().to_string());
@@ -102,21 +148,75 @@ impl Signer {
         // 添加签名字段
         params.insert("w_rid".to_string(), w_rid);
         
-        params
+        Ok(params)
+    }
+
+    /// 从nav接口获取WBI的img_key和sub_key，当天内复用缓存结果
+    pub async fn fetch_wbi_keys(client: &BilibiliClient) -> Result<(String, String)> {
+        Self::fetch_wbi_keys_with(client, NAV_URL, &WBI_KEY_CACHE, chrono::Local::now().date_naive()).await
+    }
+
+    async fn fetch_wbi_keys_with(
+        client: &BilibiliClient,
+        url: &str,
+        cache: &WbiKeyCache,
+        today: NaiveDate,
+    ) -> Result<(String, String)> {
+        if let Some(keys) = cache.get(today) {
+            return Ok(keys);
+        }
+
+        // 未登录时nav返回-101但仍带有wbi_img，因此不按返回码判断
+        let response: ApiResponse<serde_json::Value> = client.get_raw(url).await?;
+        let keys = response.data.as_ref()
+            .and_then(Self::parse_wbi_keys)
+            .ok_or_else(|| BiliError::api_error(-1, "nav接口未返回WBI密钥"))?;
+
+        cache.set(today, keys.clone());
+        Ok(keys)
+    }
+
+    /// 从nav返回data中wbi_img的img_url/sub_url取文件名作为密钥
+    fn parse_wbi_keys(data: &serde_json::Value) -> Option<(String, String)> {
+        let key_of = |field: &str| {
+            data.pointer(&format!("/wbi_img/{}", field))
+                .and_then(|v| v.as_str())
+                .and_then(|url| url.rsplit('/').next())
+                .and_then(|file| file.split('.').next())
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+        };
+        Some((key_of("img_url")?, key_of("sub_url")?))
+    }
+
+    /// 把签名后的参数拼接为查询字符串
+    pub fn to_query(params: &HashMap<String, String>) -> String {
+        let mut pairs: Vec<_> = params.iter().collect();
+        pairs.sort();
+        pairs.iter()
+            .map(|(k, v)| format!("{}={}", k, encode(v)))
+            .collect::<Vec<_>>()
+            .join("&")
     }
 
     /// 获取混合密钥
-    fn get_mixin_key(img_key: &str, sub_key: &str) -> String {
-        let combined = format!("{}{}", img_key, sub_key);
-        let mut result = String::new();
+    fn get_mixin_key(img_key: &str, sub_key: &str) -> Result<String> {
+        let combined: Vec<char> = format!("{}{}", img_key, sub_key).chars().collect();
         
-        for &index in &Self::MIXIN_KEY_ENC_TAB {
-            if let Some(ch) = combined.chars().nth(index) {
-                result.push(ch);
-            }
+        // 混合密钥表的索引覆盖0..64，密钥过短会导致签名错误
+        if combined.len() < Self::MIXIN_KEY_ENC_TAB.len() {
+            return Err(BiliError::validation(format!(
+                "WBI密钥长度不足: 需要{}个字符，实际{}个",
+                Self::MIXIN_KEY_ENC_TAB.len(),
+                combined.len()
+            )));
         }
         
-        result.chars().take(32).collect()
+        Ok(Self::MIXIN_KEY_ENC_TAB
+            .iter()
+            .take(32)
+            .map(|&index| combined[index])
+            .collect())
     }
 
     /// HMAC-SHA256签名
@@ -152,12 +252,30 @@ mod tests {
         assert_eq!(signed.get("appkey"), Some(&Signer::APP_KEY.to_string()));
     }
 
+    #[test]
+    fn test_app_sign_known_vector() {
+        assert_eq!(encode_form_value("a b~(c)*"), "a+b~%28c%29%2A");
+
+        let params = HashMap::from([
+            ("room_id".to_string(), "123456".to_string()),
+            ("title".to_string(), "今晚 8点 开播~ (测试)*".to_string()),
+            ("platform".to_string(), "pc_link".to_string()),
+            ("csrf".to_string(), "abc".to_string()),
+        ]);
+        let signed = Signer::app_sign_at(params, 1_700_000_000);
+        assert_eq!(signed["sign"], "2c917a2a1a3988f6f187b098bdc58d95");
+    }
+
     #[test]
     fn test_wbi_sign() {
         let mut params = HashMap::new();
         params.insert("mid".to_string(), "123456".to_string());
         
-        let signed = Signer::wbi_sign(params, "img_key_example", "sub_key_example");
+        let signed = Signer::wbi_sign(
+            params,
+            "7cd084941338484aae1ad9425b84077c",
+            "4932caff0ff746eab6f01bf08b70ac45",
+        ).unwrap();
         
         assert!(signed.contains_key("w_rid"));
         assert!(signed.contains_key("wts"));
@@ -165,7 +283,58 @@ mod tests {
 
     #[test]
     fn test_mixin_key() {
-        let key = Signer::get_mixin_key("1234567890abcdef", "fedcba0987654321");
-        assert_eq!(key.len(), 32);
+        let key = Signer::get_mixin_key(
+            "7cd084941338484aae1ad9425b84077c",
+            "4932caff0ff746eab6f01bf08b70ac45",
+        ).unwrap();
+        assert_eq!(key, "ea1db124af3c7062474693fa704f4ff8");
+    }
+
+    #[test]
+    fn test_parse_wbi_keys() {
+        let value = serde_json::json!({
+            "code": -101,
+            "data": {
+                "wbi_img": {
+                    "img_url": "https://i0.hdslb.com/bfs/wbi/7cd084941338484aae1ad9425b84077c.png",
+                    "sub_url": "https://i0.hdslb.com/bfs/wbi/4932caff0ff746eab6f01bf08b70ac45.png"
+                }
+            }
+        });
+        assert_eq!(
+            Signer::parse_wbi_keys(&value["data"]),
+            Some(("7cd084941338484aae1ad9425b84077c".to_string(), "4932caff0ff746eab6f01bf08b70ac45".to_string()))
+        );
+        assert_eq!(Signer::parse_wbi_keys(&serde_json::json!({})), None);
+    }
+
+    #[tokio::test]
+    async fn test_wbi_keys_cached_per_day() {
+        let (url, served) = crate::test_support::serve_responses(vec![
+            (200, r#"{"code":-101,"message":"账号未登录","data":{"wbi_img":{"img_url":"https://i0.hdslb.com/bfs/wbi/aaa.png","sub_url":"https://i0.hdslb.com/bfs/wbi/bbb.png"}}}"#),
+            (200, r#"{"code":-101,"message":"账号未登录","data":{"wbi_img":{"img_url":"https://i0.hdslb.com/bfs/wbi/ccc.png","sub_url":"https://i0.hdslb.com/bfs/wbi/ddd.png"}}}"#),
+        ]).await;
+        let client = BilibiliClient::new(crate::client::DEFAULT_TIMEOUT_MS).unwrap();
+        let cache = WbiKeyCache::new();
+        let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        let first = Signer::fetch_wbi_keys_with(&client, &url, &cache, today).await.unwrap();
+        let again = Signer::fetch_wbi_keys_with(&client, &url, &cache, today).await.unwrap();
+        assert_eq!(first, ("aaa".to_string(), "bbb".to_string()));
+        assert_eq!(again, first);
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // 跨天后重新获取
+        let next = Signer::fetch_wbi_keys_with(&client, &url, &cache, today.succ_opt().unwrap()).await.unwrap();
+        assert_eq!(next, ("ccc".to_string(), "ddd".to_string()));
+    }
+
+    #[test]
+    fn test_mixin_key_too_short() {
+        let result = Signer::get_mixin_key("1234567890abcdef", "fedcba0987654321");
+        assert!(matches!(result, Err(BiliError::Validation(_))));
+        
+        let params = HashMap::from([("mid".to_string(), "123456".to_string())]);
+        assert!(Signer::wbi_sign(params, "short", "keys").is_err());
     }
 }
\ No newline at end of file
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+/// 收到的原始请求
+pub struct CapturedRequest {
+    pub head: String,
+    pub body: String,
+}
+
+impl CapturedRequest {
+    /// 获取请求头的值（名称不区分大小写）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim())
+    }
+}
+
+/// 分区列表接口中单个子分区的JSON
+pub fn area_json(id: u32, name: &str, parent_id: u32, parent: &str, pinyin: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": id.to_string(), "name": name, "parent_id": parent_id.to_string(), "parent_name": parent,
+        "act_id": "0", "hot_status": 0, "lock_status": "0", "pic": "", "area_type": 0, "pinyin": pinyin,
+    })
+}
+
+/// 启动只处理一次请求的HTTP服务，返回服务地址和捕获到的请求
+pub async fn serve_once(response_body: &'static str) -> (String, oneshot::Receiver<CapturedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let request = respond(&mut socket, 200, response_body).await;
+        let _ = tx.send(request);
+    });
+
+    (addr, rx)
+}
+
+/// 启动按顺序返回多个响应的HTTP服务，返回服务地址和已处理的请求数
+pub async fn serve_responses(responses: Vec<(u16, &'static str)>) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = format!("http://{}", listener.local_addr().unwrap());
+    let served = Arc::new(AtomicUsize::new(0));
+
+    let counter = served.clone();
+    tokio::spawn(async move {
+        for (status, body) in responses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            respond(&mut socket, status, body).await;
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    (addr, served)
+}
+
+/// 读取一个完整请求并写回响应
+async fn respond(socket: &mut TcpStream, status: u16, response_body: &str) -> CapturedRequest {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    // 读取请求头和按Content-Length读取请求体
+    let (head, body) = loop {
+        let n = socket.read(&mut chunk).await.unwrap();
+        buf.extend_from_slice(&chunk[..n]);
+        let text = String::from_utf8_lossy(&buf).to_string();
+        if let Some(pos) = text.find("\r\n\r\n") {
+            let head = text[..pos].to_string();
+            let length = head.lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            if buf.len() >= pos + 4 + length || n == 0 {
+                let body = String::from_utf8_lossy(&buf[pos + 4..]).to_string();
+                break (head, body);
+            }
+        }
+        if n == 0 {
+            break (text, String::new());
+        }
+    };
+
+    let response = format!(
+        "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        response_body.len(),
+        response_body
+    );
+    socket.write_all(response.as_bytes()).await.unwrap();
+    CapturedRequest { head, body }
+}
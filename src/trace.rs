@@ -0,0 +1,98 @@
+use std::io::Write;
+use crate::client::ApiResponse;
+use crate::config::Config;
+use crate::error::Result;
+
+/// 是否记录请求trace日志，由 `trace` feature 控制
+const ENABLED: bool = cfg!(feature = "trace");
+
+/// 写入trace日志前需要脱敏的参数名（不区分大小写）
+const SENSITIVE_KEYS: [&str; 7] = ["csrf", "csrf_token", "cookie", "sessdata", "bili_jct", "access_key", "refresh_token"];
+
+/// 脱敏后显示的值
+const REDACTED: &str = "***";
+
+fn is_sensitive(key: &str) -> bool {
+    SENSITIVE_KEYS.iter().any(|sensitive| key.eq_ignore_ascii_case(sensitive))
+}
+
+/// 把表单参数格式化为 `k=v&k=v`，敏感参数的值替换为 `***`
+pub fn redact_form<'a>(params: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    params.into_iter()
+        .map(|(key, value)| format!("{}={}", key, if is_sensitive(key) { REDACTED } else { value }))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// 脱敏JSON请求体顶层的敏感字段
+pub fn redact_json(value: &serde_json::Value) -> String {
+    let mut value = value.clone();
+    if let Some(object) = value.as_object_mut() {
+        for (key, field) in object.iter_mut() {
+            if is_sensitive(key) {
+                *field = REDACTED.into();
+            }
+        }
+    }
+    value.to_string()
+}
+
+/// 只保留URL的路径和脱敏后的查询参数
+pub fn redact_url(url: &str) -> String {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return url.split('?').next().unwrap_or_default().to_string();
+    };
+    let pairs: Vec<(String, String)> = parsed.query_pairs().into_owned().collect();
+    if pairs.is_empty() {
+        return parsed.path().to_string();
+    }
+    format!("{}?{}", parsed.path(), redact_form(pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))))
+}
+
+/// 格式化一条trace日志：时间、方法、接口、参数和响应code
+fn format_entry<T>(method: &str, url: &str, params: &str, result: &Result<ApiResponse<T>>) -> String {
+    let outcome = match result {
+        Ok(response) => format!("code={}", response.code),
+        Err(e) => match e.error_code() {
+            Some(code) => format!("code={} error={}", code, e),
+            None => format!("error={}", e),
+        },
+    };
+    let params = if params.is_empty() { String::new() } else { format!(" params={}", params) };
+    format!("{} {} {}{} -> {}", chrono::Utc::now().to_rfc3339(), method, redact_url(url), params, outcome)
+}
+
+/// 记录一次请求，未开启 `trace` feature 时直接返回，参数也不会被格式化
+pub fn record<T>(method: &str, url: &str, params: impl FnOnce() -> String, result: &Result<ApiResponse<T>>) {
+    if !ENABLED {
+        return;
+    }
+    let entry = format_entry(method, url, &params(), result);
+    // 日志写入失败不影响请求本身
+    if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open(Config::get_log_path()) {
+        let _ = writeln!(file, "{}", entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BiliError;
+
+    #[test]
+    fn test_trace_entry_redacts_secrets() {
+        assert_eq!(redact_form([("room_id", "1"), ("csrf", "abc"), ("CSRF_TOKEN", "abc")]), "room_id=1&csrf=***&CSRF_TOKEN=***");
+        assert_eq!(redact_url("https://api.live.bilibili.com/room/v1/Room/get_info?room_id=1&csrf=abc"), "/room/v1/Room/get_info?room_id=1&csrf=***");
+        assert_eq!(redact_url("https://api.live.bilibili.com/xlive/test"), "/xlive/test");
+        assert_eq!(redact_json(&serde_json::json!({"msg": "hi", "csrf": "abc"})), r#"{"csrf":"***","msg":"hi"}"#);
+
+        let ok: Result<ApiResponse<()>> = Ok(ApiResponse { code: 0, message: "0".to_string(), data: None, msg: None });
+        let entry = format_entry("POST", "https://api.live.bilibili.com/room/v1/Room/update", "csrf=***", &ok);
+        assert!(entry.ends_with("POST /room/v1/Room/update params=csrf=*** -> code=0"), "{}", entry);
+
+        let err: Result<ApiResponse<()>> = Err(BiliError::api_error(60009, "分区不存在"));
+        let entry = format_entry("GET", "https://api.live.bilibili.com/x?csrf=abc", "", &err);
+        assert!(entry.contains("GET /x?csrf=*** -> code=60009"), "{}", entry);
+        assert!(!entry.contains("abc"));
+    }
+}
@@ -1,7 +1,13 @@
-use std::io::{stdout, Stdout};
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::{self, stdout, Stdout};
+use std::path::Path;
+use std::time::{Duration, Instant};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -15,7 +21,87 @@ use ratatui::{
     },
     Frame, Terminal,
 };
-use crate::{Live, Config, auth::UserInfo, error::Result};
+use crate::{Live, Bullet, Config, QRCode, auth::UserInfo, error::Result};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use crate::auth::Auth;
+use crate::client::BilibiliClient;
+use crate::credential::{CredentialStore, Credentials, TomlStore};
+use crate::live::{self, FaceAuthStatus, HeartbeatHandle, LiveStats, StopOutcome, StreamKey};
+use crate::bullet::FailFastResults;
+use crate::danmaku::DanmakuMessage;
+use crate::notify::{Notifier, NotifyFuture, StreamEvent, WebhookNotifier};
+use crate::clipboard::{Clipboard, CopyTarget};
+use crate::config::STREAM_INFO_MAX_AGE_HOURS;
+
+/// 菜单栏宽度范围
+const MENU_MIN_WIDTH: u16 = 20;
+const MENU_MAX_WIDTH: u16 = 32;
+/// 终端宽度低于该值时菜单和信息面板改为上下排列
+const NARROW_WIDTH: u16 = 60;
+/// 上下排列时菜单栏的高度
+const STACKED_MENU_HEIGHT: u16 = 8;
+
+/// 没有输入时的tick间隔，只在有动画时触发重绘
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 消息弹窗的最小和最大尺寸（宽, 高）
+const MESSAGE_MIN_SIZE: (u16, u16) = (40, 7);
+const MESSAGE_MAX_SIZE: (u16, u16) = (80, 20);
+
+/// 连按两次Esc的判定间隔
+const DOUBLE_ESC_WINDOW: Duration = Duration::from_millis(500);
+
+/// 同一列表项上两次点击视为双击的间隔
+const DOUBLE_CLICK_WINDOW: Duration = Duration::from_millis(400);
+
+/// 后台拉取直播间实时数据的间隔
+const STATS_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 转发实时弹幕的缓冲条数，主循环每次都会取空
+const DANMAKU_CHANNEL_CAPACITY: usize = 64;
+
+/// 定时下播前在状态栏显示倒计时的时长
+const AUTO_STOP_COUNTDOWN: Duration = Duration::from_secs(5 * 60);
+
+/// 定时下播最多可设置的分钟数
+const AUTO_STOP_MAX_MINUTES: u64 = 24 * 60;
+
+/// 定时下播在菜单中的位置
+const AUTO_STOP_MENU_INDEX: usize = 5;
+
+/// 日志面板保留的最大条数，超出后丢弃最旧的
+const LOG_CAPACITY: usize = 200;
+
+/// 日志面板中的一条记录
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: chrono::DateTime<chrono::Local>,
+    pub level: MessageType,
+    pub text: String,
+}
+
+/// 本场直播统计到的峰值，从未轮询到时为None
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionPeaks {
+    pub online: Option<u64>,
+    pub watched: Option<u64>,
+}
+
+impl SessionPeaks {
+    /// 记录一次轮询结果，只有超过当前峰值时才更新
+    pub fn record(&mut self, online: Option<u64>, watched: Option<u64>) {
+        fn raise(peak: &mut Option<u64>, value: Option<u64>) {
+            if let Some(value) = value
+                && peak.is_none_or(|current| value > current)
+            {
+                *peak = Some(value);
+            }
+        }
+        raise(&mut self.online, online);
+        raise(&mut self.watched, watched);
+    }
+}
 
 #[derive(Clone)]
 pub struct AppState {
@@ -24,6 +110,14 @@ pub struct AppState {
     pub menu_items: Vec<String>,
     pub is_live: bool,
     pub show_area_search: bool,
+    pub show_area_id_input: bool,
+    pub area_id_input: String,
+    /// 定时下播的时间点，未设置时为None
+    pub auto_stop_at: Option<Instant>,
+    /// 开播后推流码生效的时间点，已生效时为None
+    pub stream_ready_at: Option<Instant>,
+    pub show_auto_stop_input: bool,
+    pub auto_stop_input: String,
     pub area_search_query: String,
     pub area_list: Vec<crate::live::AreaCategory>,
     pub filtered_areas: Vec<crate::live::AreaData>,
@@ -38,11 +132,40 @@ pub struct AppState {
     pub show_loading: bool,
     pub loading_message: String,
     pub stream_server: String,
-    pub stream_key: String,
+    pub stream_key: StreamKey,
+    /// 开播前的人脸认证状态，查询失败时为None
+    pub face_auth: Option<FaceAuthStatus>,
+    /// 开播接口要求人脸认证时的认证二维码链接，显示认证弹窗期间为Some
+    pub face_auth_qr: Option<String>,
+    /// 上次轮询人脸认证状态的时间
+    pub face_auth_polled_at: Option<Instant>,
+    /// 本场直播的在线/观看峰值
+    pub peaks: SessionPeaks,
+    /// 最近一次拉取到的实时数据，从未拉取成功时为None
+    pub live_stats: Option<LiveStats>,
+    /// 是否已有登录会话，未登录时首个菜单项为“登录”
+    pub logged_in: bool,
+    pub show_login_prompt: bool,
+    /// 已请求扫码登录，由主循环退出界面后处理
+    pub login_requested: bool,
     pub show_help: bool,
+    /// 所有提示消息的历史记录
+    pub logs: VecDeque<LogEntry>,
+    pub show_log: bool,
+    /// 是否显示实时弹幕面板
+    pub show_danmaku: bool,
+    /// 日志面板距最新一条向上滚动的条数，为0时停在底部
+    pub log_scroll: usize,
+    pub last_toggle: Option<Instant>,
+    pub last_esc: Option<Instant>,
+    /// 上一帧菜单和分区列表的位置，用于把鼠标坐标换算为列表项
+    pub menu_area: Rect,
+    pub area_list_area: Rect,
+    /// 上一次点击的时间和列表项，用于判定双击
+    pub last_click: Option<(Instant, usize)>,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageType {
     Info,
     Success,
@@ -58,6 +181,12 @@ impl Default for AppState {
             menu_items: Vec::new(),
             is_live: false,
             show_area_search: false,
+            show_area_id_input: false,
+            area_id_input: String::new(),
+            auto_stop_at: None,
+            stream_ready_at: None,
+            show_auto_stop_input: false,
+            auto_stop_input: String::new(),
             area_search_query: String::new(),
             area_list: Vec::new(),
             filtered_areas: Vec::new(),
@@ -72,8 +201,25 @@ impl Default for AppState {
             show_loading: false,
             loading_message: String::new(),
             stream_server: String::new(),
-            stream_key: String::new(),
+            stream_key: StreamKey::default(),
+            face_auth: None,
+            face_auth_qr: None,
+            face_auth_polled_at: None,
+            peaks: SessionPeaks::default(),
+            live_stats: None,
+            logged_in: false,
+            show_login_prompt: false,
+            login_requested: false,
             show_help: false,
+            logs: VecDeque::with_capacity(LOG_CAPACITY),
+            show_log: false,
+            show_danmaku: false,
+            log_scroll: 0,
+            last_toggle: None,
+            last_esc: None,
+            menu_area: Rect::default(),
+            area_list_area: Rect::default(),
+            last_click: None,
         };
         state.update_menu_items();
         state.menu_state.select(Some(0));
@@ -113,6 +259,7 @@ impl AppState {
     }
 
     pub fn show_message(&mut self, message: String, message_type: MessageType) {
+        self.push_log(message_type, message.clone());
         self.message = message;
         self.message_type = message_type;
         self.show_message = true;
@@ -122,6 +269,42 @@ impl AppState {
         self.show_message = false;
     }
 
+    /// 追加一条日志，已满时丢弃最旧的一条；向上翻看时保持当前视图不动
+    pub fn push_log(&mut self, level: MessageType, text: String) {
+        if self.logs.len() == LOG_CAPACITY {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(LogEntry { time: chrono::Local::now(), level, text });
+        if self.log_scroll > 0 {
+            self.log_scroll = (self.log_scroll + 1).min(self.logs.len() - 1);
+        }
+    }
+
+    /// 打开或关闭日志面板，打开时回到最新一条
+    pub fn toggle_log(&mut self) {
+        self.show_log = !self.show_log;
+        self.log_scroll = 0;
+    }
+
+    /// 打开或关闭弹幕面板
+    pub fn toggle_danmaku(&mut self) {
+        self.show_danmaku = !self.show_danmaku;
+    }
+
+    pub fn scroll_log_up(&mut self) {
+        self.log_scroll = (self.log_scroll + 1).min(self.logs.len().saturating_sub(1));
+    }
+
+    pub fn scroll_log_down(&mut self) {
+        self.log_scroll = self.log_scroll.saturating_sub(1);
+    }
+
+    /// 日志面板中可见的记录，height为面板能显示的行数
+    pub fn visible_logs(&self, height: usize) -> impl Iterator<Item = &LogEntry> {
+        let end = self.logs.len() - self.log_scroll;
+        self.logs.range(end.saturating_sub(height)..end)
+    }
+
     pub fn show_loading(&mut self, message: String) {
         self.loading_message = message;
         self.show_loading = true;
@@ -132,25 +315,8 @@ impl AppState {
     }
 
     pub fn filter_areas(&mut self, query: &str) {
-        self.filtered_areas.clear();
-        
-        if query.is_empty() {
-            // 如果查询为空，显示所有分区
-            for category in &self.area_list {
-                self.filtered_areas.extend(category.list.clone());
-            }
-        } else {
-            // 搜索分区
-            let query_lower = query.to_lowercase();
-            for category in &self.area_list {
-                for area in &category.list {
-                    if area.name.to_lowercase().contains(&query_lower) 
-                        || area.parent_name.to_lowercase().contains(&query_lower) {
-                        self.filtered_areas.push(area.clone());
-                    }
-                }
-            }
-        }
+        // 查询为空时显示所有分区
+        self.filtered_areas = live::search_areas(&self.area_list, query);
         
         // 重置选择
         self.area_state.select(if self.filtered_areas.is_empty() { None } else { Some(0) });
@@ -202,8 +368,11 @@ impl AppState {
         // 如果菜单为空，初始化菜单
         if self.menu_items.is_empty() {
             self.menu_items.push("开始直播".to_string());
+            self.menu_items.push("使用上次设置开播".to_string());
             self.menu_items.push("修改标题".to_string());
             self.menu_items.push("修改分区".to_string());
+            self.menu_items.push("输入分区ID".to_string());
+            self.menu_items.push("定时下播".to_string());
             self.menu_items.push("帮助".to_string());
             self.menu_items.push("退出程序".to_string());
             
@@ -212,24 +381,49 @@ impl AppState {
             self.menu_state.select(Some(0));
         }
         
-        // 根据直播状态更新第一个菜单项的文本
-        if self.is_live {
+        // 根据登录和直播状态更新第一个菜单项的文本
+        if !self.logged_in {
+            self.menu_items[0] = "登录".to_string();
+        } else if self.is_live {
             self.menu_items[0] = "结束直播".to_string();
         } else {
             self.menu_items[0] = "开始直播".to_string();
         }
+        
+        self.menu_items[AUTO_STOP_MENU_INDEX] = if self.auto_stop_at.is_some() {
+            "取消定时下播".to_string()
+        } else {
+            "定时下播".to_string()
+        };
     }
 
     /// 更新直播状态并更新菜单项文本
     pub fn set_live_status(&mut self, is_live: bool) {
         if self.is_live != is_live {
             self.is_live = is_live;
+            // 下播后定时和推流码倒计时随之失效
+            if !is_live {
+                self.auto_stop_at = None;
+                self.stream_ready_at = None;
+            }
             self.update_menu_items();
         }
     }
 
+    /// 更新登录状态并更新菜单项文本
+    pub fn set_logged_in(&mut self, logged_in: bool) {
+        self.logged_in = logged_in;
+        self.update_menu_items();
+    }
+
+    /// 请求扫码登录并关闭登录提示
+    pub fn request_login(&mut self) {
+        self.show_login_prompt = false;
+        self.login_requested = true;
+    }
+
     /// 设置推流信息
-    pub fn set_stream_info(&mut self, server: String, key: String) {
+    pub fn set_stream_info(&mut self, server: String, key: StreamKey) {
         self.stream_server = server;
         self.stream_key = key;
     }
@@ -237,7 +431,204 @@ impl AppState {
     /// 清空推流信息
     pub fn clear_stream_info(&mut self) {
         self.stream_server.clear();
-        self.stream_key.clear();
+        self.stream_key = StreamKey::default();
+    }
+
+    /// 尝试开始一次开播/下播操作，距上次操作不足冷却时间时返回false
+    pub fn try_begin_toggle(&mut self, cooldown: Duration) -> bool {
+        if let Some(last) = self.last_toggle
+            && last.elapsed() < cooldown
+        {
+            return false;
+        }
+        self.last_toggle = Some(Instant::now());
+        true
+    }
+
+    /// 更新实时数据，缺少的字段保留上一次的数值；直播中同时更新本场峰值
+    pub fn apply_stats(&mut self, stats: LiveStats) {
+        self.live_stats = Some(self.live_stats.unwrap_or_default().merge(stats));
+        if self.is_live {
+            self.peaks.record(stats.online, stats.watched);
+        }
+    }
+
+    /// 下播后的本场总结，未统计到的数据显示为“—”
+    pub fn session_summary(&self, duration: Option<chrono::Duration>) -> String {
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "—".to_string());
+        format!(
+            "直播已结束\n\n时长: {}\n最高在线: {}\n累计观看: {}\n标题: {}\n分区: {}",
+            or_dash(duration.map(format_duration)),
+            or_dash(self.peaks.online.map(|v| v.to_string())),
+            or_dash(self.peaks.watched.map(|v| v.to_string())),
+            self.current_title,
+            self.current_area,
+        )
+    }
+
+    /// 根据下播结果更新状态，返回是否已真正下播
+    pub fn apply_stop_outcome(&mut self, outcome: StopOutcome) -> bool {
+        match outcome {
+            StopOutcome::Stopped => {
+                self.set_live_status(false);
+                self.clear_stream_info();
+                true
+            }
+            StopOutcome::Pending => false,
+        }
+    }
+
+    /// 设置在after之后自动下播
+    pub fn set_auto_stop(&mut self, after: Duration, now: Instant) {
+        self.auto_stop_at = Some(now + after);
+        self.update_menu_items();
+    }
+
+    pub fn cancel_auto_stop(&mut self) {
+        self.auto_stop_at = None;
+        self.update_menu_items();
+    }
+
+    /// 距定时下播的剩余时间，只在最后几分钟内返回，用于状态栏倒计时
+    pub fn auto_stop_countdown(&self, now: Instant) -> Option<Duration> {
+        self.auto_stop_at
+            .map(|at| at.saturating_duration_since(now))
+            .filter(|remaining| *remaining <= AUTO_STOP_COUNTDOWN)
+    }
+
+    /// 到达定时下播时间时清除定时并返回true
+    pub fn take_auto_stop_due(&mut self, now: Instant) -> bool {
+        if self.auto_stop_at.is_some_and(|at| now >= at) {
+            self.cancel_auto_stop();
+            return true;
+        }
+        false
+    }
+
+    /// 推流码距生效的剩余时间，用于状态栏倒计时
+    pub fn stream_ready_countdown(&self, now: Instant) -> Option<Duration> {
+        self.stream_ready_at.map(|at| at.saturating_duration_since(now))
+    }
+
+    /// 推流码到达生效时间时清除倒计时并返回true
+    pub fn take_stream_ready_due(&mut self, now: Instant) -> bool {
+        if self.stream_ready_at.is_some_and(|at| now >= at) {
+            self.stream_ready_at = None;
+            return true;
+        }
+        false
+    }
+
+    /// 显示人脸认证二维码弹窗，并从现在开始计算轮询间隔
+    pub fn show_face_auth(&mut self, qr: String) {
+        self.face_auth = Some(FaceAuthStatus::Required);
+        self.face_auth_qr = Some(qr);
+        self.face_auth_polled_at = Some(Instant::now());
+    }
+
+    /// 关闭人脸认证弹窗，停止轮询
+    pub fn hide_face_auth(&mut self) {
+        self.face_auth_qr = None;
+        self.face_auth_polled_at = None;
+    }
+
+    /// 人脸认证弹窗打开且距上次轮询已超过间隔时返回true，并记录本次轮询时间
+    pub fn take_face_auth_poll_due(&mut self, now: Instant) -> bool {
+        if self.face_auth_qr.is_none()
+            || self.face_auth_polled_at.is_some_and(|at| now.duration_since(at) < live::FACE_AUTH_POLL_INTERVAL)
+        {
+            return false;
+        }
+        self.face_auth_polled_at = Some(now);
+        true
+    }
+
+    /// 是否有弹窗或输入框处于打开状态
+    pub fn has_overlay(&self) -> bool {
+        self.face_auth_qr.is_some()
+            || self.show_loading
+            || self.show_help
+            || self.show_log
+            || self.show_danmaku
+            || self.show_message
+            || self.show_title_input
+            || self.show_area_search
+            || self.show_area_id_input
+            || self.show_auto_stop_input
+            || self.show_login_prompt
+    }
+
+    /// 关闭所有弹窗和输入框并清空其缓冲区
+    pub fn dismiss_overlays(&mut self) {
+        self.show_loading = false;
+        self.loading_message.clear();
+        self.show_help = false;
+        self.show_log = false;
+        self.show_danmaku = false;
+        self.show_message = false;
+        self.message.clear();
+        self.show_title_input = false;
+        self.title_input.clear();
+        self.show_area_search = false;
+        self.area_search_query.clear();
+        self.show_area_id_input = false;
+        self.area_id_input.clear();
+        self.show_auto_stop_input = false;
+        self.auto_stop_input.clear();
+        self.show_login_prompt = false;
+        self.hide_face_auth();
+    }
+
+    /// 记录一次对列表项index的点击，短时间内在同一项上点击两次时返回true
+    pub fn register_click(&mut self, index: usize, now: Instant) -> bool {
+        let double = self.last_click
+            .is_some_and(|(at, last)| last == index && now.duration_since(at) < DOUBLE_CLICK_WINDOW);
+        self.last_click = if double { None } else { Some((now, index)) };
+        double
+    }
+
+    /// 选中菜单项，越界时忽略
+    pub fn select_menu(&mut self, index: usize) {
+        if index < self.menu_items.len() {
+            self.menu_state.select(Some(index));
+            self.selected_menu = index;
+        }
+    }
+
+    /// 选中分区列表项，越界时忽略
+    pub fn select_area(&mut self, index: usize) {
+        if index < self.filtered_areas.len() {
+            self.area_state.select(Some(index));
+        }
+    }
+
+    /// 记录一次Esc，短时间内连按两次时返回true
+    pub fn register_esc(&mut self) -> bool {
+        let now = Instant::now();
+        let double = self.last_esc
+            .is_some_and(|last| now.duration_since(last) < DOUBLE_ESC_WINDOW);
+        self.last_esc = if double { None } else { Some(now) };
+        double
+    }
+
+    /// 分区列表为空时才调用fetch加载，已有（如预取的）列表则直接复用
+    pub async fn ensure_area_list<F, Fut>(&mut self, fetch: F) -> Result<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<crate::live::AreaCategory>>>,
+    {
+        if self.area_list.is_empty() {
+            self.area_list = fetch().await?;
+        }
+        Ok(())
+    }
+
+    /// 界面上是否有需要持续刷新的动画
+    pub fn is_animating(&self) -> bool {
+        let now = Instant::now();
+        self.show_loading
+            || self.auto_stop_countdown(now).is_some()
+            || self.stream_ready_countdown(now).is_some()
     }
 
     /// 显示帮助
@@ -253,48 +644,322 @@ impl AppState {
 
 }
 
+/// 终端初始化/恢复操作，抽象出来便于替换
+pub trait TerminalOps {
+    fn enable_raw_mode(&mut self) -> io::Result<()>;
+    fn disable_raw_mode(&mut self) -> io::Result<()>;
+    fn enter_alternate_screen(&mut self) -> io::Result<()>;
+    fn leave_alternate_screen(&mut self) -> io::Result<()>;
+    fn enable_mouse_capture(&mut self) -> io::Result<()>;
+    fn disable_mouse_capture(&mut self) -> io::Result<()>;
+}
+
+/// 基于crossterm的终端操作
+pub struct CrosstermOps;
+
+impl TerminalOps for CrosstermOps {
+    fn enable_raw_mode(&mut self) -> io::Result<()> {
+        enable_raw_mode()
+    }
+
+    fn disable_raw_mode(&mut self) -> io::Result<()> {
+        disable_raw_mode()
+    }
+
+    fn enter_alternate_screen(&mut self) -> io::Result<()> {
+        execute!(stdout(), EnterAlternateScreen)
+    }
+
+    fn leave_alternate_screen(&mut self) -> io::Result<()> {
+        execute!(stdout(), LeaveAlternateScreen)
+    }
+
+    fn enable_mouse_capture(&mut self) -> io::Result<()> {
+        execute!(stdout(), EnableMouseCapture)
+    }
+
+    fn disable_mouse_capture(&mut self) -> io::Result<()> {
+        execute!(stdout(), DisableMouseCapture)
+    }
+}
+
+/// 进入TUI前设置终端
+pub fn setup_terminal(ops: &mut dyn TerminalOps, alternate_screen: bool) -> io::Result<()> {
+    ops.enable_raw_mode()?;
+    if alternate_screen {
+        ops.enter_alternate_screen()?;
+    }
+    ops.enable_mouse_capture()
+}
+
+/// 退出TUI时恢复终端，两种模式下都会关闭raw mode
+pub fn restore_terminal(ops: &mut dyn TerminalOps, alternate_screen: bool) -> io::Result<()> {
+    let mouse_result = ops.disable_mouse_capture();
+    let raw_result = ops.disable_raw_mode();
+    if alternate_screen {
+        ops.leave_alternate_screen()?;
+    }
+    mouse_result.and(raw_result)
+}
+
+/// 把鼠标坐标换算为带边框列表中的项下标，点在边框或空白处时返回None
+pub fn list_index_at(area: Rect, offset: usize, len: usize, column: u16, row: u16) -> Option<usize> {
+    let inner = Rect {
+        x: area.x.saturating_add(1),
+        y: area.y.saturating_add(1),
+        width: area.width.saturating_sub(2),
+        height: area.height.saturating_sub(2),
+    };
+    if column < inner.x || column >= inner.right() || row < inner.y || row >= inner.bottom() {
+        return None;
+    }
+    let index = offset + (row - inner.y) as usize;
+    (index < len).then_some(index)
+}
+
+/// 重绘调度：只在输入、窗口变化或动画tick时重绘，多次请求合并为一次
+#[derive(Debug)]
+pub struct RedrawScheduler {
+    pending: bool,
+}
+
+impl RedrawScheduler {
+    /// 首帧总是需要绘制
+    pub fn new() -> Self {
+        Self { pending: true }
+    }
+
+    pub fn request(&mut self) {
+        self.pending = true;
+    }
+
+    /// 空闲tick，只有存在动画时才需要重绘
+    pub fn on_tick(&mut self, animating: bool) {
+        if animating {
+            self.pending = true;
+        }
+    }
+
+    /// 取出并清除重绘请求
+    pub fn take(&mut self) -> bool {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// 格式化直播时长，如 `1小时05分`、`12分30秒`
+fn format_duration(duration: chrono::Duration) -> String {
+    let secs = duration.num_seconds().max(0);
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}小时{:02}分", hours, minutes)
+    } else {
+        format!("{}分{:02}秒", minutes, seconds)
+    }
+}
+
+impl Default for RedrawScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 弹幕消息缓冲区，超出容量时丢弃最旧的消息
+///
+/// 自动滚动时视图始终停在最新消息；冻结时视图停在冻结那一刻的位置，
+/// 之后到达的消息计入未读数，用于提示有新消息。
+#[derive(Debug)]
+pub struct DanmakuFeed {
+    messages: VecDeque<String>,
+    capacity: usize,
+    auto_scroll: bool,
+    unread: usize,
+}
+
+impl DanmakuFeed {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            messages: VecDeque::with_capacity(capacity),
+            capacity,
+            auto_scroll: true,
+            unread: 0,
+        }
+    }
+
+    /// 追加一条消息，缓冲区已满时丢弃最旧的一条
+    pub fn push(&mut self, message: String) {
+        if self.messages.len() == self.capacity {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message);
+        if !self.auto_scroll {
+            self.unread = (self.unread + 1).min(self.messages.len());
+        }
+    }
+
+    /// 切换自动滚动，恢复自动滚动时清空未读数，返回切换后的状态
+    pub fn toggle_auto_scroll(&mut self) -> bool {
+        self.auto_scroll = !self.auto_scroll;
+        if self.auto_scroll {
+            self.unread = 0;
+        }
+        self.auto_scroll
+    }
+
+    pub fn is_auto_scroll(&self) -> bool {
+        self.auto_scroll
+    }
+
+    /// 冻结期间新到达的消息数
+    pub fn unread(&self) -> usize {
+        self.unread
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// 当前视图中可见的消息，height为面板能显示的行数
+    pub fn visible(&self, height: usize) -> impl Iterator<Item = &String> {
+        // 冻结的位置已被丢弃时停在缓冲区开头
+        let end = match self.messages.len() - self.unread {
+            0 => height.min(self.messages.len()),
+            end => end,
+        };
+        let start = end.saturating_sub(height);
+        self.messages.range(start..end)
+    }
+}
+
+/// TUI中的默认通知，写入日志面板而不是输出到终端
+struct PanelNotifier(mpsc::UnboundedSender<(MessageType, String)>);
+
+impl Notifier for PanelNotifier {
+    fn notify(&self, event: StreamEvent) -> NotifyFuture<'_> {
+        Box::pin(async move {
+            let _ = self.0.send((MessageType::Info, format!("[通知] {}", event.describe())));
+            Ok(())
+        })
+    }
+}
+
 pub struct TuiApp {
     pub state: AppState,
     pub live: Option<Live>,
+    pub bullet: Option<Bullet>,
     pub config: Config,
     pub user_info: Option<UserInfo>,
+    pub credential_store: Box<dyn CredentialStore>,
+    pub notifier: Box<dyn Notifier>,
+    /// 复制推流信息用的剪贴板，X11下需要在运行期间保留
+    pub clipboard: Clipboard,
+    /// 弹幕消息缓冲区，容量取自配置
+    pub danmaku: DanmakuFeed,
+    /// 后台实时数据拉取任务的接收端
+    stats_rx: Option<mpsc::Receiver<LiveStats>>,
+    /// 后台发送开播弹幕的结果
+    auto_danmaku_rx: Option<mpsc::Receiver<FailFastResults>>,
+    /// 实时弹幕的接收端，连接在后台建立
+    danmaku_rx: Option<mpsc::Receiver<DanmakuMessage>>,
+    /// 直播期间的心跳任务
+    heartbeat: Option<HeartbeatHandle>,
+    /// 后台任务和客户端回调写入日志面板的消息，由主循环取出
+    log_tx: mpsc::UnboundedSender<(MessageType, String)>,
+    log_rx: mpsc::UnboundedReceiver<(MessageType, String)>,
 }
 
 impl TuiApp {
     pub fn new(config: Config) -> Self {
+        let (log_tx, log_rx) = mpsc::unbounded_channel();
+        let notifier: Box<dyn Notifier> = match WebhookNotifier::from_config(&config) {
+            Ok(Some(webhook)) => Box::new(webhook),
+            Ok(None) => Box::new(PanelNotifier(log_tx.clone())),
+            Err(e) => {
+                let _ = log_tx.send((MessageType::Error, e.context("创建通知").to_string()));
+                Box::new(PanelNotifier(log_tx.clone()))
+            }
+        };
         Self {
             state: AppState::default(),
             live: None,
+            bullet: None,
+            notifier,
+            danmaku: DanmakuFeed::new(config.danmaku_buffer),
             config,
             user_info: None,
+            credential_store: Box::new(TomlStore::new()),
+            clipboard: Clipboard::new(),
+            stats_rx: None,
+            heartbeat: None,
+            auto_danmaku_rx: None,
+            danmaku_rx: None,
+            log_tx,
+            log_rx,
         }
     }
 
     pub fn with_live(mut self, live: Live, user_info: UserInfo) -> Self {
+        self.attach_retry_log(live.get_client());
         self.live = Some(live);
         self.user_info = Some(user_info);
+        self.state.set_logged_in(true);
+        self
+    }
+
+    pub fn with_bullet(mut self, bullet: Bullet) -> Self {
+        self.attach_retry_log(bullet.get_client());
+        self.bullet = Some(bullet);
+        self
+    }
+
+    pub fn with_credential_store(mut self, store: Box<dyn CredentialStore>) -> Self {
+        self.credential_store = store;
+        self
+    }
+
+    pub fn with_notifier(mut self, notifier: Box<dyn Notifier>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// 使用预取的分区列表，首次修改分区时无需再请求
+    pub fn with_area_list(mut self, areas: Vec<crate::live::AreaCategory>) -> Self {
+        self.state.area_list = areas;
         self
     }
 
+    /// 把客户端的重试信息写入日志面板，直接打印会覆盖TUI界面
+    fn attach_retry_log(&self, client: &BilibiliClient) {
+        let tx = self.log_tx.clone();
+        client.set_retry_hook(move |attempt| {
+            let _ = tx.send((MessageType::Warning, attempt.to_string()));
+        });
+    }
+
     pub async fn run(mut self) -> Result<()> {
         // 设置终端
-        enable_raw_mode()?;
-        let mut stdout = stdout();
-        execute!(stdout, EnterAlternateScreen)?;
-        let backend = CrosstermBackend::new(stdout);
+        let alternate_screen = self.config.tui_alternate_screen;
+        let mut ops = CrosstermOps;
+        setup_terminal(&mut ops, alternate_screen)?;
+        let backend = CrosstermBackend::new(stdout());
         let mut terminal = Terminal::new(backend)?;
 
         // 初始化当前直播信息
+        self.apply_last_area().await;
         self.initialize_live_info().await;
+        self.warn_if_stream_info_stale();
+        self.warn_if_cannot_start().await;
+        self.start_stats_poller();
+        self.start_danmaku_feed();
 
         let result = self.run_app(&mut terminal).await;
 
         // 恢复终端
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen
-        )?;
+        restore_terminal(&mut ops, alternate_screen)?;
         terminal.show_cursor()?;
 
         result
@@ -305,46 +970,258 @@ impl TuiApp {
             // 更新直播状态
             if let Ok(is_live) = live.is_live().await {
                 self.state.set_live_status(is_live);
+                // 接管进行中的直播时继续上报心跳
+                self.heartbeat = is_live.then(|| live.start_heartbeat());
+                
+                // 上次开播中断时找回推流码，而不是重新开播
+                match Live::recover_pending_start(&self.config, is_live, || live.get_stream_addr()).await {
+                    Some(Ok((server, key))) => {
+                        if let Err(e) = self.config.save_stream_info(server, key) {
+                            eprintln!("保存推流信息失败: {}", e);
+                        }
+                    }
+                    Some(Err(e)) => eprintln!("恢复推流码失败: {}", e),
+                    None if self.config.has_pending_start() => {
+                        let _ = self.config.clear_pending_start();
+                    }
+                    None => {}
+                }
                 
-                // 如果正在直播，从配置文件加载推流信息
+                // 如果正在直播，从配置文件加载推流信息，没有保存时向B站查询当前推流码
                 if is_live {
                     if let Some((server, key)) = self.config.get_stream_info() {
                         self.state.set_stream_info(server, key);
+                    } else if let Ok(Some((server, key))) = live.get_active_stream_info().await {
+                        if let Err(e) = self.config.save_stream_info(server.clone(), key.clone()) {
+                            eprintln!("保存推流信息失败: {}", e);
+                        }
+                        self.state.set_stream_info(server, key);
                     }
                 }
             }
 
+            if let Ok(status) = live.face_auth_status().await {
+                self.state.face_auth = Some(status);
+            }
+
             // 更新标题
             if let Ok(title) = live.get_current_title().await {
                 self.state.current_title = title;
             }
 
             // 更新分区
-            if let Ok((_, area_name)) = live.get_current_area().await {
-                self.state.current_area = area_name;
+            if let Ok(area_path) = live.get_current_area_path().await {
+                self.state.current_area = area_path;
+            }
+        }
+    }
+
+    /// 按配置把分区恢复为上次使用的分区
+    async fn apply_last_area(&mut self) {
+        if !self.config.auto_apply_last_area {
+            return;
+        }
+        let Some(live) = &self.live else {
+            return;
+        };
+        
+        if let Err(e) = self.state.ensure_area_list(|| live.get_area_list()).await {
+            eprintln!("获取分区列表失败: {}", e);
+            return;
+        }
+        match live.apply_last_area(&self.config, &self.state.area_list).await {
+            Ok(Some((id, name))) => {
+                self.state.show_message(format!("已恢复上次使用的分区: {} (ID: {})", name, id), MessageType::Info);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("恢复上次使用的分区失败: {}", e),
+        }
+    }
+
+    /// 记录最近使用的分区，供下次启动时恢复
+    fn remember_area(&mut self, area_id: u32) {
+        let parent_id = live::find_area(&self.state.area_list, area_id).map(|area| area.parent_id);
+        if let Err(e) = self.config.remember_area(parent_id, area_id) {
+            eprintln!("保存分区设置失败: {}", e);
+        }
+    }
+
+    /// 恢复直播中的会话时，若保存的推流码过旧则提示重新获取
+    fn warn_if_stream_info_stale(&mut self) {
+        let max_age = chrono::Duration::hours(STREAM_INFO_MAX_AGE_HOURS);
+        if self.state.is_live && self.config.is_stream_info_stale(max_age) {
+            self.state.show_message(
+                format!("保存的推流码已超过{}小时，可能已失效，建议重新开播获取", STREAM_INFO_MAX_AGE_HOURS),
+                MessageType::Warning,
+            );
+        }
+    }
+
+    /// 未在直播且不满足开播条件时提前提示，避免开播时才报错
+    async fn warn_if_cannot_start(&mut self) {
+        if self.state.is_live || self.state.show_message {
+            return;
+        }
+        let Some(live) = &self.live else {
+            return;
+        };
+        match live.check_live_permission().await {
+            Ok(permission) if !permission.can_start() => {
+                self.state.show_message(
+                    format!("当前账号暂时无法开播:\n{}", permission.problems().join("\n")),
+                    MessageType::Warning,
+                );
             }
+            Ok(_) => {}
+            Err(e) => eprintln!("检查开播条件失败: {}", e),
         }
     }
 
     async fn run_app(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        let mut redraw = RedrawScheduler::new();
         loop {
-            terminal.draw(|f| self.ui(f))?;
+            if let Some(rx) = &mut self.stats_rx {
+                while let Ok(stats) = rx.try_recv() {
+                    self.state.apply_stats(stats);
+                    redraw.request();
+                }
+            }
 
-            if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    if key.kind == KeyEventKind::Press {
-                        if !self.handle_key(key.code).await? {
-                            break;
-                        }
+            while let Ok((level, text)) = self.log_rx.try_recv() {
+                self.state.push_log(level, text);
+                redraw.request();
+            }
+
+            if self.drain_danmaku() && self.state.show_danmaku {
+                redraw.request();
+            }
+
+            if !self.state.is_live {
+                self.heartbeat = None;
+            }
+            while let Some(alert) = self.heartbeat.as_mut().and_then(HeartbeatHandle::try_alert) {
+                if alert.reached_limit() {
+                    self.state.push_log(MessageType::Error, alert.to_string());
+                    if !self.state.has_overlay() {
+                        self.state.show_message(alert.to_string(), MessageType::Error);
+                    }
+                } else {
+                    self.state.push_log(MessageType::Warning, alert.to_string());
+                }
+                redraw.request();
+            }
+
+            if self.state.take_auto_stop_due(Instant::now()) {
+                self.state.dismiss_overlays();
+                self.handle_stop_live().await?;
+                if self.state.show_message {
+                    self.state.message = format!("⏰ 已到定时下播时间\n{}", self.state.message);
+                }
+                redraw.request();
+            }
+
+            if self.state.take_stream_ready_due(Instant::now()) {
+                self.state.push_log(MessageType::Success, "推流码已生效，可以开始推流".to_string());
+                redraw.request();
+            }
+
+            if self.state.take_face_auth_poll_due(Instant::now()) {
+                self.poll_face_auth().await?;
+                redraw.request();
+            }
+
+            // 等开播提示关闭后再显示，避免覆盖推流码
+            if !self.state.show_message
+                && let Some(rx) = &mut self.auto_danmaku_rx
+                && let Ok(results) = rx.try_recv()
+            {
+                let level = if results.all_sent() { MessageType::Info } else { MessageType::Warning };
+                self.state.show_message(format!("开播弹幕: {}", results.summary()), level);
+                self.auto_danmaku_rx = None;
+                redraw.request();
+            }
+
+            if redraw.take() {
+                terminal.draw(|f| self.ui(f))?;
+            }
+
+            if !event::poll(TICK_INTERVAL)? {
+                redraw.on_tick(self.state.is_animating());
+                continue;
+            }
+
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if !self.handle_key(key.code).await? {
+                        break;
+                    }
+                    if std::mem::take(&mut self.state.login_requested) {
+                        self.login(terminal).await?;
                     }
+                    redraw.request();
                 }
+                Event::Mouse(mouse) => {
+                    if !self.handle_mouse(mouse).await? {
+                        break;
+                    }
+                    if std::mem::take(&mut self.state.login_requested) {
+                        self.login(terminal).await?;
+                    }
+                    redraw.request();
+                }
+                Event::Resize(_, _) => redraw.request(),
+                _ => {}
             }
         }
         Ok(())
     }
 
-    async fn handle_key(&mut self, key: KeyCode) -> Result<bool> {
-        // 如果显示加载界面，忽略按键
+    /// 处理鼠标：滚轮移动选择，单击选中，双击等同于Enter；只作用于分区列表和没有弹窗时的菜单
+    async fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<bool> {
+        if self.state.show_loading {
+            return Ok(true);
+        }
+        let in_area_search = self.state.show_area_search;
+        if !in_area_search && self.state.has_overlay() {
+            return Ok(true);
+        }
+
+        match mouse.kind {
+            MouseEventKind::ScrollUp if in_area_search => self.state.previous_area(),
+            MouseEventKind::ScrollDown if in_area_search => self.state.next_area(),
+            MouseEventKind::ScrollUp => self.state.previous_menu(),
+            MouseEventKind::ScrollDown => self.state.next_menu(),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let index = if in_area_search {
+                    list_index_at(self.state.area_list_area, self.state.area_state.offset(), self.state.filtered_areas.len(), mouse.column, mouse.row)
+                } else {
+                    list_index_at(self.state.menu_area, self.state.menu_state.offset(), self.state.menu_items.len(), mouse.column, mouse.row)
+                };
+                let Some(index) = index else {
+                    return Ok(true);
+                };
+                if in_area_search {
+                    self.state.select_area(index);
+                } else {
+                    self.state.select_menu(index);
+                }
+                if self.state.register_click(index, Instant::now()) {
+                    return self.handle_key(KeyCode::Enter).await;
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    async fn handle_key(&mut self, key: KeyCode) -> Result<bool> {
+        // 连按两次Esc强制关闭所有弹窗，防止界面卡在某个状态
+        if key == KeyCode::Esc && self.state.has_overlay() && self.state.register_esc() {
+            self.state.dismiss_overlays();
+            return Ok(true);
+        }
+
+        // 如果显示加载界面，忽略按键
         if self.state.show_loading {
             return Ok(true);
         }
@@ -366,6 +1243,47 @@ impl TuiApp {
             return Ok(true);
         }
 
+        // 处理人脸认证弹窗，认证完成前只能取消
+        if self.state.face_auth_qr.is_some() {
+            if matches!(key, KeyCode::Esc | KeyCode::Char('q')) {
+                self.state.hide_face_auth();
+            }
+            return Ok(true);
+        }
+
+        // 处理日志面板
+        if self.state.show_log {
+            match key {
+                KeyCode::Up => self.state.scroll_log_up(),
+                KeyCode::Down => self.state.scroll_log_down(),
+                KeyCode::Esc | KeyCode::Char('l') | KeyCode::Char('q') => self.state.toggle_log(),
+                _ => {}
+            }
+            return Ok(true);
+        }
+
+        // 处理弹幕面板
+        if self.state.show_danmaku {
+            match key {
+                KeyCode::Char('f') | KeyCode::Char(' ') => {
+                    self.danmaku.toggle_auto_scroll();
+                }
+                KeyCode::Esc | KeyCode::Char('d') | KeyCode::Char('q') => self.state.toggle_danmaku(),
+                _ => {}
+            }
+            return Ok(true);
+        }
+
+        // 处理登录提示
+        if self.state.show_login_prompt {
+            match key {
+                KeyCode::Enter => self.state.request_login(),
+                KeyCode::Esc | KeyCode::Char('q') => self.state.show_login_prompt = false,
+                _ => {}
+            }
+            return Ok(true);
+        }
+
         // 处理标题输入
         if self.state.show_title_input {
             match key {
@@ -391,11 +1309,79 @@ impl TuiApp {
             return Ok(true);
         }
 
+        // 处理分区ID输入
+        if self.state.show_area_id_input {
+            match key {
+                KeyCode::Enter => {
+                    match self.state.area_id_input.trim().parse::<u32>() {
+                        Ok(area_id) if area_id != 0 => {
+                            self.state.show_area_id_input = false;
+                            self.state.area_id_input.clear();
+                            self.set_area_raw(area_id).await?;
+                        }
+                        _ => {
+                            self.state.show_message("请输入有效的分区ID".to_string(), MessageType::Warning);
+                        }
+                    }
+                }
+                KeyCode::Esc => {
+                    self.state.show_area_id_input = false;
+                    self.state.area_id_input.clear();
+                }
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    self.state.area_id_input.push(c);
+                }
+                KeyCode::Backspace => {
+                    self.state.area_id_input.pop();
+                }
+                _ => {}
+            }
+            return Ok(true);
+        }
+
+        // 处理定时下播输入
+        if self.state.show_auto_stop_input {
+            match key {
+                KeyCode::Enter => {
+                    match self.state.auto_stop_input.trim().parse::<u64>() {
+                        Ok(minutes) if (1..=AUTO_STOP_MAX_MINUTES).contains(&minutes) => {
+                            self.state.show_auto_stop_input = false;
+                            self.state.auto_stop_input.clear();
+                            self.state.set_auto_stop(Duration::from_secs(minutes * 60), Instant::now());
+                            self.state.show_message(format!("将在{}分钟后自动下播", minutes), MessageType::Success);
+                        }
+                        _ => {
+                            self.state.show_message(
+                                format!("请输入1到{}之间的分钟数", AUTO_STOP_MAX_MINUTES),
+                                MessageType::Warning,
+                            );
+                        }
+                    }
+                }
+                KeyCode::Esc => {
+                    self.state.show_auto_stop_input = false;
+                    self.state.auto_stop_input.clear();
+                }
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    self.state.auto_stop_input.push(c);
+                }
+                KeyCode::Backspace => {
+                    self.state.auto_stop_input.pop();
+                }
+                _ => {}
+            }
+            return Ok(true);
+        }
+
         // 处理分区搜索
         if self.state.show_area_search {
             match key {
                 KeyCode::Enter => {
                     if let Some(area) = self.state.get_selected_area() {
+                        if area.is_locked() {
+                            self.state.show_message("该分区已被锁定，暂时无法使用".to_string(), MessageType::Warning);
+                            return Ok(true);
+                        }
                         let area_id = area.id;
                         self.set_area(area_id).await?;
                         self.state.show_area_search = false;
@@ -432,12 +1418,23 @@ impl TuiApp {
             KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
             KeyCode::Up => self.state.previous_menu(),
             KeyCode::Down => self.state.next_menu(),
+            KeyCode::Char('c') => self.copy_stream_info(),
+            KeyCode::Char('l') => self.state.toggle_log(),
+            KeyCode::Char('d') => self.state.toggle_danmaku(),
             KeyCode::Enter => {
                 if let Some(menu_item) = self.state.menu_items.get(self.state.selected_menu) {
                     match menu_item.as_str() {
+                        "登录" => self.state.request_login(),
                         "开始直播" => self.handle_start_live().await?,
+                        "使用上次设置开播" => self.handle_start_with_last_settings().await?,
                         "修改标题" => self.handle_modify_title().await?,
                         "修改分区" => self.handle_modify_area().await?,
+                        "输入分区ID" => self.handle_area_id_input().await?,
+                        "定时下播" => self.handle_auto_stop_input(),
+                        "取消定时下播" => {
+                            self.state.cancel_auto_stop();
+                            self.state.show_message("已取消定时下播".to_string(), MessageType::Info);
+                        }
                         "结束直播" => self.handle_stop_live().await?,
                         "帮助" => self.handle_help().await?,
                         "退出程序" => return Ok(false),
@@ -452,46 +1449,329 @@ impl TuiApp {
         Ok(true)
     }
 
+    /// 启动后台实时数据拉取，替换掉之前的任务
+    fn start_stats_poller(&mut self) {
+        self.stats_rx = self.live.as_ref().map(|live| live.spawn_stats_poller(STATS_POLL_INTERVAL));
+    }
+
+    /// 在后台连接弹幕服务器并转发消息，替换掉之前的连接；连接失败写入日志面板
+    fn start_danmaku_feed(&mut self) {
+        let Some(bullet) = self.bullet.clone() else {
+            return;
+        };
+        let log_tx = self.log_tx.clone();
+        let (tx, rx) = mpsc::channel(DANMAKU_CHANNEL_CAPACITY);
+        tokio::spawn(async move {
+            match bullet.connect_danmaku().await {
+                Ok(mut messages) => {
+                    while let Some(message) = messages.recv().await {
+                        if tx.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                    let _ = log_tx.send((MessageType::Warning, "弹幕服务器连接已断开".to_string()));
+                }
+                Err(e) => {
+                    let _ = log_tx.send((MessageType::Warning, e.context("连接弹幕服务器").to_string()));
+                }
+            }
+        });
+        self.danmaku_rx = Some(rx);
+    }
+
+    /// 把已收到的弹幕放入缓冲区，人气值不进入弹幕列表；返回是否有新消息
+    fn drain_danmaku(&mut self) -> bool {
+        let Some(rx) = &mut self.danmaku_rx else {
+            return false;
+        };
+        let mut received = false;
+        while let Ok(message) = rx.try_recv() {
+            if !matches!(message, DanmakuMessage::Popularity(_)) {
+                self.danmaku.push(message.to_string());
+                received = true;
+            }
+        }
+        received
+    }
+
+    /// 查询一次人脸认证状态，认证完成后关闭弹窗并重新开播
+    async fn poll_face_auth(&mut self) -> Result<()> {
+        let Some(live) = &self.live else {
+            return Ok(());
+        };
+        match live.face_auth_status().await {
+            Ok(FaceAuthStatus::Required) => {}
+            Ok(status) => {
+                self.state.face_auth = Some(status);
+                self.state.hide_face_auth();
+                self.state.push_log(MessageType::Info, "人脸认证已完成，重新开播".to_string());
+                // 上次开播请求并未生效，不计入开关播冷却
+                self.state.last_toggle = None;
+                self.handle_start_live().await?;
+            }
+            Err(e) => eprintln!("查询人脸认证状态失败: {}", e),
+        }
+        Ok(())
+    }
+
+    /// 在后台按配置发送开播弹幕，结果由主循环显示
+    fn start_auto_danmaku(&mut self) {
+        if self.config.auto_danmaku.is_empty() {
+            return;
+        }
+        let Some(bullet) = self.bullet.clone() else {
+            return;
+        };
+        
+        let messages = self.config.auto_danmaku.clone();
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let results = bullet.send_bullets_adaptive(messages.iter().map(String::as_str).collect()).await;
+            let _ = tx.send(results).await;
+        });
+        self.auto_danmaku_rx = Some(rx);
+    }
+
+    /// 把推流服务器和推流码拼接后复制到剪贴板
+    fn copy_stream_info(&mut self) {
+        if !self.state.is_live || self.state.stream_server.is_empty() {
+            self.state.show_message("当前没有可复制的推流信息".to_string(), MessageType::Warning);
+            return;
+        }
+
+        let text = format!("{}{}", self.state.stream_server, self.state.stream_key.reveal());
+        let config_path = self.config.config_path();
+        let fallback_dir = config_path.parent().unwrap_or(Path::new("."));
+        match self.clipboard.copy(&text, fallback_dir) {
+            Ok(CopyTarget::Clipboard) => {
+                self.state.show_message("已复制到剪贴板".to_string(), MessageType::Success);
+            }
+            Ok(CopyTarget::File(path)) => {
+                self.state.show_message(
+                    format!("剪贴板不可用，推流信息已写入:\n{}", path.display()),
+                    MessageType::Warning,
+                );
+            }
+            Err(e) => self.state.show_message(e.context("复制").to_string(), MessageType::Error),
+        }
+    }
+
+    /// 没有登录会话时弹出登录提示，返回是否需要登录
+    fn require_login(&mut self) -> bool {
+        if self.live.is_some() {
+            return false;
+        }
+        self.state.show_login_prompt = true;
+        true
+    }
+
+    /// 暂时离开TUI在终端中扫码登录，完成后恢复界面
+    async fn login(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        let alternate_screen = self.config.tui_alternate_screen;
+        let mut ops = CrosstermOps;
+        restore_terminal(&mut ops, alternate_screen)?;
+        let result = self.qr_login().await;
+        setup_terminal(&mut ops, alternate_screen)?;
+        terminal.clear()?;
+
+        let result = match result {
+            Ok(user_info) => self.apply_login(user_info).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {
+                self.apply_last_area().await;
+                self.initialize_live_info().await;
+                self.start_stats_poller();
+                self.start_danmaku_feed();
+                self.state.show_message("登录成功".to_string(), MessageType::Success);
+            }
+            Err(e) => self.state.show_message(e.context("登录").to_string(), MessageType::Error),
+        }
+        Ok(())
+    }
+
+    /// 显示二维码并等待扫码，Ctrl+C取消
+    async fn qr_login(&self) -> Result<UserInfo> {
+        let auth = Auth::with_client(BilibiliClient::from_config(&self.config)?)
+            .with_qr_refresh_limit(self.config.qr_refresh_limit)
+            .with_qr_viewer(self.config.qr_open_viewer);
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        let watcher = tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                token.cancel();
+            }
+        });
+        let result = auth.qr_login_with_cancel(&cancel, || println!("按Ctrl+C取消登录")).await;
+        watcher.abort();
+        result
+    }
+
+    /// 用新的登录信息创建直播和弹幕客户端并保存认证信息
+    ///
+    /// 配置了managed_room_id时操作指定的直播间，而不是登录账号的直播间。
+    async fn apply_login(&mut self, user_info: UserInfo) -> Result<()> {
+        let client = BilibiliClient::from_config(&self.config)?
+            .with_retry_config(self.config.retry_count, self.config.retry_delay);
+        client.add_cookies(&user_info.cookies);
+        self.attach_retry_log(&client);
+        let (room_id, warning) = live::resolve_target_room(&client, &self.config.endpoints, self.config.managed_room_id, user_info.room_id).await;
+        if let Some(warning) = warning {
+            self.state.push_log(MessageType::Warning, warning);
+        }
+
+        let creds = Credentials {
+            room_id: user_info.room_id.to_string(),
+            cookie_str: Auth::cookies_to_string(&user_info.cookies),
+            csrf: user_info.csrf.clone(),
+            refresh_token: user_info.refresh_token.clone(),
+        };
+        self.config.set_credentials(creds.room_id.clone(), creds.cookie_str.clone(), creds.csrf.clone());
+        self.config.refresh_token = creds.refresh_token.clone();
+        if let Err(e) = self.credential_store.save(&creds) {
+            eprintln!("保存认证信息失败: {}", e);
+        }
+
+        self.live = Some(Live::with_client(client.clone(), room_id, user_info.csrf.clone())
+            .with_auto_truncate_title(self.config.auto_truncate_title)
+            .with_signing_disabled(self.config.disable_signing)
+            .with_stream_protocol(self.config.stream_protocol.clone())
+            .with_endpoints(&self.config.endpoints));
+        self.bullet = Some(Bullet::with_client(client, room_id, user_info.csrf.clone())
+            .with_sensitive_words(self.config.sensitive_words.clone()));
+        self.user_info = Some(user_info);
+        self.state.set_logged_in(true);
+        Ok(())
+    }
+
     async fn handle_start_live(&mut self) -> Result<()> {
+        if self.require_login() {
+            return Ok(());
+        }
         if self.state.is_live {
             self.state.show_message("已经在直播中".to_string(), MessageType::Warning);
             return Ok(());
         }
 
+        // 需要人脸认证时先重新查询，用户可能已在App中完成认证
+        if self.state.face_auth == Some(FaceAuthStatus::Required)
+            && let Some(live) = &self.live
+            && let Ok(status) = live.face_auth_status().await
+        {
+            self.state.face_auth = Some(status);
+        }
+        if self.state.face_auth == Some(FaceAuthStatus::Required) {
+            self.state.show_message(
+                "开播前需要完成人脸认证\n请在B站App中打开直播中心完成认证后再开播".to_string(),
+                MessageType::Warning,
+            );
+            return Ok(());
+        }
+
+        if !self.state.try_begin_toggle(self.config.toggle_cooldown()) {
+            self.state.show_message("操作过于频繁，请稍后再试".to_string(), MessageType::Warning);
+            return Ok(());
+        }
+
         if let Some(live) = &self.live {
             self.state.show_loading("正在开始直播...".to_string());
             
             // 获取当前分区ID
             let (area_id, _) = live.get_current_area().await.unwrap_or((0, "未知".to_string()));
             
+            // 先写入待确认标记，推流码保存后清除
+            if let Err(e) = self.config.mark_pending_start() {
+                eprintln!("写入开播标记失败: {}", e);
+            }
+            
             match live.start_live(area_id).await {
-                Ok(stream_data) => {
-                    let (rtmp_url, stream_key) = live.parse_stream_info(&stream_data);
+                Ok((stream_data, _diagnostics)) => {
+                    let (rtmp_url, stream_key) = live.preferred_stream_info(&stream_data);
                     
-                    // 更新状态
+                    // 更新状态，新的一场直播重新统计峰值
                     self.state.set_live_status(true);
                     self.state.set_stream_info(rtmp_url.clone(), stream_key.clone());
+                    self.state.peaks = SessionPeaks::default();
+                    // 推流码尚未生效时在状态栏倒计时，不阻塞界面
+                    self.state.stream_ready_at = stream_data.ready_delay().map(|delay| Instant::now() + delay);
                     
                     // 保存推流信息到配置文件
                     if let Err(e) = self.config.save_stream_info(rtmp_url.clone(), stream_key.clone()) {
                         eprintln!("保存推流信息失败: {}", e);
                     }
+                    if let Err(e) = live.remember_settings(&mut self.config).await {
+                        eprintln!("保存本次直播设置失败: {}", e);
+                    }
                     
                     self.state.hide_loading();
                     
-                    let message = format!("直播已开启！\n推流地址: {}\n推流码: {}", rtmp_url, stream_key);
+                    if let Err(e) = self.notifier.notify(StreamEvent::StreamStarted { room_id: live.get_room_id() }).await {
+                        self.state.push_log(MessageType::Warning, e.to_string());
+                    }
+
+                    let mut message = match self.config.stream_key_visibility.display(&stream_key) {
+                        Some(key) => format!("直播已开启！\n推流地址: {}\n推流码: {}", rtmp_url, key),
+                        None => format!("直播已开启！\n推流地址: {}", rtmp_url),
+                    };
+                    if let Some(delay) = stream_data.ready_delay() {
+                        message.push_str(&format!("\n推流码约{}秒后生效", delay.as_secs_f64().ceil() as u64));
+                    }
                     self.state.show_message(message, MessageType::Success);
+                    self.heartbeat = Some(live.start_heartbeat());
+                    self.start_auto_danmaku();
                 }
                 Err(e) => {
-                    self.state.hide_loading();
-                    self.state.show_message(format!("开启直播失败: {}", e), MessageType::Error);
+                    self.state.dismiss_overlays();
+                    match e.face_auth_qr() {
+                        Some(qr) => self.state.show_face_auth(qr.to_string()),
+                        None => self.state.show_message(e.context("开启直播").to_string(), MessageType::Error),
+                    }
                 }
             }
         }
         Ok(())
     }
 
+    /// 恢复上次直播的标题和分区后开播
+    async fn handle_start_with_last_settings(&mut self) -> Result<()> {
+        if self.require_login() {
+            return Ok(());
+        }
+        if self.state.is_live {
+            self.state.show_message("已经在直播中".to_string(), MessageType::Warning);
+            return Ok(());
+        }
+        let Some(settings) = self.config.last_live_settings().cloned() else {
+            self.state.show_message("没有上次直播设置的历史记录\n请先手动设置标题和分区开播一次".to_string(), MessageType::Warning);
+            return Ok(());
+        };
+        let Some(live) = &self.live else {
+            return Ok(());
+        };
+
+        self.state.show_loading("正在恢复上次直播设置...".to_string());
+        if let Err(e) = live.apply_last_settings(&settings).await {
+            self.state.hide_loading();
+            self.state.show_message(e.context("恢复上次直播设置").to_string(), MessageType::Error);
+            return Ok(());
+        }
+        if !settings.live_title.is_empty() {
+            self.state.current_title = settings.live_title.clone();
+        }
+        if let Ok(area_path) = live.get_current_area_path().await {
+            self.state.current_area = area_path;
+        }
+        self.state.hide_loading();
+        self.state.push_log(MessageType::Info, format!("已恢复上次直播设置: {} | {}", self.state.current_title, self.state.current_area));
+
+        self.handle_start_live().await
+    }
+
     async fn handle_modify_title(&mut self) -> Result<()> {
+        if self.require_login() {
+            return Ok(());
+        }
         if self.live.is_some() {
             self.state.title_input = self.state.current_title.clone();
             self.state.show_title_input = true;
@@ -500,57 +1780,90 @@ impl TuiApp {
     }
 
     async fn handle_modify_area(&mut self) -> Result<()> {
+        if self.require_login() {
+            return Ok(());
+        }
         if let Some(live) = &self.live {
             if self.state.area_list.is_empty() {
                 self.state.show_loading("正在加载分区列表...".to_string());
-                
-                match live.get_area_list().await {
-                    Ok(areas) => {
-                        self.state.area_list = areas;
-                        self.state.filter_areas(""); // 显示所有分区
-                        self.state.hide_loading();
-                        self.state.show_area_search = true;
-                    }
-                    Err(e) => {
-                        self.state.hide_loading();
-                        self.state.show_message(format!("加载分区列表失败: {}", e), MessageType::Error);
-                    }
+            }
+
+            match self.state.ensure_area_list(|| live.get_area_list()).await {
+                Ok(()) => {
+                    self.state.filter_areas(""); // 显示所有分区
+                    self.state.hide_loading();
+                    self.state.show_area_search = true;
+                }
+                Err(e) => {
+                    self.state.dismiss_overlays();
+                    self.state.show_message(e.context("加载分区列表").to_string(), MessageType::Error);
                 }
-            } else {
-                self.state.filter_areas(""); // 显示所有分区
-                self.state.show_area_search = true;
             }
         }
         Ok(())
     }
 
+    async fn handle_area_id_input(&mut self) -> Result<()> {
+        if self.require_login() {
+            return Ok(());
+        }
+        self.state.show_area_id_input = true;
+        self.state.area_id_input.clear();
+        Ok(())
+    }
+
+    fn handle_auto_stop_input(&mut self) {
+        if self.require_login() {
+            return;
+        }
+        if !self.state.is_live {
+            self.state.show_message("当前未在直播中".to_string(), MessageType::Warning);
+            return;
+        }
+        self.state.show_auto_stop_input = true;
+        self.state.auto_stop_input.clear();
+    }
+
     async fn handle_stop_live(&mut self) -> Result<()> {
+        if self.require_login() {
+            return Ok(());
+        }
         if !self.state.is_live {
             self.state.show_message("当前未在直播中".to_string(), MessageType::Warning);
             return Ok(());
         }
 
+        if !self.state.try_begin_toggle(self.config.toggle_cooldown()) {
+            self.state.show_message("操作过于频繁，请稍后再试".to_string(), MessageType::Warning);
+            return Ok(());
+        }
+
         if let Some(live) = &self.live {
             self.state.show_loading("正在结束直播...".to_string());
             
             match live.stop_live().await {
-                Ok(_) => {
-                    // 更新状态
-                    self.state.set_live_status(false);
-                    self.state.clear_stream_info();
-                    
-                    // 清除配置文件中的推流信息
-                    if let Err(e) = self.config.clear_stream_info() {
-                        eprintln!("清除推流信息失败: {}", e);
-                    }
-                    
+                Ok(outcome) => {
                     self.state.hide_loading();
                     
-                    self.state.show_message("直播已结束".to_string(), MessageType::Success);
+                    // 更新状态
+                    if self.state.apply_stop_outcome(outcome) {
+                        let duration = self.config.stream_saved_at.map(|started| chrono::Utc::now() - started);
+                        // 清除配置文件中的推流信息
+                        if let Err(e) = self.config.clear_stream_info() {
+                            eprintln!("清除推流信息失败: {}", e);
+                        }
+                        
+                        if let Err(e) = self.notifier.notify(StreamEvent::StreamStopped { room_id: live.get_room_id() }).await {
+                            self.state.push_log(MessageType::Warning, e.to_string());
+                        }
+                        self.state.show_message(self.state.session_summary(duration), MessageType::Success);
+                    } else {
+                        self.state.show_message("下播请求已提交，直播间仍在结束中，请稍后再确认状态".to_string(), MessageType::Warning);
+                    }
                 }
                 Err(e) => {
-                    self.state.hide_loading();
-                    self.state.show_message(format!("结束直播失败: {}", e), MessageType::Error);
+                    self.state.dismiss_overlays();
+                    self.state.show_message(e.context("结束直播").to_string(), MessageType::Error);
                 }
             }
         }
@@ -569,14 +1882,20 @@ impl TuiApp {
             self.state.show_loading("正在设置标题...".to_string());
             
             match live.set_title(&self.state.title_input).await {
-                Ok(_) => {
-                    self.state.current_title = self.state.title_input.clone();
+                Ok(update) => {
                     self.state.hide_loading();
-                    self.state.show_message("标题设置成功".to_string(), MessageType::Success);
+                    match update.truncation_notice() {
+                        Some(notice) => {
+                            self.state.push_log(MessageType::Warning, notice.clone());
+                            self.state.show_message(format!("标题设置成功\n{}", notice), MessageType::Warning);
+                        }
+                        None => self.state.show_message("标题设置成功".to_string(), MessageType::Success),
+                    }
+                    self.state.current_title = update.title;
                 }
                 Err(e) => {
-                    self.state.hide_loading();
-                    self.state.show_message(format!("设置标题失败: {}", e), MessageType::Error);
+                    self.state.dismiss_overlays();
+                    self.state.show_message(e.context("设置标题").to_string(), MessageType::Error);
                 }
             }
         }
@@ -589,13 +1908,34 @@ impl TuiApp {
             
             match live.set_area(area_id).await {
                 Ok(_) => {
+                    self.remember_area(area_id);
                     self.initialize_live_info().await;
                     self.state.hide_loading();
                     self.state.show_message("分区设置成功".to_string(), MessageType::Success);
                 }
                 Err(e) => {
+                    self.state.dismiss_overlays();
+                    self.state.show_message(e.context("设置分区").to_string(), MessageType::Error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_area_raw(&mut self, area_id: u32) -> Result<()> {
+        if let Some(live) = &self.live {
+            self.state.show_loading("正在设置分区...".to_string());
+            
+            match live.set_area_raw(area_id).await {
+                Ok((id, name)) => {
+                    self.remember_area(id);
+                    self.state.current_area = name.clone();
                     self.state.hide_loading();
-                    self.state.show_message(format!("设置分区失败: {}", e), MessageType::Error);
+                    self.state.show_message(format!("分区已设置为: {} (ID: {})", name, id), MessageType::Success);
+                }
+                Err(e) => {
+                    self.state.dismiss_overlays();
+                    self.state.show_message(e.context("设置分区").to_string(), MessageType::Error);
                 }
             }
         }
@@ -611,12 +1951,10 @@ impl TuiApp {
             ])
             .split(f.area());
 
+        let (direction, constraints) = main_layout(chunks[0]);
         let main_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(25),  // 菜单宽度
-                Constraint::Percentage(75),  // 信息显示宽度
-            ])
+            .direction(direction)
+            .constraints(constraints)
             .split(chunks[0]);
 
         self.render_menu(f, main_chunks[0]);
@@ -632,6 +1970,30 @@ impl TuiApp {
             self.render_area_search(f);
         }
 
+        if self.state.show_area_id_input {
+            self.render_area_id_input(f);
+        }
+
+        if self.state.show_auto_stop_input {
+            self.render_auto_stop_input(f);
+        }
+
+        if self.state.show_login_prompt {
+            self.render_login_prompt(f);
+        }
+
+        if self.state.face_auth_qr.is_some() {
+            self.render_face_auth(f);
+        }
+
+        if self.state.show_log {
+            self.render_log(f);
+        }
+
+        if self.state.show_danmaku {
+            self.render_danmaku(f);
+        }
+
         if self.state.show_message {
             self.render_message(f);
         }
@@ -669,12 +2031,21 @@ impl TuiApp {
             .highlight_symbol("►");
 
         f.render_stateful_widget(list, area, &mut self.state.menu_state);
+        self.state.menu_area = area;
     }
 
     fn render_info(&self, f: &mut Frame, area: Rect) {
         // 直播信息
         let live_status = if self.state.is_live { "🔴 直播中" } else { "⚫ 未开播" };
-        let mut info_text = vec![
+        let mut info_text = Vec::new();
+        if !self.state.logged_in {
+            info_text.push(Line::from(Span::styled(
+                "⚠️ 未登录，选择「登录」扫码后即可开播",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )));
+            info_text.push(Line::from(""));
+        }
+        info_text.extend([
             Line::from(vec![
                 Span::styled("状态: ", Style::default().fg(Color::Gray)),
                 Span::styled(live_status, if self.state.is_live { 
@@ -693,23 +2064,49 @@ impl TuiApp {
                 Span::styled("分区: ", Style::default().fg(Color::Gray)),
                 Span::styled(&self.state.current_area, Style::default().fg(Color::Green)),
             ]),
-        ];
+        ]);
 
-        // 如果正在直播，显示推流信息
-        if self.state.is_live && !self.state.stream_server.is_empty() {
+        if let Some(status) = self.state.face_auth {
+            let color = match status {
+                FaceAuthStatus::Required => Color::Yellow,
+                _ => Color::Green,
+            };
             info_text.push(Line::from(""));
             info_text.push(Line::from(vec![
-                Span::styled("推流服务器: ", Style::default().fg(Color::Gray)),
-                Span::styled(&self.state.stream_server, Style::default().fg(Color::Cyan)),
+                Span::styled("人脸认证: ", Style::default().fg(Color::Gray)),
+                Span::styled(status.label(), Style::default().fg(color)),
             ]));
+        }
+
+        if let Some(stats) = self.state.live_stats {
+            let value = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_else(|| "—".to_string());
             info_text.push(Line::from(""));
             info_text.push(Line::from(vec![
-                Span::styled("推流码: ", Style::default().fg(Color::Gray)),
-                Span::styled(&self.state.stream_key, Style::default().fg(Color::Cyan)),
+                Span::styled("实时数据: ", Style::default().fg(Color::Gray)),
+                Span::styled(
+                    format!("在线(人气) {} | 看过 {} | 关注 {}", value(stats.online), value(stats.watched), value(stats.followers)),
+                    Style::default().fg(Color::Yellow),
+                ),
             ]));
         }
 
-        let info_widget = Paragraph::new(info_text)
+        // 如果正在直播，显示推流信息
+        if self.state.is_live && !self.state.stream_server.is_empty() {
+            info_text.push(Line::from(""));
+            info_text.push(Line::from(vec![
+                Span::styled("推流服务器: ", Style::default().fg(Color::Gray)),
+                Span::styled(&self.state.stream_server, Style::default().fg(Color::Cyan)),
+            ]));
+            if let Some(key) = self.config.stream_key_visibility.display(&self.state.stream_key) {
+                info_text.push(Line::from(""));
+                info_text.push(Line::from(vec![
+                    Span::styled("推流码: ", Style::default().fg(Color::Gray)),
+                    Span::styled(key, Style::default().fg(Color::Cyan)),
+                ]));
+            }
+        }
+
+        let info_widget = Paragraph::new(info_text)
             .block(Block::default()
                 .title("📊 直播信息")
                 .borders(Borders::ALL)
@@ -720,10 +2117,17 @@ impl TuiApp {
     }
 
     fn render_status(&self, f: &mut Frame, area: Rect) {
-        let status_text = format!("房间号: {} | 用户ID: {}", 
+        let mut status_text = format!("房间号: {} | 用户ID: {}", 
             self.live.as_ref().map(|l| l.get_room_id().to_string()).unwrap_or_else(|| "未知".to_string()),
             self.user_info.as_ref().map(|u| u.uid.to_string()).unwrap_or_else(|| "未知".to_string())
         );
+        if let Some(remaining) = self.state.auto_stop_countdown(Instant::now()) {
+            let secs = remaining.as_secs();
+            status_text.push_str(&format!(" | ⏰ {:02}:{:02}后自动下播", secs / 60, secs % 60));
+        }
+        if let Some(remaining) = self.state.stream_ready_countdown(Instant::now()) {
+            status_text.push_str(&format!(" | ⏳ 推流码{}秒后生效", remaining.as_secs_f64().ceil() as u64));
+        }
 
         let status = Paragraph::new(status_text)
             .style(Style::default().fg(Color::White))
@@ -734,7 +2138,7 @@ impl TuiApp {
     }
 
     fn render_title_input(&self, f: &mut Frame) {
-        let area = centered_rect(70, 30, f.area());
+        let area = centered_rect_clamped(70, 30, 40, 11, 90, 11, f.area());
         
         f.render_widget(Clear, area);
         
@@ -772,8 +2176,147 @@ impl TuiApp {
         f.render_widget(hint, input_chunks[2]);
     }
 
+    fn render_area_id_input(&self, f: &mut Frame) {
+        let area = centered_rect_clamped(50, 30, 36, 9, 70, 9, f.area());
+        
+        f.render_widget(Clear, area);
+        
+        let input_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ])
+            .split(area);
+
+        let title_widget = Paragraph::new("按分区ID设置分区")
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title_widget, input_chunks[0]);
+
+        let input_text = format!("{}█", self.state.area_id_input);
+        let input_widget = Paragraph::new(input_text)
+            .style(Style::default().fg(Color::White).bg(Color::Black))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("输入分区ID")
+                .border_style(Style::default().fg(Color::Cyan)));
+        f.render_widget(input_widget, input_chunks[1]);
+
+        let hint = Paragraph::new("Enter: 确认 | Esc: 取消")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center);
+        f.render_widget(hint, input_chunks[2]);
+    }
+
+    fn render_auto_stop_input(&self, f: &mut Frame) {
+        let area = centered_rect_clamped(50, 30, 36, 9, 70, 9, f.area());
+        
+        f.render_widget(Clear, area);
+        
+        let input_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ])
+            .split(area);
+
+        let title_widget = Paragraph::new("定时下播")
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title_widget, input_chunks[0]);
+
+        let input_text = format!("{}█", self.state.auto_stop_input);
+        let input_widget = Paragraph::new(input_text)
+            .style(Style::default().fg(Color::White).bg(Color::Black))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("多少分钟后下播")
+                .border_style(Style::default().fg(Color::Cyan)));
+        f.render_widget(input_widget, input_chunks[1]);
+
+        let hint = Paragraph::new("Enter: 确认 | Esc: 取消")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center);
+        f.render_widget(hint, input_chunks[2]);
+    }
+
+    fn render_face_auth(&self, f: &mut Frame) {
+        let Some(qr) = &self.state.face_auth_qr else {
+            return;
+        };
+        let area = f.area();
+        f.render_widget(Clear, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        let title_widget = Paragraph::new("开播需要人脸认证，请使用B站App扫码完成认证")
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title_widget, chunks[0]);
+
+        let lines = if qr.is_empty() {
+            vec![Line::from("未返回认证二维码，请在B站App中打开直播中心完成认证")]
+        } else {
+            QRCode::render_to_lines_within(qr, chunks[1].width, chunks[1].height)
+                .unwrap_or_else(|e| vec![Line::from(e.to_string())])
+        };
+        f.render_widget(Paragraph::new(lines).alignment(Alignment::Center), chunks[1]);
+
+        let hint = Paragraph::new("认证完成后将自动重新开播 | Esc: 取消")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center);
+        f.render_widget(hint, chunks[2]);
+    }
+
+    fn render_login_prompt(&self, f: &mut Frame) {
+        let area = centered_rect_clamped(50, 30, 40, 8, 70, 12, f.area());
+        
+        f.render_widget(Clear, area);
+        
+        let prompt_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        let title_widget = Paragraph::new("⚠️ 未登录")
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title_widget, prompt_chunks[0]);
+
+        let content_widget = Paragraph::new("该操作需要先登录B站账号\n将暂时离开界面显示登录二维码")
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(content_widget, prompt_chunks[1]);
+
+        let hint = Paragraph::new("Enter: 扫码登录 | Esc: 取消")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center);
+        f.render_widget(hint, prompt_chunks[2]);
+    }
+
     fn render_area_search(&mut self, f: &mut Frame) {
-        let area = centered_rect(80, 70, f.area());
+        let area = centered_rect_clamped(80, 70, 50, 16, 120, 40, f.area());
         
         f.render_widget(Clear, area);
         
@@ -798,7 +2341,7 @@ impl TuiApp {
         let search_query = self.state.area_search_query.clone();
         let search_widget = Paragraph::new(search_query.as_str())
             .style(Style::default().fg(Color::White))
-            .block(Block::default().borders(Borders::ALL).title("搜索分区 (输入关键词)"));
+            .block(Block::default().borders(Borders::ALL).title("搜索分区 (中文或拼音，空格分隔多个关键词)"));
         f.render_widget(search_widget, search_chunks[1]);
 
         // 分区列表
@@ -806,29 +2349,46 @@ impl TuiApp {
         let items: Vec<ListItem> = filtered_areas
             .iter()
             .map(|area| {
-                ListItem::new(format!("  {} - {}", area.parent_name, area.name))
+                if area.is_locked() {
+                    ListItem::new(format!("🔒 {} - {}", area.parent_name, area.name))
+                        .style(Style::default().fg(Color::DarkGray))
+                } else {
+                    ListItem::new(format!("  {} - {}", area.parent_name, area.name))
+                }
             })
             .collect();
 
-        let list = List::new(items)
-            .block(Block::default()
-                .title("分区列表")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Blue)))
-            .highlight_style(Style::default().bg(Color::Blue).fg(Color::White))
-            .highlight_symbol("►");
-
-        f.render_stateful_widget(list, search_chunks[2], &mut self.state.area_state);
+        if items.is_empty() {
+            let placeholder = Paragraph::new("无匹配结果")
+                .style(Style::default().fg(Color::DarkGray))
+                .alignment(Alignment::Center)
+                .block(Block::default()
+                    .title("分区列表")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Blue)));
+            f.render_widget(placeholder, search_chunks[2]);
+        } else {
+            let list = List::new(items)
+                .block(Block::default()
+                    .title("分区列表")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Blue)))
+                .highlight_style(Style::default().bg(Color::Blue).fg(Color::White))
+                .highlight_symbol("►");
+
+            f.render_stateful_widget(list, search_chunks[2], &mut self.state.area_state);
+        }
+        self.state.area_list_area = search_chunks[2];
 
         // 提示
-        let hint = Paragraph::new("↑/↓: 选择 | Enter: 确认 | Esc: 取消")
+        let hint = Paragraph::new("↑/↓/滚轮: 选择 | Enter/双击: 确认 | Esc: 取消")
             .style(Style::default().fg(Color::Gray))
             .alignment(Alignment::Center);
         f.render_widget(hint, search_chunks[3]);
     }
 
     fn render_message(&self, f: &mut Frame) {
-        let area = centered_rect(60, 30, f.area());
+        let area = centered_rect_clamped(60, 30, MESSAGE_MIN_SIZE.0, MESSAGE_MIN_SIZE.1, MESSAGE_MAX_SIZE.0, MESSAGE_MAX_SIZE.1, f.area());
         
         f.render_widget(Clear, area);
         
@@ -870,8 +2430,94 @@ impl TuiApp {
         f.render_widget(hint, message_chunks[2]);
     }
 
+    fn render_log(&self, f: &mut Frame) {
+        let area = centered_rect_clamped(80, 80, 50, 12, 120, 40, f.area());
+        
+        f.render_widget(Clear, area);
+        
+        let log_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        let height = log_chunks[0].height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self.state.visible_logs(height)
+            .map(|entry| {
+                let (label, color) = match entry.level {
+                    MessageType::Info => ("信息", Color::Blue),
+                    MessageType::Success => ("成功", Color::Green),
+                    MessageType::Warning => ("警告", Color::Yellow),
+                    MessageType::Error => ("错误", Color::Red),
+                };
+                Line::from(vec![
+                    Span::styled(entry.time.format("%H:%M:%S ").to_string(), Style::default().fg(Color::Gray)),
+                    Span::styled(format!("[{}] ", label), Style::default().fg(color)),
+                    // 多行消息在面板中合并为一行
+                    Span::raw(entry.text.replace('\n', " ")),
+                ])
+            })
+            .collect();
+
+        let title = if self.state.log_scroll > 0 {
+            format!("📜 日志 ({}/{}，已向上翻{}条)", self.state.logs.len() - self.state.log_scroll, self.state.logs.len(), self.state.log_scroll)
+        } else {
+            format!("📜 日志 ({})", self.state.logs.len())
+        };
+        let log_widget = Paragraph::new(lines)
+            .block(Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Blue)));
+        f.render_widget(log_widget, log_chunks[0]);
+
+        let hint = Paragraph::new("↑/↓: 滚动 | l/Esc: 关闭")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center);
+        f.render_widget(hint, log_chunks[1]);
+    }
+
+    fn render_danmaku(&self, f: &mut Frame) {
+        let area = centered_rect_clamped(80, 80, 50, 12, 120, 40, f.area());
+        
+        f.render_widget(Clear, area);
+        
+        let danmaku_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        let height = danmaku_chunks[0].height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self.danmaku.visible(height)
+            .map(|message| Line::from(message.as_str()))
+            .collect();
+
+        // 冻结时在标题中提示，有新消息时高亮
+        let (title, color) = match (self.danmaku.is_auto_scroll(), self.danmaku.unread()) {
+            (true, _) => (format!("💬 弹幕 ({})", self.danmaku.len()), Color::Blue),
+            (false, 0) => (format!("💬 弹幕 ({}，已冻结)", self.danmaku.len()), Color::Gray),
+            (false, unread) => (format!("💬 弹幕 ({}，已冻结，{}条新消息)", self.danmaku.len(), unread), Color::Yellow),
+        };
+        let danmaku_widget = Paragraph::new(lines)
+            .block(Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(color)));
+        f.render_widget(danmaku_widget, danmaku_chunks[0]);
+
+        let hint = Paragraph::new("f/空格: 冻结/自动滚动 | d/Esc: 关闭")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center);
+        f.render_widget(hint, danmaku_chunks[1]);
+    }
+
     fn render_loading(&self, f: &mut Frame) {
-        let area = centered_rect(50, 20, f.area());
+        let area = centered_rect_clamped(50, 20, 30, 7, 60, 7, f.area());
         
         f.render_widget(Clear, area);
         
@@ -907,7 +2553,7 @@ impl TuiApp {
     }
 
     fn render_help(&self, f: &mut Frame) {
-        let area = centered_rect(70, 80, f.area());
+        let area = centered_rect_clamped(70, 80, 50, 20, 90, 40, f.area());
         
         f.render_widget(Clear, area);
         
@@ -934,12 +2580,20 @@ impl TuiApp {
             Line::from("  ↑/↓  - 选择菜单项"),
             Line::from("  Enter - 确认选择"),
             Line::from("  Esc/q - 退出程序"),
+            Line::from("  Esc×2 - 强制关闭所有弹窗"),
+            Line::from("  c     - 复制推流地址和推流码"),
+            Line::from("  l     - 打开/关闭日志面板"),
+            Line::from("  d     - 打开/关闭弹幕面板，面板中按f冻结/恢复自动滚动"),
             Line::from(""),
             Line::from("📋 菜单说明:"),
             Line::from(""),
+            Line::from("  • 登录 - 未登录时扫码登录B站账号"),
             Line::from("  • 开始直播 - 开启直播，获取推流码"),
+            Line::from("  • 使用上次设置开播 - 恢复上次直播的标题和分区后开播"),
             Line::from("  • 修改标题 - 修改当前直播间标题"),
             Line::from("  • 修改分区 - 修改当前直播间分区"),
+            Line::from("  • 输入分区ID - 直接按分区ID设置分区"),
+            Line::from("  • 定时下播 - 直播中设置多少分钟后自动下播，再次选择可取消"),
             Line::from("  • 结束直播 - 结束当前直播"),
             Line::from("  • 帮助 - 显示此帮助信息"),
             Line::from("  • 退出程序 - 关闭应用程序"),
@@ -959,22 +2613,545 @@ impl TuiApp {
     }
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
-
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
-} 
\ No newline at end of file
+/// 在r中居中放置占其百分比的弹窗，宽高再限制在[min, max]之间
+///
+/// 限制后的尺寸不会超过r本身，终端很小时弹窗铺满整个区域。
+fn centered_rect_clamped(percent_x: u16, percent_y: u16, min_w: u16, min_h: u16, max_w: u16, max_h: u16, r: Rect) -> Rect {
+    let scale = |total: u16, percent: u16| (u32::from(total) * u32::from(percent) / 100) as u16;
+    let width = scale(r.width, percent_x).clamp(min_w, max_w).min(r.width);
+    let height = scale(r.height, percent_y).clamp(min_h, max_h).min(r.height);
+    Rect::new(
+        r.x + (r.width - width) / 2,
+        r.y + (r.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// 根据终端尺寸计算主界面的排列方向和菜单/信息面板的约束
+fn main_layout(area: Rect) -> (Direction, [Constraint; 2]) {
+    if area.width < NARROW_WIDTH {
+        return (
+            Direction::Vertical,
+            [Constraint::Length(STACKED_MENU_HEIGHT), Constraint::Min(0)],
+        );
+    }
+
+    let menu_width = (area.width / 4).clamp(MENU_MIN_WIDTH, MENU_MAX_WIDTH);
+    (
+        Direction::Horizontal,
+        [Constraint::Length(menu_width), Constraint::Min(0)],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StreamKeyVisibility;
+    use crate::test_support::area_json;
+
+    #[derive(Default)]
+    struct RecordingOps {
+        calls: Vec<&'static str>,
+    }
+
+    impl TerminalOps for RecordingOps {
+        fn enable_raw_mode(&mut self) -> io::Result<()> {
+            self.calls.push("enable_raw_mode");
+            Ok(())
+        }
+
+        fn disable_raw_mode(&mut self) -> io::Result<()> {
+            self.calls.push("disable_raw_mode");
+            Ok(())
+        }
+
+        fn enter_alternate_screen(&mut self) -> io::Result<()> {
+            self.calls.push("enter_alternate_screen");
+            Ok(())
+        }
+
+        fn leave_alternate_screen(&mut self) -> io::Result<()> {
+            self.calls.push("leave_alternate_screen");
+            Ok(())
+        }
+
+        fn enable_mouse_capture(&mut self) -> io::Result<()> {
+            self.calls.push("enable_mouse_capture");
+            Ok(())
+        }
+
+        fn disable_mouse_capture(&mut self) -> io::Result<()> {
+            self.calls.push("disable_mouse_capture");
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_log_keeps_latest_entries_and_scrolls() {
+        let mut state = AppState::default();
+        state.show_message("开播成功".to_string(), MessageType::Success);
+        state.hide_message();
+        assert_eq!(state.logs.len(), 1);
+        assert_eq!(state.logs[0].level, MessageType::Success);
+
+        for i in 0..LOG_CAPACITY {
+            state.push_log(MessageType::Info, format!("第{}条", i));
+        }
+        assert_eq!(state.logs.len(), LOG_CAPACITY);
+        assert_eq!(state.logs[0].text, "第0条");
+
+        let texts = |state: &AppState| state.visible_logs(2).map(|e| e.text.clone()).collect::<Vec<_>>();
+        assert_eq!(texts(&state), vec![format!("第{}条", LOG_CAPACITY - 2), format!("第{}条", LOG_CAPACITY - 1)]);
+
+        // 向上翻看时新日志不会挪动视图
+        state.toggle_log();
+        state.scroll_log_up();
+        let before = texts(&state);
+        state.push_log(MessageType::Error, "推流中断".to_string());
+        assert_eq!(texts(&state), before);
+
+        state.scroll_log_down();
+        state.scroll_log_down();
+        assert_eq!(state.log_scroll, 0);
+        assert_eq!(texts(&state).last().map(String::as_str), Some("推流中断"));
+    }
+
+    #[test]
+    fn test_stream_ready_countdown_and_due() {
+        let mut state = AppState::default();
+        state.set_live_status(true);
+        let now = Instant::now();
+        state.stream_ready_at = Some(now + Duration::from_secs(5));
+        assert_eq!(state.stream_ready_countdown(now), Some(Duration::from_secs(5)));
+        assert!(state.is_animating());
+        assert!(!state.take_stream_ready_due(now));
+        assert!(state.take_stream_ready_due(now + Duration::from_secs(5)));
+        assert!(state.stream_ready_countdown(now).is_none());
+
+        // 下播后倒计时随之清除
+        state.stream_ready_at = Some(now + Duration::from_secs(5));
+        state.set_live_status(false);
+        assert!(state.stream_ready_at.is_none());
+    }
+
+    #[test]
+    fn test_auto_stop_countdown_and_due() {
+        let mut state = AppState::default();
+        state.set_live_status(true);
+        let now = Instant::now();
+        state.set_auto_stop(Duration::from_secs(30 * 60), now);
+        assert_eq!(state.menu_items[AUTO_STOP_MENU_INDEX], "取消定时下播");
+
+        // 最后几分钟才显示倒计时
+        assert!(state.auto_stop_countdown(now).is_none());
+        let near = now + Duration::from_secs(26 * 60);
+        assert_eq!(state.auto_stop_countdown(near), Some(Duration::from_secs(4 * 60)));
+        assert!(!state.take_auto_stop_due(near));
+
+        assert!(state.take_auto_stop_due(now + Duration::from_secs(30 * 60)));
+        assert!(state.auto_stop_at.is_none());
+        assert_eq!(state.menu_items[AUTO_STOP_MENU_INDEX], "定时下播");
+
+        // 手动下播后定时随之取消
+        state.set_auto_stop(Duration::from_secs(60), now);
+        state.set_live_status(false);
+        assert!(state.auto_stop_at.is_none());
+    }
+
+    #[test]
+    fn test_face_auth_poll_due() {
+        let mut state = AppState::default();
+        assert!(!state.take_face_auth_poll_due(Instant::now()));
+
+        state.show_face_auth("https://www.bilibili.com/h5/face".to_string());
+        assert!(state.has_overlay());
+        assert_eq!(state.face_auth, Some(FaceAuthStatus::Required));
+        let now = Instant::now();
+        assert!(!state.take_face_auth_poll_due(now));
+        let later = now + live::FACE_AUTH_POLL_INTERVAL;
+        assert!(state.take_face_auth_poll_due(later));
+        assert!(!state.take_face_auth_poll_due(later));
+
+        state.dismiss_overlays();
+        assert!(state.face_auth_qr.is_none());
+        assert!(!state.take_face_auth_poll_due(later + live::FACE_AUTH_POLL_INTERVAL));
+    }
+
+    #[test]
+    fn test_dismiss_overlays() {
+        let mut state = AppState::default();
+        state.show_loading("加载中".to_string());
+        state.show_message("提示".to_string(), MessageType::Info);
+        state.show_help();
+        state.show_title_input = true;
+        state.title_input = "新标题".to_string();
+        state.show_area_search = true;
+        state.area_search_query = "游戏".to_string();
+        assert!(state.has_overlay());
+
+        state.dismiss_overlays();
+
+        assert!(!state.has_overlay());
+        assert!(state.title_input.is_empty());
+        assert!(state.area_search_query.is_empty());
+        assert!(state.message.is_empty());
+        assert!(state.loading_message.is_empty());
+    }
+
+    #[test]
+    fn test_danmaku_feed_drops_oldest_at_capacity() {
+        let mut feed = DanmakuFeed::new(3);
+        for i in 0..5 {
+            feed.push(format!("msg{}", i));
+        }
+
+        assert_eq!(feed.len(), 3);
+        let visible: Vec<_> = feed.visible(10).cloned().collect();
+        assert_eq!(visible, ["msg2", "msg3", "msg4"]);
+    }
+
+    #[test]
+    fn test_danmaku_feed_freeze_keeps_view() {
+        let mut feed = DanmakuFeed::new(4);
+        for i in 0..3 {
+            feed.push(format!("msg{}", i));
+        }
+        assert_eq!(feed.visible(2).cloned().collect::<Vec<_>>(), ["msg1", "msg2"]);
+
+        // 冻结后新消息不移动视图，只累计未读数
+        assert!(!feed.toggle_auto_scroll());
+        feed.push("msg3".to_string());
+        feed.push("msg4".to_string());
+        assert_eq!(feed.unread(), 2);
+        assert_eq!(feed.visible(2).cloned().collect::<Vec<_>>(), ["msg1", "msg2"]);
+
+        // 缓冲区满后丢弃最旧消息，冻结的视图最多退到缓冲区开头
+        for i in 5..10 {
+            feed.push(format!("msg{}", i));
+        }
+        assert_eq!(feed.unread(), 4);
+        assert_eq!(feed.visible(2).cloned().collect::<Vec<_>>(), ["msg6", "msg7"]);
+
+        // 恢复自动滚动后回到最新消息
+        assert!(feed.toggle_auto_scroll());
+        assert_eq!(feed.unread(), 0);
+        assert_eq!(feed.visible(2).cloned().collect::<Vec<_>>(), ["msg8", "msg9"]);
+    }
+
+    #[tokio::test]
+    async fn test_danmaku_panel_freeze_indicator() {
+        let mut app = TuiApp::new(Config::default());
+        let (tx, rx) = mpsc::channel(8);
+        app.danmaku_rx = Some(rx);
+        let render = |app: &mut TuiApp| {
+            let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(100, 20)).unwrap();
+            terminal.draw(|f| app.ui(f)).unwrap();
+            terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect::<String>().replace(' ', "")
+        };
+
+        tx.send(DanmakuMessage::Danmaku { uid: 1, uname: "观众".to_string(), text: "你好".to_string() }).await.unwrap();
+        tx.send(DanmakuMessage::Popularity(99)).await.unwrap();
+        assert!(app.drain_danmaku());
+        assert_eq!(app.danmaku.len(), 1);
+
+        app.handle_key(KeyCode::Char('d')).await.unwrap();
+        assert!(app.state.show_danmaku);
+        let screen = render(&mut app);
+        assert!(screen.contains("观众:你好"));
+        assert!(!screen.contains("已冻结"));
+
+        // 冻结后新消息只在标题中提示
+        app.handle_key(KeyCode::Char('f')).await.unwrap();
+        assert!(!app.danmaku.is_auto_scroll());
+        tx.send(DanmakuMessage::Gift { uid: 2, uname: "老板".to_string(), gift_name: "小心心".to_string(), num: 3 }).await.unwrap();
+        assert!(app.drain_danmaku());
+        let screen = render(&mut app);
+        assert!(screen.contains("已冻结，1条新消息"));
+        assert!(!screen.contains("小心心"));
+
+        app.handle_key(KeyCode::Char('f')).await.unwrap();
+        assert!(render(&mut app).contains("老板赠送小心心×3"));
+
+        app.handle_key(KeyCode::Esc).await.unwrap();
+        assert!(!app.state.show_danmaku);
+    }
+
+    #[tokio::test]
+    async fn test_default_notifier_writes_log_panel() {
+        let mut app = TuiApp::new(Config::default());
+        app.notifier.notify(StreamEvent::StreamStarted { room_id: 123 }).await.unwrap();
+
+        let (level, text) = app.log_rx.try_recv().unwrap();
+        assert_eq!(level, MessageType::Info);
+        assert_eq!(text, "[通知] 直播间 123 已开播");
+    }
+
+    #[tokio::test]
+    async fn test_start_live_without_session_prompts_login() {
+        let mut app = TuiApp::new(Config::default());
+        assert_eq!(app.state.menu_items[0], "登录");
+
+        app.handle_start_live().await.unwrap();
+        assert!(app.state.show_login_prompt);
+        assert!(!app.state.show_loading);
+
+        app.handle_key(KeyCode::Enter).await.unwrap();
+        assert!(!app.state.show_login_prompt);
+        assert!(app.state.login_requested);
+    }
+
+    #[tokio::test]
+    async fn test_prefetched_area_list_skips_fetch() {
+        let areas = vec![crate::live::AreaCategory {
+            id: 2,
+            name: "网游".to_string(),
+            list: vec![],
+        }];
+        let mut app = TuiApp::new(Config::default()).with_area_list(areas);
+        let calls = std::cell::Cell::new(0);
+
+        app.state.ensure_area_list(|| async {
+            calls.set(calls.get() + 1);
+            Ok(Vec::new())
+        }).await.unwrap();
+
+        assert_eq!(calls.get(), 0);
+        assert_eq!(app.state.area_list.len(), 1);
+        assert_eq!(app.state.area_list[0].name, "网游");
+    }
+
+    #[test]
+    fn test_main_layout_wide_and_narrow() {
+        let (direction, constraints) = main_layout(Rect::new(0, 0, 200, 40));
+        assert_eq!(direction, Direction::Horizontal);
+        assert_eq!(constraints, [Constraint::Length(MENU_MAX_WIDTH), Constraint::Min(0)]);
+
+        let (direction, constraints) = main_layout(Rect::new(0, 0, 80, 24));
+        assert_eq!(direction, Direction::Horizontal);
+        assert_eq!(constraints, [Constraint::Length(MENU_MIN_WIDTH), Constraint::Min(0)]);
+
+        let (direction, constraints) = main_layout(Rect::new(0, 0, 40, 24));
+        assert_eq!(direction, Direction::Vertical);
+        assert_eq!(constraints, [Constraint::Length(STACKED_MENU_HEIGHT), Constraint::Min(0)]);
+    }
+
+    fn render_info_text(visibility: StreamKeyVisibility) -> String {
+        let config = Config {
+            stream_key_visibility: visibility,
+            ..Config::default()
+        };
+        let mut app = TuiApp::new(config);
+        app.state.set_live_status(true);
+        app.state.set_stream_info("rtmp://live-push.bilivideo.com/live-bvc/".to_string(), StreamKey::new("?streamname=live_1&key=secret"));
+
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(120, 20)).unwrap();
+        terminal.draw(|f| app.render_info(f, f.area())).unwrap();
+        terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn test_stream_key_visibility_modes() {
+        let always = render_info_text(StreamKeyVisibility::Always);
+        assert!(always.contains("?streamname=live_1&key=secret"));
+
+        let masked = render_info_text(StreamKeyVisibility::Masked);
+        assert!(masked.contains("live_***"));
+        assert!(!masked.contains("key=secret"));
+
+        let hidden = render_info_text(StreamKeyVisibility::Hidden);
+        assert!(!hidden.contains("key=secret"));
+        assert!(!hidden.contains("live_***"));
+        assert!(!hidden.contains("推流码"));
+        assert!(hidden.contains("rtmp://live-push.bilivideo.com/live-bvc/"));
+    }
+
+    #[test]
+    fn test_idle_ticks_do_not_redraw() {
+        let mut state = AppState::default();
+        let mut redraw = RedrawScheduler::new();
+        assert!(redraw.take());
+
+        for _ in 0..5 {
+            redraw.on_tick(state.is_animating());
+            assert!(!redraw.take());
+        }
+
+        state.show_loading("加载中".to_string());
+        redraw.on_tick(state.is_animating());
+        assert!(redraw.take());
+
+        // 多次请求合并为一次重绘
+        redraw.request();
+        redraw.request();
+        assert!(redraw.take());
+        assert!(!redraw.take());
+    }
+
+    #[test]
+    fn test_pending_stop_keeps_live_state() {
+        let mut state = AppState::default();
+        state.set_live_status(true);
+        state.set_stream_info("rtmp://live-push.bilivideo.com/live-bvc/".to_string(), StreamKey::new("?streamname=live_1"));
+
+        assert!(!state.apply_stop_outcome(StopOutcome::Pending));
+        assert!(state.is_live);
+        assert!(!state.stream_key.is_empty());
+
+        assert!(state.apply_stop_outcome(StopOutcome::Stopped));
+        assert!(!state.is_live);
+        assert!(state.stream_key.is_empty());
+    }
+
+    #[test]
+    fn test_session_peaks_only_rise() {
+        let mut peaks = SessionPeaks::default();
+        peaks.record(Some(120), None);
+        assert_eq!(peaks, SessionPeaks { online: Some(120), watched: None });
+
+        peaks.record(Some(80), Some(300));
+        assert_eq!(peaks, SessionPeaks { online: Some(120), watched: Some(300) });
+
+        peaks.record(Some(150), None);
+        assert_eq!(peaks, SessionPeaks { online: Some(150), watched: Some(300) });
+    }
+
+    #[test]
+    fn test_session_summary_without_stats() {
+        let mut state = AppState {
+            current_title: "测试直播".to_string(),
+            current_area: "单机游戏".to_string(),
+            ..Default::default()
+        };
+
+        let summary = state.session_summary(None);
+        assert!(summary.contains("时长: —"));
+        assert!(summary.contains("最高在线: —"));
+        assert!(summary.contains("标题: 测试直播"));
+
+        state.peaks.record(Some(42), Some(1000));
+        let summary = state.session_summary(Some(chrono::Duration::seconds(3 * 3600 + 5 * 60)));
+        assert!(summary.contains("时长: 3小时05分"));
+        assert!(summary.contains("最高在线: 42"));
+        assert!(summary.contains("累计观看: 1000"));
+    }
+
+    #[test]
+    fn test_apply_stats_keeps_previous_values() {
+        let mut state = AppState::default();
+        state.apply_stats(LiveStats { online: Some(10), watched: Some(20), followers: Some(30) });
+        // 未开播时只更新展示数据，不计入峰值
+        assert_eq!(state.peaks, SessionPeaks::default());
+
+        state.set_live_status(true);
+        state.apply_stats(LiveStats { online: Some(15), ..Default::default() });
+        assert_eq!(state.live_stats, Some(LiveStats { online: Some(15), watched: Some(20), followers: Some(30) }));
+        assert_eq!(state.peaks.online, Some(15));
+    }
+
+    #[test]
+    fn test_centered_rect_clamped_bounds() {
+        // 小终端上至少保持最小尺寸，但不超出终端
+        let tiny = Rect::new(0, 0, 50, 10);
+        let popup = centered_rect_clamped(60, 30, 40, 7, 80, 20, tiny);
+        assert_eq!((popup.width, popup.height), (40, 7));
+        assert_eq!((popup.x, popup.y), (5, 1));
+        let popup = centered_rect_clamped(60, 30, 40, 7, 80, 20, Rect::new(0, 0, 30, 5));
+        assert_eq!((popup.width, popup.height), (30, 5));
+
+        // 大终端上不超过最大尺寸并保持居中
+        let huge = Rect::new(10, 5, 400, 120);
+        let popup = centered_rect_clamped(60, 30, 40, 7, 80, 20, huge);
+        assert_eq!((popup.width, popup.height), (80, 20));
+        assert_eq!((popup.x, popup.y), (170, 55));
+    }
+
+    #[test]
+    fn test_toggle_cooldown() {
+        let mut state = AppState::default();
+        assert!(state.try_begin_toggle(Duration::from_secs(3)));
+        assert!(!state.try_begin_toggle(Duration::from_secs(3)));
+
+        let mut state = AppState::default();
+        assert!(state.try_begin_toggle(Duration::ZERO));
+        assert!(state.try_begin_toggle(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_inline_mode_skips_alternate_screen() {
+        let mut ops = RecordingOps::default();
+        setup_terminal(&mut ops, false).unwrap();
+        restore_terminal(&mut ops, false).unwrap();
+        assert_eq!(ops.calls, vec!["enable_raw_mode", "enable_mouse_capture", "disable_mouse_capture", "disable_raw_mode"]);
+
+        let mut ops = RecordingOps::default();
+        setup_terminal(&mut ops, true).unwrap();
+        restore_terminal(&mut ops, true).unwrap();
+        assert_eq!(ops.calls, vec![
+            "enable_raw_mode",
+            "enter_alternate_screen",
+            "enable_mouse_capture",
+            "disable_mouse_capture",
+            "disable_raw_mode",
+            "leave_alternate_screen",
+        ]);
+    }
+
+    #[test]
+    fn test_filter_areas_and_empty_placeholder() {
+        let areas: Vec<crate::live::AreaCategory> = serde_json::from_value(serde_json::json!([
+            {"id": 2, "name": "网游", "list": [
+                area_json(86, "英雄联盟", 2, "网游", "yingxionglianmeng"),
+            ]},
+            {"id": 3, "name": "手游", "list": [
+                area_json(321, "原神", 3, "手游", "yuanshen"),
+            ]},
+        ])).unwrap();
+        let mut app = TuiApp::new(Config::default()).with_area_list(areas);
+        let names = |state: &AppState| state.filtered_areas.iter().map(|area| area.name.clone()).collect::<Vec<_>>();
+
+        app.state.filter_areas("ys");
+        assert_eq!(names(&app.state), vec!["原神"]);
+        app.state.filter_areas("YS");
+        assert_eq!(names(&app.state), vec!["原神"]);
+        assert_eq!(app.state.area_state.selected(), Some(0));
+        app.state.filter_areas("网游 yxlm");
+        assert_eq!(names(&app.state), vec!["英雄联盟"]);
+
+        app.state.filter_areas("网游 ys");
+        assert!(app.state.filtered_areas.is_empty());
+        assert_eq!(app.state.area_state.selected(), None);
+
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|f| app.render_area_search(f)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.replace(' ', "").contains("无匹配结果"));
+    }
+
+    #[test]
+    fn test_mouse_click_maps_to_list_items() {
+        // 带边框的列表：第一行和最后一行是边框
+        let area = Rect::new(2, 3, 20, 6);
+        assert_eq!(list_index_at(area, 0, 10, 5, 3), None);
+        assert_eq!(list_index_at(area, 0, 10, 5, 4), Some(0));
+        assert_eq!(list_index_at(area, 2, 10, 5, 7), Some(5));
+        assert_eq!(list_index_at(area, 0, 10, 5, 8), None);
+        assert_eq!(list_index_at(area, 0, 10, 2, 4), None);
+        // 列表项不足时点击空白处
+        assert_eq!(list_index_at(area, 0, 2, 5, 6), None);
+        assert_eq!(list_index_at(Rect::default(), 0, 10, 0, 0), None);
+
+        let mut state = AppState::default();
+        state.select_menu(1);
+        assert_eq!(state.selected_menu, 1);
+        state.select_menu(usize::MAX);
+        assert_eq!(state.selected_menu, 1);
+
+        let now = Instant::now();
+        assert!(!state.register_click(1, now));
+        assert!(state.register_click(1, now + Duration::from_millis(100)));
+        assert!(!state.register_click(1, now + Duration::from_millis(200)));
+        assert!(!state.register_click(2, now + Duration::from_millis(300)));
+        assert!(!state.register_click(2, now + Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,57 @@
+use serde_json::Value;
+
+/// 按 `a.b.c` 形式的路径逐层取出嵌套的JSON字段，任一层缺失时返回None
+///
+/// 路径段为数字时同时支持按数组下标取值，如 `list.0.name`。
+pub fn json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |current, key| match current {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => None,
+    })
+}
+
+/// 取出路径上的整数，字段缺失或类型不是整数时返回None
+pub fn json_i64(value: &Value, path: &str) -> Option<i64> {
+    json_path(value, path).and_then(Value::as_i64)
+}
+
+/// 取出路径上的非负整数，字段缺失或类型不符时返回None
+pub fn json_u64(value: &Value, path: &str) -> Option<u64> {
+    json_path(value, path).and_then(Value::as_u64)
+}
+
+/// 取出路径上的字符串，字段缺失或类型不是字符串时返回None
+pub fn json_str<'a>(value: &'a Value, path: &str) -> Option<&'a str> {
+    json_path(value, path).and_then(Value::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_path_over_nested_values() {
+        let value = json!({
+            "room_info": {"live_status": 1, "title": "测试直播", "online": -1},
+            "list": [{"name": "first"}],
+        });
+
+        assert_eq!(json_i64(&value, "room_info.live_status"), Some(1));
+        assert_eq!(json_str(&value, "room_info.title"), Some("测试直播"));
+        assert_eq!(json_str(&value, "list.0.name"), Some("first"));
+        assert!(json_path(&value, "room_info").is_some_and(Value::is_object));
+
+        // 缺少字段
+        assert_eq!(json_path(&value, "room_info.area_id"), None);
+        assert_eq!(json_path(&value, "missing.live_status"), None);
+        assert_eq!(json_str(&value, "list.1.name"), None);
+
+        // 类型不符
+        assert_eq!(json_i64(&value, "room_info.title"), None);
+        assert_eq!(json_str(&value, "room_info.live_status"), None);
+        assert_eq!(json_u64(&value, "room_info.online"), None);
+        assert_eq!(json_path(&value, "room_info.title.length"), None);
+    }
+}